@@ -10,11 +10,23 @@ if [ ! -f $DATABASE_URL ]; then
     echo "Creating database at $DATABASE_URL"
     sqlite3 $DATABASE_URL << 'SQL'
 CREATE TABLE IF NOT EXISTS timed_mute (
+    rowid INTEGER PRIMARY KEY,
     actor VARCHAR NOT NULL,
     muted_actor VARCHAR NOT NULL,
     created_date BIGINT NOT NULL,
     expiration_date BIGINT NOT NULL,
-    status INTEGER NOT NULL
+    status INTEGER NOT NULL,
+    original_expiration_date BIGINT,
+    extended_date BIGINT,
+    drift_date BIGINT,
+    expired_date BIGINT,
+    cancelled_date BIGINT,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    next_attempt_date BIGINT,
+    was_muted_before BOOLEAN NOT NULL DEFAULT 0,
+    group_id INTEGER,
+    start_date BIGINT
 );
 CREATE TABLE IF NOT EXISTS profile (
     did VARCHAR NOT NULL,
@@ -23,12 +35,120 @@ CREATE TABLE IF NOT EXISTS profile (
     status INTEGER NOT NULL
 );
 CREATE TABLE IF NOT EXISTS timed_mute_word (
+    rowid INTEGER PRIMARY KEY,
     actor VARCHAR NOT NULL,
     muted_word VARCHAR NOT NULL,
     created_date BIGINT NOT NULL,
     expiration_date BIGINT NOT NULL,
-    status INTEGER NOT NULL
+    status INTEGER NOT NULL,
+    original_expiration_date BIGINT,
+    extended_date BIGINT,
+    targets VARCHAR NOT NULL DEFAULT 'tag,content',
+    actor_target VARCHAR NOT NULL DEFAULT 'all',
+    drift_date BIGINT,
+    expired_date BIGINT,
+    cancelled_date BIGINT,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    next_attempt_date BIGINT,
+    group_id INTEGER,
+    start_date BIGINT
+);
+CREATE TABLE IF NOT EXISTS profile_session (
+    access_jwt VARCHAR NOT NULL,
+    refresh_jwt VARCHAR NOT NULL,
+    did VARCHAR NOT NULL,
+    active BOOLEAN NOT NULL,
+    status VARCHAR
+);
+CREATE UNIQUE INDEX IF NOT EXISTS profile_session_did ON profile_session (did);
+CREATE UNIQUE INDEX IF NOT EXISTS timed_mute_unresolved_target ON timed_mute (actor, muted_actor) WHERE status IN (0, 5);
+CREATE UNIQUE INDEX IF NOT EXISTS timed_mute_word_unresolved_target ON timed_mute_word (actor, muted_word COLLATE NOCASE) WHERE status IN (0, 5);
+CREATE INDEX IF NOT EXISTS timed_mute_actor_history ON timed_mute (actor, rowid);
+CREATE INDEX IF NOT EXISTS timed_mute_word_actor_history ON timed_mute_word (actor, rowid);
+CREATE TABLE IF NOT EXISTS timed_block (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    blocked_actor VARCHAR NOT NULL,
+    record_uri VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    next_attempt_date BIGINT
+);
+CREATE TABLE IF NOT EXISTS timed_thread_mute (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    thread_uri VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    next_attempt_date BIGINT
+);
+CREATE TABLE IF NOT EXISTS timed_list_mute (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    list_uri VARCHAR NOT NULL,
+    mode VARCHAR NOT NULL,
+    record_uri VARCHAR,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    next_attempt_date BIGINT
+);
+CREATE TABLE IF NOT EXISTS mute_event (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    source VARCHAR NOT NULL,
+    action VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    target VARCHAR NOT NULL,
+    entry_id INTEGER,
+    result VARCHAR NOT NULL,
+    error VARCHAR,
+    created_date BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS mute_event_actor ON mute_event (actor, rowid);
+CREATE TABLE IF NOT EXISTS mute_group (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    name VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS mute_group_name ON mute_group (actor, name COLLATE NOCASE);
+CREATE TABLE IF NOT EXISTS mute_group_member (
+    rowid INTEGER PRIMARY KEY,
+    group_id INTEGER NOT NULL,
+    kind VARCHAR NOT NULL,
+    target VARCHAR NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS mute_group_member_target ON mute_group_member (group_id, kind, target COLLATE NOCASE);
+CREATE INDEX IF NOT EXISTS timed_mute_scheduled ON timed_mute (start_date) WHERE status = 4;
+CREATE INDEX IF NOT EXISTS timed_mute_word_scheduled ON timed_mute_word (start_date) WHERE status = 4;
+CREATE TABLE IF NOT EXISTS recurring_mute (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    target VARCHAR NOT NULL,
+    cron VARCHAR NOT NULL,
+    timezone VARCHAR NOT NULL,
+    duration BIGINT NOT NULL,
+    next_run_date BIGINT NOT NULL,
+    last_run_date BIGINT,
+    last_error VARCHAR,
+    created_date BIGINT NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    next_attempt_date BIGINT
 );
+CREATE INDEX IF NOT EXISTS recurring_mute_next_run ON recurring_mute (next_run_date);
+CREATE INDEX IF NOT EXISTS recurring_mute_next_attempt ON recurring_mute (next_attempt_date) WHERE next_attempt_date IS NOT NULL;
 SQL
 fi
 
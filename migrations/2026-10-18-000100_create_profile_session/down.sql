@@ -0,0 +1,2 @@
+DROP INDEX profile_session_did;
+DROP TABLE profile_session;
@@ -0,0 +1,9 @@
+CREATE TABLE profile_session (
+    access_jwt VARCHAR NOT NULL,
+    refresh_jwt VARCHAR NOT NULL,
+    did VARCHAR NOT NULL,
+    active BOOLEAN NOT NULL,
+    status VARCHAR
+);
+
+CREATE UNIQUE INDEX profile_session_did ON profile_session (did);
@@ -0,0 +1,8 @@
+CREATE TABLE timed_block (
+    actor VARCHAR NOT NULL,
+    blocked_actor VARCHAR NOT NULL,
+    record_uri VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL
+);
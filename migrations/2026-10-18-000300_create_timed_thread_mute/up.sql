@@ -0,0 +1,7 @@
+CREATE TABLE timed_thread_mute (
+    actor VARCHAR NOT NULL,
+    thread_uri VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL
+);
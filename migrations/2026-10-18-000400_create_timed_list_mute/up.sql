@@ -0,0 +1,9 @@
+CREATE TABLE timed_list_mute (
+    actor VARCHAR NOT NULL,
+    list_uri VARCHAR NOT NULL,
+    mode VARCHAR NOT NULL,
+    record_uri VARCHAR,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL
+);
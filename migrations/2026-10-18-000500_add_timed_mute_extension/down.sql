@@ -0,0 +1,4 @@
+ALTER TABLE timed_mute DROP COLUMN original_expiration_date;
+ALTER TABLE timed_mute DROP COLUMN extended_date;
+ALTER TABLE timed_mute_word DROP COLUMN original_expiration_date;
+ALTER TABLE timed_mute_word DROP COLUMN extended_date;
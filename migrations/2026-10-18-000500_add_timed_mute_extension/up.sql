@@ -0,0 +1,4 @@
+ALTER TABLE timed_mute ADD COLUMN original_expiration_date BIGINT;
+ALTER TABLE timed_mute ADD COLUMN extended_date BIGINT;
+ALTER TABLE timed_mute_word ADD COLUMN original_expiration_date BIGINT;
+ALTER TABLE timed_mute_word ADD COLUMN extended_date BIGINT;
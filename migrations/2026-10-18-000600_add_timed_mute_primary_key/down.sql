@@ -0,0 +1,31 @@
+CREATE TABLE timed_mute_old (
+    actor VARCHAR NOT NULL,
+    muted_actor VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL,
+    original_expiration_date BIGINT,
+    extended_date BIGINT
+);
+INSERT INTO timed_mute_old
+    (rowid, actor, muted_actor, created_date, expiration_date, status, original_expiration_date, extended_date)
+    SELECT rowid, actor, muted_actor, created_date, expiration_date, status, original_expiration_date, extended_date
+    FROM timed_mute;
+DROP TABLE timed_mute;
+ALTER TABLE timed_mute_old RENAME TO timed_mute;
+
+CREATE TABLE timed_mute_word_old (
+    actor VARCHAR NOT NULL,
+    muted_word VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL,
+    original_expiration_date BIGINT,
+    extended_date BIGINT
+);
+INSERT INTO timed_mute_word_old
+    (rowid, actor, muted_word, created_date, expiration_date, status, original_expiration_date, extended_date)
+    SELECT rowid, actor, muted_word, created_date, expiration_date, status, original_expiration_date, extended_date
+    FROM timed_mute_word;
+DROP TABLE timed_mute_word;
+ALTER TABLE timed_mute_word_old RENAME TO timed_mute_word;
@@ -0,0 +1,2 @@
+ALTER TABLE timed_mute_word DROP COLUMN targets;
+ALTER TABLE timed_mute_word DROP COLUMN actor_target;
@@ -0,0 +1,2 @@
+ALTER TABLE timed_mute_word ADD COLUMN targets VARCHAR NOT NULL DEFAULT 'tag,content';
+ALTER TABLE timed_mute_word ADD COLUMN actor_target VARCHAR NOT NULL DEFAULT 'all';
@@ -0,0 +1,2 @@
+ALTER TABLE timed_mute DROP COLUMN drift_date;
+ALTER TABLE timed_mute_word DROP COLUMN drift_date;
@@ -0,0 +1,2 @@
+ALTER TABLE timed_mute ADD COLUMN drift_date BIGINT;
+ALTER TABLE timed_mute_word ADD COLUMN drift_date BIGINT;
@@ -0,0 +1,45 @@
+ALTER TABLE timed_mute DROP COLUMN attempt_count;
+ALTER TABLE timed_mute DROP COLUMN last_error;
+ALTER TABLE timed_mute DROP COLUMN next_attempt_date;
+ALTER TABLE timed_mute_word DROP COLUMN attempt_count;
+ALTER TABLE timed_mute_word DROP COLUMN last_error;
+ALTER TABLE timed_mute_word DROP COLUMN next_attempt_date;
+
+CREATE TABLE timed_block_old (
+    actor VARCHAR NOT NULL,
+    blocked_actor VARCHAR NOT NULL,
+    record_uri VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL
+);
+INSERT INTO timed_block_old (rowid, actor, blocked_actor, record_uri, created_date, expiration_date, status)
+    SELECT rowid, actor, blocked_actor, record_uri, created_date, expiration_date, status FROM timed_block;
+DROP TABLE timed_block;
+ALTER TABLE timed_block_old RENAME TO timed_block;
+
+CREATE TABLE timed_thread_mute_old (
+    actor VARCHAR NOT NULL,
+    thread_uri VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL
+);
+INSERT INTO timed_thread_mute_old (rowid, actor, thread_uri, created_date, expiration_date, status)
+    SELECT rowid, actor, thread_uri, created_date, expiration_date, status FROM timed_thread_mute;
+DROP TABLE timed_thread_mute;
+ALTER TABLE timed_thread_mute_old RENAME TO timed_thread_mute;
+
+CREATE TABLE timed_list_mute_old (
+    actor VARCHAR NOT NULL,
+    list_uri VARCHAR NOT NULL,
+    mode VARCHAR NOT NULL,
+    record_uri VARCHAR,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL
+);
+INSERT INTO timed_list_mute_old (rowid, actor, list_uri, mode, record_uri, created_date, expiration_date, status)
+    SELECT rowid, actor, list_uri, mode, record_uri, created_date, expiration_date, status FROM timed_list_mute;
+DROP TABLE timed_list_mute;
+ALTER TABLE timed_list_mute_old RENAME TO timed_list_mute;
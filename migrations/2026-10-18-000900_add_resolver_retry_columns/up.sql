@@ -0,0 +1,58 @@
+ALTER TABLE timed_mute ADD COLUMN attempt_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE timed_mute ADD COLUMN last_error VARCHAR;
+ALTER TABLE timed_mute ADD COLUMN next_attempt_date BIGINT;
+ALTER TABLE timed_mute_word ADD COLUMN attempt_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE timed_mute_word ADD COLUMN last_error VARCHAR;
+ALTER TABLE timed_mute_word ADD COLUMN next_attempt_date BIGINT;
+
+-- Rebuilt with an explicit rowid so the ids the resolver and API use are stable.
+CREATE TABLE timed_block_new (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    blocked_actor VARCHAR NOT NULL,
+    record_uri VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    next_attempt_date BIGINT
+);
+INSERT INTO timed_block_new (rowid, actor, blocked_actor, record_uri, created_date, expiration_date, status)
+    SELECT rowid, actor, blocked_actor, record_uri, created_date, expiration_date, status FROM timed_block;
+DROP TABLE timed_block;
+ALTER TABLE timed_block_new RENAME TO timed_block;
+
+CREATE TABLE timed_thread_mute_new (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    thread_uri VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    next_attempt_date BIGINT
+);
+INSERT INTO timed_thread_mute_new (rowid, actor, thread_uri, created_date, expiration_date, status)
+    SELECT rowid, actor, thread_uri, created_date, expiration_date, status FROM timed_thread_mute;
+DROP TABLE timed_thread_mute;
+ALTER TABLE timed_thread_mute_new RENAME TO timed_thread_mute;
+
+CREATE TABLE timed_list_mute_new (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    list_uri VARCHAR NOT NULL,
+    mode VARCHAR NOT NULL,
+    record_uri VARCHAR,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT NOT NULL,
+    status INTEGER NOT NULL,
+    attempt_count INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    next_attempt_date BIGINT
+);
+INSERT INTO timed_list_mute_new (rowid, actor, list_uri, mode, record_uri, created_date, expiration_date, status)
+    SELECT rowid, actor, list_uri, mode, record_uri, created_date, expiration_date, status FROM timed_list_mute;
+DROP TABLE timed_list_mute;
+ALTER TABLE timed_list_mute_new RENAME TO timed_list_mute;
@@ -0,0 +1,2 @@
+DROP INDEX timed_mute_word_active_target;
+DROP INDEX timed_mute_active_target;
@@ -0,0 +1,22 @@
+-- Keep the active row that expires last for each target; the others are
+-- marked replaced (status 3).
+UPDATE timed_mute SET status = 3
+WHERE status = 0 AND EXISTS (
+    SELECT 1 FROM timed_mute other
+    WHERE other.status = 0
+    AND other.actor = timed_mute.actor
+    AND other.muted_actor = timed_mute.muted_actor
+    AND (other.expiration_date > timed_mute.expiration_date
+        OR (other.expiration_date = timed_mute.expiration_date AND other.rowid > timed_mute.rowid))
+);
+UPDATE timed_mute_word SET status = 3
+WHERE status = 0 AND EXISTS (
+    SELECT 1 FROM timed_mute_word other
+    WHERE other.status = 0
+    AND other.actor = timed_mute_word.actor
+    AND other.muted_word = timed_mute_word.muted_word COLLATE NOCASE
+    AND (other.expiration_date > timed_mute_word.expiration_date
+        OR (other.expiration_date = timed_mute_word.expiration_date AND other.rowid > timed_mute_word.rowid))
+);
+CREATE UNIQUE INDEX timed_mute_active_target ON timed_mute (actor, muted_actor) WHERE status = 0;
+CREATE UNIQUE INDEX timed_mute_word_active_target ON timed_mute_word (actor, muted_word COLLATE NOCASE) WHERE status = 0;
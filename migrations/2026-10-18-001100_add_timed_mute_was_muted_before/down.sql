@@ -0,0 +1 @@
+ALTER TABLE timed_mute DROP COLUMN was_muted_before;
@@ -0,0 +1 @@
+ALTER TABLE timed_mute ADD COLUMN was_muted_before BOOLEAN NOT NULL DEFAULT 0;
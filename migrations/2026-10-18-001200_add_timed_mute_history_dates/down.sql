@@ -0,0 +1,6 @@
+DROP INDEX timed_mute_word_actor_history;
+DROP INDEX timed_mute_actor_history;
+ALTER TABLE timed_mute_word DROP COLUMN cancelled_date;
+ALTER TABLE timed_mute_word DROP COLUMN expired_date;
+ALTER TABLE timed_mute DROP COLUMN cancelled_date;
+ALTER TABLE timed_mute DROP COLUMN expired_date;
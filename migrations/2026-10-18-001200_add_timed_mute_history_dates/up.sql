@@ -0,0 +1,9 @@
+ALTER TABLE timed_mute ADD COLUMN expired_date BIGINT;
+ALTER TABLE timed_mute ADD COLUMN cancelled_date BIGINT;
+ALTER TABLE timed_mute_word ADD COLUMN expired_date BIGINT;
+ALTER TABLE timed_mute_word ADD COLUMN cancelled_date BIGINT;
+-- Rows resolved before these columns existed are assumed to have expired on time.
+UPDATE timed_mute SET expired_date = expiration_date WHERE status = 1;
+UPDATE timed_mute_word SET expired_date = expiration_date WHERE status = 1;
+CREATE INDEX timed_mute_actor_history ON timed_mute (actor, rowid);
+CREATE INDEX timed_mute_word_actor_history ON timed_mute_word (actor, rowid);
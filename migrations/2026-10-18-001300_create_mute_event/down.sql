@@ -0,0 +1,2 @@
+DROP INDEX mute_event_actor;
+DROP TABLE mute_event;
@@ -0,0 +1,13 @@
+CREATE TABLE mute_event (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    source VARCHAR NOT NULL,
+    action VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    target VARCHAR NOT NULL,
+    entry_id INTEGER,
+    result VARCHAR NOT NULL,
+    error VARCHAR,
+    created_date BIGINT NOT NULL
+);
+CREATE INDEX mute_event_actor ON mute_event (actor, rowid);
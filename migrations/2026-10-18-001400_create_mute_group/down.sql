@@ -0,0 +1,6 @@
+ALTER TABLE timed_mute_word DROP COLUMN group_id;
+ALTER TABLE timed_mute DROP COLUMN group_id;
+DROP INDEX mute_group_member_target;
+DROP TABLE mute_group_member;
+DROP INDEX mute_group_name;
+DROP TABLE mute_group;
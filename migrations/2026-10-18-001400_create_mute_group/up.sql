@@ -0,0 +1,17 @@
+CREATE TABLE mute_group (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    name VARCHAR NOT NULL,
+    created_date BIGINT NOT NULL,
+    expiration_date BIGINT
+);
+CREATE UNIQUE INDEX mute_group_name ON mute_group (actor, name COLLATE NOCASE);
+CREATE TABLE mute_group_member (
+    rowid INTEGER PRIMARY KEY,
+    group_id INTEGER NOT NULL,
+    kind VARCHAR NOT NULL,
+    target VARCHAR NOT NULL
+);
+CREATE UNIQUE INDEX mute_group_member_target ON mute_group_member (group_id, kind, target COLLATE NOCASE);
+ALTER TABLE timed_mute ADD COLUMN group_id INTEGER;
+ALTER TABLE timed_mute_word ADD COLUMN group_id INTEGER;
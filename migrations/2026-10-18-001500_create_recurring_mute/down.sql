@@ -0,0 +1,2 @@
+DROP INDEX recurring_mute_next_run;
+DROP TABLE recurring_mute;
@@ -0,0 +1,14 @@
+CREATE TABLE recurring_mute (
+    rowid INTEGER PRIMARY KEY,
+    actor VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    target VARCHAR NOT NULL,
+    cron VARCHAR NOT NULL,
+    timezone VARCHAR NOT NULL,
+    duration BIGINT NOT NULL,
+    next_run_date BIGINT NOT NULL,
+    last_run_date BIGINT,
+    last_error VARCHAR,
+    created_date BIGINT NOT NULL
+);
+CREATE INDEX recurring_mute_next_run ON recurring_mute (next_run_date);
@@ -0,0 +1,4 @@
+DROP INDEX timed_mute_word_scheduled;
+DROP INDEX timed_mute_scheduled;
+ALTER TABLE timed_mute_word DROP COLUMN start_date;
+ALTER TABLE timed_mute DROP COLUMN start_date;
@@ -0,0 +1,4 @@
+ALTER TABLE timed_mute ADD COLUMN start_date BIGINT;
+ALTER TABLE timed_mute_word ADD COLUMN start_date BIGINT;
+CREATE INDEX timed_mute_scheduled ON timed_mute (start_date) WHERE status = 4;
+CREATE INDEX timed_mute_word_scheduled ON timed_mute_word (start_date) WHERE status = 4;
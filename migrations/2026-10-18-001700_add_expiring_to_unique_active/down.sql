@@ -0,0 +1,4 @@
+DROP INDEX timed_mute_word_unresolved_target;
+DROP INDEX timed_mute_unresolved_target;
+CREATE UNIQUE INDEX timed_mute_active_target ON timed_mute (actor, muted_actor) WHERE status = 0;
+CREATE UNIQUE INDEX timed_mute_word_active_target ON timed_mute_word (actor, muted_word COLLATE NOCASE) WHERE status = 0;
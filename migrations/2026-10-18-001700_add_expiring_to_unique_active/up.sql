@@ -0,0 +1,35 @@
+-- Rows the resolver is expiring still hold their target: a second active
+-- row next to one could be unmuted by it. The earliest row of each target
+-- knows whether the user muted it before any timed mute, so that is carried
+-- to the others, then only the row that expires last is kept.
+DROP INDEX timed_mute_word_active_target;
+DROP INDEX timed_mute_active_target;
+UPDATE timed_mute SET was_muted_before = (
+    SELECT earliest.was_muted_before FROM timed_mute earliest
+    WHERE earliest.status IN (0, 5)
+    AND earliest.actor = timed_mute.actor
+    AND earliest.muted_actor = timed_mute.muted_actor
+    ORDER BY earliest.created_date, earliest.rowid
+    LIMIT 1
+)
+WHERE status IN (0, 5);
+UPDATE timed_mute SET status = 3
+WHERE status IN (0, 5) AND EXISTS (
+    SELECT 1 FROM timed_mute other
+    WHERE other.status IN (0, 5)
+    AND other.actor = timed_mute.actor
+    AND other.muted_actor = timed_mute.muted_actor
+    AND (other.expiration_date > timed_mute.expiration_date
+        OR (other.expiration_date = timed_mute.expiration_date AND other.rowid > timed_mute.rowid))
+);
+UPDATE timed_mute_word SET status = 3
+WHERE status IN (0, 5) AND EXISTS (
+    SELECT 1 FROM timed_mute_word other
+    WHERE other.status IN (0, 5)
+    AND other.actor = timed_mute_word.actor
+    AND other.muted_word = timed_mute_word.muted_word COLLATE NOCASE
+    AND (other.expiration_date > timed_mute_word.expiration_date
+        OR (other.expiration_date = timed_mute_word.expiration_date AND other.rowid > timed_mute_word.rowid))
+);
+CREATE UNIQUE INDEX timed_mute_unresolved_target ON timed_mute (actor, muted_actor) WHERE status IN (0, 5);
+CREATE UNIQUE INDEX timed_mute_word_unresolved_target ON timed_mute_word (actor, muted_word COLLATE NOCASE) WHERE status IN (0, 5);
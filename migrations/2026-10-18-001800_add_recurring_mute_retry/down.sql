@@ -0,0 +1,3 @@
+DROP INDEX recurring_mute_next_attempt;
+ALTER TABLE recurring_mute DROP COLUMN next_attempt_date;
+ALTER TABLE recurring_mute DROP COLUMN attempt_count;
@@ -0,0 +1,3 @@
+ALTER TABLE recurring_mute ADD COLUMN attempt_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE recurring_mute ADD COLUMN next_attempt_date BIGINT;
+CREATE INDEX recurring_mute_next_attempt ON recurring_mute (next_attempt_date) WHERE next_attempt_date IS NOT NULL;
@@ -0,0 +1,85 @@
+use std::env;
+
+use axum::extract::{Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use tower_sessions::Session;
+
+use crate::error::AppError;
+use crate::event::{EventQuery, MuteEvents};
+use crate::tmute::{get_user_id, FailedTimedMutes};
+use crate::{DBPool, APPLICATION_JSON};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Returns whether `did` is listed in the comma separated `ADMIN_DIDS`.
+fn is_admin(did: &str) -> bool {
+    env::var("ADMIN_DIDS")
+        .map(|admins| admins.split(',').any(|admin| admin.trim() == did))
+        .unwrap_or(false)
+}
+
+/// Returns the signed in user's DID if they are an admin.
+async fn get_admin_id(session: Session) -> Result<String> {
+    let user_id = get_user_id(session).await?;
+    if !is_admin(user_id.as_str()) {
+        return Err(AppError::Forbidden);
+    }
+    Ok(user_id)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/timed-failures",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Failed timed entries for every user", body = FailedTimedMutes),
+        (status=401, description="Unauthorized"),
+        (status=403, description="Not an admin"),
+    ),
+)]
+pub async fn list_failed(session: Session, State(pool): State<DBPool>) -> Result<Response> {
+    get_admin_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+    let failed = FailedTimedMutes::fetch(&mut conn, None);
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(failed),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/events",
+    params(
+        ("bskytools" = String, Cookie,),
+        EventQuery
+    ),
+    responses(
+        (status=200, description="Audit log of every user's timed entries, newest first", body = MuteEvents),
+        (status=400, description="Invalid filter or cursor"),
+        (status=401, description="Unauthorized"),
+        (status=403, description="Not an admin"),
+    ),
+)]
+pub async fn list_events(
+    session: Session,
+    State(pool): State<DBPool>,
+    Query(query): Query<EventQuery>,
+) -> Result<Response> {
+    get_admin_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+    let filter = query.filter(query.actor.as_deref())?;
+    let events = MuteEvents::fetch(&mut conn, &filter);
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(events),
+    )
+        .into_response())
+}
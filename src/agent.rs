@@ -1,20 +1,133 @@
+use crate::crypto;
 use crate::error::AppError;
+use crate::helper::{
+    delete_profile_session, fetch_profile_session, fetch_profile_v1, normalize_word,
+    save_profile_session, DBPool, DBPooledConnection,
+};
+use crate::models::Profile;
+use crate::ratelimit::RateLimitedClient;
+use async_trait::async_trait;
+use bsky_sdk::agent::config::Config;
+use bsky_sdk::api::agent::store::SessionStore;
+use bsky_sdk::api::agent::Session;
 use bsky_sdk::api::app::bsky::actor::defs::{
-    MutedWord, MutedWordData, Preferences, PreferencesItem,
+    MutedWord, MutedWordData, MutedWordsPrefData, Preferences, PreferencesItem, ProfileViewDetailed,
 };
-use bsky_sdk::api::types::string::AtIdentifier;
+use bsky_sdk::api::app::bsky::feed::defs::{ThreadViewPost, ThreadViewPostRepliesItem};
+use bsky_sdk::api::com::atproto::server::create_session::OutputData;
+use bsky_sdk::api::types::string::{AtIdentifier, Datetime, Did, Handle};
 use bsky_sdk::api::types::Union;
+use bsky_sdk::api::xrpc::error::{Error as XrpcError, XrpcErrorKind};
+use bsky_sdk::api::xrpc::http::{Method, Request, Response, StatusCode};
+use bsky_sdk::api::xrpc::{HttpClient, OutputDataOrBytes, XrpcClient, XrpcRequest};
+use bsky_sdk::record::Record;
 use bsky_sdk::BskyAgent;
+use diesel::SqliteConnection;
 use ipld_core::ipld::Ipld;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
 use std::str::FromStr;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use tokio::sync::RwLock;
 
 pub type Result<T> = std::result::Result<T, AppError>;
-pub type GetAgentResult = Result<BskyAgent>;
+pub type Agent = BskyAgent<RateLimitedClient, DbSessionStore>;
+pub type GetAgentResult = Result<Agent>;
 pub type MuteActorResult = Result<()>;
 pub type UnmuteActorResult = Result<()>;
 
+static SESSION_POOL: OnceLock<DBPool> = OnceLock::new();
+
+/// Sets the pool that stored Bluesky sessions are read from and written to.
+pub fn init(pool: DBPool) {
+    let _ = SESSION_POOL.set(pool);
+}
+
+/// DIDs whose last session could not be stored, with when and why. Signing
+/// in again would only create another session that is lost the same way, so
+/// `get_agent_for_profile` refuses to for `SESSION_SAVE_BACKOFF` seconds.
+static UNSAVED_SESSIONS: Mutex<BTreeMap<String, (i64, String)>> = Mutex::new(BTreeMap::new());
+const SESSION_SAVE_BACKOFF: i64 = 5 * 60;
+
+/// Returns the save error for `did` if its session was lost less than
+/// `SESSION_SAVE_BACKOFF` seconds before `now`.
+fn unsaved_session(did: &str, now: i64) -> Option<AppError> {
+    let unsaved = UNSAVED_SESSIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let (failed_at, error) = unsaved.get(did)?;
+    (now < failed_at + SESSION_SAVE_BACKOFF)
+        .then(|| AppError::PoolError(format!("Session for {} could not be saved: {}", did, error)))
+}
+
+fn session_connection() -> Result<DBPooledConnection> {
+    SESSION_POOL
+        .get()
+        .ok_or_else(|| AppError::PoolError("session pool has not been set".to_string()))?
+        .get()
+        .map_err(|e| AppError::PoolError(e.to_string()))
+}
+
+/// Session store that mirrors the agent's session into `profile_session`, so
+/// refreshed tokens survive the agent and the next call can resume them.
+#[derive(Default)]
+pub struct DbSessionStore {
+    session: RwLock<Option<Session>>,
+}
+
+#[async_trait]
+impl SessionStore for DbSessionStore {
+    async fn get_session(&self) -> Option<Session> {
+        self.session.read().await.clone()
+    }
+
+    async fn set_session(&self, session: Session) {
+        let saved = session_connection().and_then(|mut conn| {
+            save_profile_session(
+                &mut conn,
+                session.did.as_str(),
+                session.access_jwt.as_str(),
+                session.refresh_jwt.as_str(),
+                &session.active.unwrap_or(true),
+                session.status.as_deref(),
+            )
+        });
+        {
+            let mut unsaved = UNSAVED_SESSIONS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            match saved {
+                Ok(_) => {
+                    unsaved.remove(session.did.as_str());
+                }
+                Err(e) => {
+                    let now = chrono::offset::Utc::now().timestamp();
+                    unsaved.insert(session.did.to_string(), (now, e.to_string()));
+                }
+            }
+        }
+        self.session.write().await.replace(session);
+    }
+
+    /// Only forgets the session in memory. The agent clears it after any
+    /// failed refresh, outages included, so the stored row is only deleted
+    /// by `get_agent_for_profile` once the refresh token is rejected.
+    async fn clear_session(&self) {
+        self.session.write().await.take();
+    }
+}
+
 pub async fn get_agent(username: &str, password: &str) -> GetAgentResult {
-    let agent: BskyAgent = BskyAgent::builder()
+    login(username, username, password).await
+}
+
+/// Signs in with a fresh session. `account` keys the client's rate limit
+/// state and should be the DID when it is known.
+async fn login(account: &str, username: &str, password: &str) -> GetAgentResult {
+    let agent = BskyAgent::builder()
+        .client(RateLimitedClient::new(Config::default().endpoint, account))
+        .store(DbSessionStore::default())
         .build()
         .await
         .map_err(|e| AppError::BskyError(e.to_string()))?;
@@ -25,7 +138,167 @@ pub async fn get_agent(username: &str, password: &str) -> GetAgentResult {
     Ok(agent)
 }
 
-pub async fn mute_actor(agent: &BskyAgent, actor: &str) -> MuteActorResult {
+/// Builds an agent on a stored session. The agent checks the session and
+/// refreshes an expired access token on the way.
+async fn resume(profile: &Profile, session: Session) -> Result<Agent> {
+    let agent = BskyAgent::builder()
+        .client(RateLimitedClient::new(
+            Config::default().endpoint,
+            profile.did.as_str(),
+        ))
+        .store(DbSessionStore::default())
+        .build()
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    match agent.resume_session(session).await {
+        Ok(()) => Ok(agent),
+        Err(e) if is_auth_failure(&e) => Err(AppError::Unauthorized),
+        Err(e) => Err(AppError::BskyError(e.to_string())),
+    }
+}
+
+/// Whether a request failed on its tokens rather than on the network or the
+/// server.
+fn is_auth_failure<E: Debug>(error: &XrpcError<E>) -> bool {
+    matches!(
+        error,
+        XrpcError::XrpcResponse(response)
+            if response.status == StatusCode::BAD_REQUEST
+                || response.status == StatusCode::UNAUTHORIZED
+    )
+}
+
+/// Whether a refresh was refused because the refresh token itself expired or
+/// was revoked.
+fn is_token_rejected<E: Debug>(error: &XrpcError<E>) -> bool {
+    matches!(
+        error,
+        XrpcError::XrpcResponse(response)
+            if matches!(
+                &response.error,
+                Some(XrpcErrorKind::Undefined(body))
+                    if matches!(body.error.as_deref(), Some("ExpiredToken" | "InvalidToken"))
+            )
+    )
+}
+
+/// Exchanges the session's refresh token for a new session. Returns `None`
+/// when Bluesky rejected the refresh token, and an error for anything else,
+/// such as rate limits or outages.
+async fn refresh(profile: &Profile, session: &Session) -> Result<Option<Session>> {
+    use bsky_sdk::api::com::atproto::server::refresh_session;
+    let client = SessionClient {
+        inner: RateLimitedClient::new(Config::default().endpoint, profile.did.as_str()),
+        token: session.refresh_jwt.clone(),
+    };
+    let response = client
+        .send_xrpc::<(), (), refresh_session::Output, refresh_session::Error>(&XrpcRequest {
+            method: Method::POST,
+            nsid: refresh_session::NSID.into(),
+            parameters: None,
+            input: None,
+            encoding: None,
+        })
+        .await;
+    let output = match response {
+        Ok(OutputDataOrBytes::Data(output)) => output,
+        Ok(OutputDataOrBytes::Bytes(_)) => {
+            return Err(AppError::BskyError("Unexpected response".to_string()))
+        }
+        Err(e) if is_token_rejected(&e) => return Ok(None),
+        Err(e) => return Err(AppError::BskyError(e.to_string())),
+    };
+    Ok(Some(
+        OutputData {
+            access_jwt: output.data.access_jwt,
+            active: output.data.active,
+            did: output.data.did,
+            did_doc: output.data.did_doc,
+            email: None,
+            email_auth_factor: None,
+            email_confirmed: None,
+            handle: output.data.handle,
+            refresh_jwt: output.data.refresh_jwt,
+            status: output.data.status,
+        }
+        .into(),
+    ))
+}
+
+/// Returns an agent for the given profile, resuming its stored session when
+/// possible. Expired access tokens are refreshed; the stored password is only
+/// decrypted and used when there is no session or Bluesky rejected its
+/// refresh token. Rate limits and outages are returned as errors instead, as
+/// is a session that could not be stored, until `SESSION_SAVE_BACKOFF` passes.
+pub async fn get_agent_for_profile(profile: &Profile) -> GetAgentResult {
+    if let Some(session) = stored_session(profile)? {
+        match resume(profile, session.clone()).await {
+            Err(AppError::Unauthorized) => {}
+            resumed => return resumed,
+        }
+        if let Some(refreshed) = refresh(profile, &session).await? {
+            return resume(profile, refreshed).await.map_err(|e| match e {
+                AppError::Unauthorized => {
+                    AppError::BskyError("Refreshed session was not accepted".to_string())
+                }
+                e => e,
+            });
+        }
+        let mut conn = session_connection()?;
+        delete_profile_session(&mut conn, profile.did.as_str())?;
+    }
+    let now = chrono::offset::Utc::now().timestamp();
+    if let Some(e) = unsaved_session(profile.did.as_str(), now) {
+        return Err(e);
+    }
+    let password = crypto::decrypt(profile.password.as_str())?;
+    let agent = login(
+        profile.did.as_str(),
+        profile.handle.as_str(),
+        password.as_str(),
+    )
+    .await?;
+    // The new session is gone with the agent if it was not stored.
+    if let Some(e) = unsaved_session(profile.did.as_str(), now) {
+        return Err(e);
+    }
+    Ok(agent)
+}
+
+/// Returns an agent for the user with the given DID, for background jobs
+/// that act on a user's behalf.
+pub async fn get_agent_for_actor(conn: &mut SqliteConnection, actor: &str) -> GetAgentResult {
+    let profile_list = fetch_profile_v1(conn, actor);
+    let profile = profile_list.first().ok_or(AppError::NotFound)?;
+    get_agent_for_profile(profile).await
+}
+
+fn stored_session(profile: &Profile) -> Result<Option<Session>> {
+    let mut conn = session_connection()?;
+    let Some(stored) = fetch_profile_session(&mut conn, profile.did.as_str())? else {
+        return Ok(None);
+    };
+    let (Ok(did), Ok(handle)) = (stored.did.parse(), profile.handle.parse()) else {
+        return Ok(None);
+    };
+    Ok(Some(
+        OutputData {
+            access_jwt: stored.access_jwt,
+            active: Some(stored.active),
+            did,
+            did_doc: None,
+            email: None,
+            email_auth_factor: None,
+            email_confirmed: None,
+            handle,
+            refresh_jwt: stored.refresh_jwt,
+            status: stored.status,
+        }
+        .into(),
+    ))
+}
+
+pub async fn mute_actor(agent: &Agent, actor: &str) -> MuteActorResult {
     use bsky_sdk::api::app::bsky::graph::mute_actor::{Input, InputData};
     agent
         .api
@@ -44,7 +317,7 @@ pub async fn mute_actor(agent: &BskyAgent, actor: &str) -> MuteActorResult {
     Ok(())
 }
 
-pub async fn unmute_actor(agent: &BskyAgent, actor: &str) -> UnmuteActorResult {
+pub async fn unmute_actor(agent: &Agent, actor: &str) -> UnmuteActorResult {
     use bsky_sdk::api::app::bsky::graph::unmute_actor::{Input, InputData};
     agent
         .api
@@ -63,7 +336,406 @@ pub async fn unmute_actor(agent: &BskyAgent, actor: &str) -> UnmuteActorResult {
     Ok(())
 }
 
-pub async fn get_preferences(agent: &BskyAgent) -> Result<Preferences> {
+/// Returns the DIDs of every account the user has muted on Bluesky.
+pub async fn get_muted_actors(agent: &Agent) -> Result<Vec<String>> {
+    use bsky_sdk::api::app::bsky::graph::get_mutes::{Parameters, ParametersData};
+    let mut dids = Vec::new();
+    let mut cursor = None;
+    loop {
+        let res = agent
+            .api
+            .app
+            .bsky
+            .graph
+            .get_mutes(Parameters {
+                data: ParametersData {
+                    cursor,
+                    limit: 100.try_into().ok(),
+                },
+                extra_data: Ipld::Null,
+            })
+            .await
+            .map_err(|e| AppError::BskyError(e.to_string()))?;
+        dids.extend(res.mutes.iter().map(|m| m.did.to_string()));
+        match &res.cursor {
+            Some(next) if !res.mutes.is_empty() => cursor = Some(next.clone()),
+            _ => break,
+        }
+    }
+    Ok(dids)
+}
+
+/// Most actors `app.bsky.actor.getProfiles` accepts per call.
+pub const PROFILES_PER_REQUEST: usize = 25;
+
+/// Looks up profiles, `PROFILES_PER_REQUEST` at a time. Actors that don't
+/// exist are left out of the result.
+pub async fn get_profiles(
+    agent: &Agent,
+    actors: Vec<AtIdentifier>,
+) -> Result<Vec<ProfileViewDetailed>> {
+    use bsky_sdk::api::app::bsky::actor::get_profiles::{Parameters, ParametersData};
+    let mut profiles = Vec::with_capacity(actors.len());
+    for chunk in actors.chunks(PROFILES_PER_REQUEST) {
+        let res = agent
+            .api
+            .app
+            .bsky
+            .actor
+            .get_profiles(Parameters {
+                data: ParametersData {
+                    actors: chunk.to_vec(),
+                },
+                extra_data: Ipld::Null,
+            })
+            .await
+            .map_err(|e| AppError::BskyError(e.to_string()))?;
+        profiles.extend(res.data.profiles);
+    }
+    Ok(profiles)
+}
+
+/// Returns the DID a handle currently points to.
+pub async fn resolve_handle(agent: &Agent, handle: Handle) -> Result<String> {
+    use bsky_sdk::api::com::atproto::identity::resolve_handle::{Parameters, ParametersData};
+    let res = agent
+        .api
+        .com
+        .atproto
+        .identity
+        .resolve_handle(Parameters {
+            data: ParametersData { handle },
+            extra_data: Ipld::Null,
+        })
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    Ok(res.data.did.to_string())
+}
+
+/// Most accounts listed from one post's quotes or reposts.
+const ENGAGEMENT_LIMIT: usize = 1000;
+
+/// Returns the DIDs of the accounts that reposted a post, newest first, up to
+/// `ENGAGEMENT_LIMIT`.
+pub async fn get_reposted_by(agent: &Agent, post_uri: &str) -> Result<Vec<Did>> {
+    use bsky_sdk::api::app::bsky::feed::get_reposted_by::{Parameters, ParametersData};
+    let mut dids = Vec::new();
+    let mut cursor = None;
+    while dids.len() < ENGAGEMENT_LIMIT {
+        let res = agent
+            .api
+            .app
+            .bsky
+            .feed
+            .get_reposted_by(Parameters {
+                data: ParametersData {
+                    cid: None,
+                    cursor,
+                    limit: 100.try_into().ok(),
+                    uri: post_uri.to_string(),
+                },
+                extra_data: Ipld::Null,
+            })
+            .await
+            .map_err(|e| AppError::BskyError(e.to_string()))?;
+        dids.extend(res.reposted_by.iter().map(|p| p.did.clone()));
+        match &res.cursor {
+            Some(next) if !res.reposted_by.is_empty() => cursor = Some(next.clone()),
+            _ => break,
+        }
+    }
+    Ok(dids)
+}
+
+/// Returns the DIDs of the accounts that replied anywhere below a post, in
+/// thread order.
+pub async fn get_reply_authors(agent: &Agent, post_uri: &str) -> Result<Vec<Did>> {
+    use bsky_sdk::api::app::bsky::feed::get_post_thread::{
+        OutputThreadRefs, Parameters, ParametersData,
+    };
+    let res = agent
+        .api
+        .app
+        .bsky
+        .feed
+        .get_post_thread(Parameters {
+            data: ParametersData {
+                depth: 1000.try_into().ok(),
+                parent_height: 0.try_into().ok(),
+                uri: post_uri.to_string(),
+            },
+            extra_data: Ipld::Null,
+        })
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    let mut dids = Vec::new();
+    if let Union::Refs(OutputThreadRefs::AppBskyFeedDefsThreadViewPost(thread)) = &res.thread {
+        collect_reply_authors(thread, &mut dids);
+    }
+    Ok(dids)
+}
+
+fn collect_reply_authors(thread: &ThreadViewPost, dids: &mut Vec<Did>) {
+    for reply in thread.replies.iter().flatten() {
+        if let Union::Refs(ThreadViewPostRepliesItem::ThreadViewPost(reply)) = reply {
+            dids.push(reply.post.author.did.clone());
+            collect_reply_authors(reply, dids);
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GetQuotesParameters<'a> {
+    uri: &'a str,
+    limit: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GetQuotesOutput {
+    cursor: Option<String>,
+    posts: Vec<QuotePost>,
+}
+
+#[derive(Deserialize)]
+struct QuotePost {
+    author: QuoteAuthor,
+}
+
+#[derive(Deserialize)]
+struct QuoteAuthor {
+    did: Did,
+}
+
+/// Client that sends a fixed token: the agent's current access token, for
+/// XRPC methods the atrium bindings don't have yet, or a refresh token, to
+/// refresh a session without the agent.
+struct SessionClient {
+    inner: RateLimitedClient,
+    token: String,
+}
+
+impl SessionClient {
+    async fn for_agent(agent: &Agent) -> Result<Self> {
+        let session = agent
+            .get_session()
+            .await
+            .ok_or_else(|| AppError::BskyError("Not signed in".to_string()))?;
+        Ok(Self {
+            inner: RateLimitedClient::new(agent.get_endpoint().await, session.did.as_str()),
+            token: session.data.access_jwt,
+        })
+    }
+}
+
+#[async_trait]
+impl HttpClient for SessionClient {
+    async fn send_http(
+        &self,
+        request: Request<Vec<u8>>,
+    ) -> std::result::Result<Response<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>>
+    {
+        self.inner.send_http(request).await
+    }
+}
+
+#[async_trait]
+impl XrpcClient for SessionClient {
+    fn base_uri(&self) -> String {
+        self.inner.base_uri()
+    }
+
+    async fn authentication_token(&self, _is_refresh: bool) -> Option<String> {
+        Some(self.token.clone())
+    }
+}
+
+/// Returns the DIDs of the accounts that quoted a post, newest first, up to
+/// `ENGAGEMENT_LIMIT`. `app.bsky.feed.getQuotes` is newer than the atrium
+/// bindings, so it is sent with the agent's access token directly; call it
+/// after another agent call so an expired token has been refreshed.
+pub async fn get_quote_authors(agent: &Agent, post_uri: &str) -> Result<Vec<Did>> {
+    let client = SessionClient::for_agent(agent).await?;
+    let mut dids = Vec::new();
+    let mut cursor = None;
+    while dids.len() < ENGAGEMENT_LIMIT {
+        let request = XrpcRequest::<_, ()> {
+            method: Method::GET,
+            nsid: "app.bsky.feed.getQuotes".to_string(),
+            parameters: Some(GetQuotesParameters {
+                uri: post_uri,
+                limit: 100,
+                cursor,
+            }),
+            input: None,
+            encoding: None,
+        };
+        let res = match client
+            .send_xrpc::<_, _, GetQuotesOutput, serde_json::Value>(&request)
+            .await
+            .map_err(|e| AppError::BskyError(e.to_string()))?
+        {
+            OutputDataOrBytes::Data(res) => res,
+            OutputDataOrBytes::Bytes(_) => {
+                return Err(AppError::BskyError(
+                    "Unexpected response from app.bsky.feed.getQuotes".to_string(),
+                ))
+            }
+        };
+        dids.extend(res.posts.iter().map(|p| p.author.did.clone()));
+        match res.cursor {
+            Some(next) if !res.posts.is_empty() => cursor = Some(next),
+            _ => break,
+        }
+    }
+    Ok(dids)
+}
+
+/// Returns the values in the user's muted words preference.
+pub async fn get_muted_words(agent: &Agent) -> Result<Vec<String>> {
+    let preferences = get_preferences(agent).await?;
+    let mut words = Vec::new();
+    for preference in &preferences {
+        if let Union::Refs(PreferencesItem::MutedWordsPref(mute_words_pref)) = preference {
+            words.extend(mute_words_pref.items.iter().map(|w| w.value.clone()));
+        }
+    }
+    Ok(words)
+}
+
+pub async fn mute_thread(agent: &Agent, thread_uri: &str) -> Result<()> {
+    use bsky_sdk::api::app::bsky::graph::mute_thread::{Input, InputData};
+    agent
+        .api
+        .app
+        .bsky
+        .graph
+        .mute_thread(Input {
+            data: InputData {
+                root: thread_uri.to_string(),
+            },
+            extra_data: Ipld::Null,
+        })
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    Ok(())
+}
+
+pub async fn unmute_thread(agent: &Agent, thread_uri: &str) -> Result<()> {
+    use bsky_sdk::api::app::bsky::graph::unmute_thread::{Input, InputData};
+    agent
+        .api
+        .app
+        .bsky
+        .graph
+        .unmute_thread(Input {
+            data: InputData {
+                root: thread_uri.to_string(),
+            },
+            extra_data: Ipld::Null,
+        })
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    Ok(())
+}
+
+pub async fn mute_actor_list(agent: &Agent, list_uri: &str) -> Result<()> {
+    use bsky_sdk::api::app::bsky::graph::mute_actor_list::{Input, InputData};
+    agent
+        .api
+        .app
+        .bsky
+        .graph
+        .mute_actor_list(Input {
+            data: InputData {
+                list: list_uri.to_string(),
+            },
+            extra_data: Ipld::Null,
+        })
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    Ok(())
+}
+
+pub async fn unmute_actor_list(agent: &Agent, list_uri: &str) -> Result<()> {
+    use bsky_sdk::api::app::bsky::graph::unmute_actor_list::{Input, InputData};
+    agent
+        .api
+        .app
+        .bsky
+        .graph
+        .unmute_actor_list(Input {
+            data: InputData {
+                list: list_uri.to_string(),
+            },
+            extra_data: Ipld::Null,
+        })
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    Ok(())
+}
+
+/// Creates an `app.bsky.graph.listblock` record and returns its AT-URI.
+pub async fn block_list(agent: &Agent, list_uri: &str) -> Result<String> {
+    use bsky_sdk::api::app::bsky::graph::listblock::{Record as ListblockRecord, RecordData};
+    let record: ListblockRecord = RecordData {
+        created_at: Datetime::now(),
+        subject: list_uri.to_string(),
+    }
+    .into();
+    let output = record
+        .create(agent)
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    Ok(output.uri.clone())
+}
+
+/// Deletes the listblock record created by [`block_list`].
+pub async fn unblock_list(agent: &Agent, record_uri: &str) -> Result<()> {
+    use bsky_sdk::api::app::bsky::graph::listblock::Record as ListblockRecord;
+    ListblockRecord::delete(agent, record_key(record_uri)?)
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))
+}
+
+/// Creates an `app.bsky.graph.block` record and returns its AT-URI.
+pub async fn block_actor(agent: &Agent, actor: &str) -> Result<String> {
+    use bsky_sdk::api::app::bsky::graph::block::{Record as BlockRecord, RecordData};
+    let record: BlockRecord = RecordData {
+        created_at: Datetime::now(),
+        subject: actor
+            .parse()
+            .map_err(|e: &str| AppError::BskyError(e.to_string()))?,
+    }
+    .into();
+    let output = record
+        .create(agent)
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+    Ok(output.uri.clone())
+}
+
+/// Deletes the block record created by [`block_actor`].
+pub async fn unblock_actor(agent: &Agent, record_uri: &str) -> Result<()> {
+    use bsky_sdk::api::app::bsky::graph::block::Record as BlockRecord;
+    BlockRecord::delete(agent, record_key(record_uri)?)
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))
+}
+
+/// Returns the record key, the last segment of an `at://did/collection/rkey` URI.
+fn record_key(record_uri: &str) -> Result<String> {
+    record_uri
+        .strip_prefix("at://")
+        .and_then(|path| path.splitn(3, '/').nth(2))
+        .filter(|rkey| !rkey.is_empty() && !rkey.contains('/'))
+        .map(str::to_string)
+        .ok_or_else(|| AppError::BskyError(format!("Invalid record uri: {}", record_uri)))
+}
+
+pub async fn get_preferences(agent: &Agent) -> Result<Preferences> {
     use bsky_sdk::api::app::bsky::actor::get_preferences::{Parameters, ParametersData};
     let res = agent
         .api
@@ -80,7 +752,7 @@ pub async fn get_preferences(agent: &BskyAgent) -> Result<Preferences> {
     Ok(res.preferences.clone())
 }
 
-pub async fn put_preferences(agent: &BskyAgent, preference: Preferences) -> Result<()> {
+pub async fn put_preferences(agent: &Agent, preference: Preferences) -> Result<()> {
     use bsky_sdk::api::app::bsky::actor::put_preferences::{Input, InputData};
     agent
         .api
@@ -98,7 +770,7 @@ pub async fn put_preferences(agent: &BskyAgent, preference: Preferences) -> Resu
     Ok(())
 }
 
-pub async fn unmute_actor_by_handle(agent: &BskyAgent, actor_handle: &str) -> UnmuteActorResult {
+pub async fn unmute_actor_by_handle(agent: &Agent, actor_handle: &str) -> UnmuteActorResult {
     use bsky_sdk::api::app::bsky::graph::unmute_actor::{Input, InputData};
     agent
         .api
@@ -120,39 +792,301 @@ pub async fn unmute_actor_by_handle(agent: &BskyAgent, actor_handle: &str) -> Un
     Ok(())
 }
 
-pub async fn add_mute_word_to_pref(agent: &BskyAgent, mute_word: String) -> Result<()> {
-    let mut preferences = get_preferences(agent).await?;
-    for preference in &mut preferences {
-        match preference {
-            Union::Refs(ref mut preference_item) => {
-                if let PreferencesItem::MutedWordsPref(ref mut mute_words_pref) = preference_item {
-                    let word = MutedWord {
-                        data: MutedWordData {
-                            targets: vec!["tag".to_string(), "content".to_string()],
-                            value: mute_word.clone(),
-                        },
-                        extra_data: Ipld::Null,
-                    };
-                    mute_words_pref.items.push(word);
-                }
-            }
-            Union::Unknown(_b) => {}
+/// Prefix of the `id` given to muted words added by this service, so they can
+/// be told apart from words the user muted in the app.
+const MUTED_WORD_ID_PREFIX: &str = "timedmutes-";
+/// Writes of the muted words preference tried before giving up when other
+/// clients keep changing it.
+const PREFERENCE_ATTEMPTS: usize = 3;
+
+/// Adds a muted word with the given `targets` (`tag`, `content`) and
+/// `actor_target` (`all` or `exclude-following`). A word this service added
+/// before is updated in place. A word the user muted themselves is left
+/// alone; when it does not cover every target and actor, this service adds
+/// its own word next to it.
+pub async fn add_mute_word_to_pref(
+    agent: &Agent,
+    mute_word: String,
+    targets: Vec<String>,
+    actor_target: &str,
+) -> Result<()> {
+    update_muted_words(
+        agent,
+        |words| has_muted_word(words, mute_word.as_str(), &targets, actor_target),
+        |words| upsert_muted_word(words, mute_word.as_str(), &targets, actor_target),
+    )
+    .await
+}
+
+/// Removes the muted word added by this service. Words with the same value
+/// that the user muted in the app are kept.
+pub async fn remove_mute_word_from_pref(agent: &Agent, mute_word: String) -> Result<()> {
+    update_muted_words(
+        agent,
+        |words| {
+            !words
+                .iter()
+                .any(|w| is_own_muted_word(w, mute_word.as_str()))
+        },
+        |words| words.retain(|w| !is_own_muted_word(w, mute_word.as_str())),
+    )
+    .await
+}
+
+/// Read-modify-write of the muted words preference. `edit` is applied and
+/// the preferences written back until a fresh read shows `applied`, so a
+/// concurrent write by another client that dropped the change is redone.
+async fn update_muted_words(
+    agent: &Agent,
+    applied: impl Fn(&[MutedWord]) -> bool,
+    edit: impl Fn(&mut Vec<MutedWord>),
+) -> Result<()> {
+    for _ in 0..PREFERENCE_ATTEMPTS {
+        let mut preferences = get_preferences(agent).await?;
+        let words = muted_words_mut(&mut preferences);
+        if applied(words) {
+            return Ok(());
         }
+        edit(words);
+        put_preferences(agent, preferences).await?;
     }
-    put_preferences(agent, preferences).await
+    let mut preferences = get_preferences(agent).await?;
+    if applied(muted_words_mut(&mut preferences)) {
+        return Ok(());
+    }
+    Err(AppError::BskyError(
+        "Muted words preference changed while it was being updated".to_string(),
+    ))
 }
 
-pub async fn remove_mute_word_from_pref(agent: &BskyAgent, mute_word: String) -> Result<()> {
-    let mut preferences = get_preferences(agent).await?;
-    for preference in &mut preferences {
-        match preference {
-            Union::Refs(ref mut preference_item) => {
-                if let PreferencesItem::MutedWordsPref(ref mut mute_words_pref) = preference_item {
-                    mute_words_pref.items.retain(|word| word.value != mute_word);
-                }
-            }
-            Union::Unknown(_b) => {}
+/// Returns the items of the muted words preference, adding an empty
+/// preference when the user has none yet.
+fn muted_words_mut(preferences: &mut Preferences) -> &mut Vec<MutedWord> {
+    let position = preferences.iter().position(|preference| {
+        matches!(preference, Union::Refs(PreferencesItem::MutedWordsPref(_)))
+    });
+    let index = position.unwrap_or_else(|| {
+        preferences.push(Union::Refs(PreferencesItem::MutedWordsPref(Box::new(
+            MutedWordsPrefData { items: Vec::new() }.into(),
+        ))));
+        preferences.len() - 1
+    });
+    match &mut preferences[index] {
+        Union::Refs(PreferencesItem::MutedWordsPref(pref)) => &mut pref.items,
+        _ => unreachable!("index points at the muted words preference"),
+    }
+}
+
+fn extra_string<'a>(word: &'a MutedWord, key: &str) -> Option<&'a str> {
+    match &word.extra_data {
+        Ipld::Map(map) => match map.get(key) {
+            Some(Ipld::String(value)) => Some(value.as_str()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether `word` was added by this service for `value`.
+fn is_own_muted_word(word: &MutedWord, value: &str) -> bool {
+    extra_string(word, "id").is_some_and(|id| id.starts_with(MUTED_WORD_ID_PREFIX))
+        && normalize_word(word.value.as_str()) == normalize_word(value)
+}
+
+/// Whether `word` mutes at least the posts of `targets` and `actor_target`.
+/// A word without `actorTarget` mutes posts from everyone.
+fn covers(word: &MutedWord, targets: &[String], actor_target: &str) -> bool {
+    let word_actor_target = extra_string(word, "actorTarget").unwrap_or("all");
+    targets.iter().all(|target| word.targets.contains(target))
+        && (word_actor_target == "all" || word_actor_target == actor_target)
+}
+
+/// Whether the preference already mutes `value` as requested: either this
+/// service's word matches it exactly, or a word the user muted covers it.
+fn has_muted_word(
+    words: &[MutedWord],
+    value: &str,
+    targets: &[String],
+    actor_target: &str,
+) -> bool {
+    words.iter().any(|word| {
+        if normalize_word(word.value.as_str()) != normalize_word(value) {
+            return false;
         }
+        if !is_own_muted_word(word, value) {
+            return covers(word, targets, actor_target);
+        }
+        word.value == value
+            && word.targets == targets
+            && extra_string(word, "actorTarget") == Some(actor_target)
+    })
+}
+
+fn upsert_muted_word(
+    words: &mut Vec<MutedWord>,
+    value: &str,
+    targets: &[String],
+    actor_target: &str,
+) {
+    let id = words
+        .iter()
+        .find(|w| is_own_muted_word(w, value))
+        .and_then(|w| extra_string(w, "id"))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}{:016x}", MUTED_WORD_ID_PREFIX, rand::random::<u64>()));
+    words.retain(|w| !is_own_muted_word(w, value));
+    // id and actorTarget are newer than the lexicon atrium was generated
+    // from, so they are sent as extra fields.
+    words.push(MutedWord {
+        data: MutedWordData {
+            targets: targets.to_vec(),
+            value: value.to_string(),
+        },
+        extra_data: Ipld::Map(BTreeMap::from([
+            ("id".to_string(), Ipld::String(id)),
+            (
+                "actorTarget".to_string(),
+                Ipld::String(actor_target.to_string()),
+            ),
+        ])),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_word(value: &str) -> MutedWord {
+        MutedWordData {
+            targets: vec!["content".to_string()],
+            value: value.to_string(),
+        }
+        .into()
+    }
+
+    fn targets() -> Vec<String> {
+        vec!["tag".to_string(), "content".to_string()]
+    }
+
+    fn xrpc_error(status: StatusCode, error: &str) -> XrpcError<()> {
+        use bsky_sdk::api::xrpc::error::{ErrorResponseBody, XrpcError as XrpcErrorResponse};
+        XrpcError::XrpcResponse(XrpcErrorResponse {
+            status,
+            error: Some(XrpcErrorKind::Undefined(ErrorResponseBody {
+                error: Some(error.to_string()),
+                message: None,
+            })),
+        })
+    }
+
+    #[test]
+    fn test_unsaved_session_backoff() {
+        let did = "did:plc:unsaved";
+        assert!(unsaved_session(did, 1000).is_none());
+        UNSAVED_SESSIONS
+            .lock()
+            .unwrap()
+            .insert(did.to_string(), (1000, "disk full".to_string()));
+        assert!(unsaved_session(did, 1000).is_some());
+        assert!(unsaved_session(did, 1000 + SESSION_SAVE_BACKOFF - 1).is_some());
+        assert!(unsaved_session(did, 1000 + SESSION_SAVE_BACKOFF).is_none());
+        assert!(unsaved_session("did:plc:other", 1000).is_none());
+    }
+
+    #[test]
+    fn test_refresh_rejection() {
+        let expired = xrpc_error(StatusCode::BAD_REQUEST, "ExpiredToken");
+        assert!(is_auth_failure(&expired));
+        assert!(is_token_rejected(&expired));
+        assert!(is_token_rejected(&xrpc_error(
+            StatusCode::UNAUTHORIZED,
+            "InvalidToken"
+        )));
+
+        // Rate limits and outages keep the session and never fall back to
+        // the password.
+        let limited = xrpc_error(StatusCode::TOO_MANY_REQUESTS, "RateLimitExceeded");
+        assert!(!is_auth_failure(&limited));
+        assert!(!is_token_rejected(&limited));
+        let down = xrpc_error(StatusCode::BAD_GATEWAY, "UpstreamFailure");
+        assert!(!is_auth_failure(&down));
+        assert!(!is_token_rejected(&down));
+        assert!(!is_token_rejected(&XrpcError::<()>::UnexpectedResponseType));
+        assert!(!is_token_rejected(&xrpc_error(
+            StatusCode::UNAUTHORIZED,
+            "AuthMissing"
+        )));
+    }
+
+    #[test]
+    fn test_muted_words_pref_created_when_missing() {
+        let mut preferences: Preferences = Vec::new();
+        muted_words_mut(&mut preferences).push(app_word("spoiler"));
+        muted_words_mut(&mut preferences).push(app_word("leak"));
+        assert_eq!(preferences.len(), 1);
+        assert_eq!(muted_words_mut(&mut preferences).len(), 2);
+    }
+
+    #[test]
+    fn test_upsert_muted_word() {
+        let mut words = vec![app_word("Spoiler")];
+        // A word the user muted themselves covers only its own targets.
+        assert!(has_muted_word(
+            &words,
+            "spoiler",
+            &["content".to_string()],
+            "exclude-following"
+        ));
+        assert!(!has_muted_word(&words, "spoiler", &targets(), "all"));
+        words[0].targets = targets();
+        words[0].extra_data = Ipld::Map(BTreeMap::from([(
+            "actorTarget".to_string(),
+            Ipld::String("exclude-following".to_string()),
+        )]));
+        assert!(has_muted_word(
+            &words,
+            "spoiler",
+            &targets(),
+            "exclude-following"
+        ));
+        assert!(!has_muted_word(&words, "spoiler", &targets(), "all"));
+        upsert_muted_word(&mut words, "spoiler", &targets(), "all");
+        assert!(has_muted_word(&words, "spoiler", &targets(), "all"));
+        words.pop();
+
+        upsert_muted_word(&mut words, "leak", &targets(), "all");
+        assert!(has_muted_word(&words, "leak", &targets(), "all"));
+        assert!(!has_muted_word(
+            &words,
+            "leak",
+            &targets(),
+            "exclude-following"
+        ));
+        let id = extra_string(&words[1], "id").unwrap().to_string();
+        assert!(id.starts_with(MUTED_WORD_ID_PREFIX));
+
+        // Updating keeps a single entry and its id.
+        upsert_muted_word(&mut words, "LEAK", &targets(), "exclude-following");
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[1].value, "LEAK");
+        assert_eq!(extra_string(&words[1], "id"), Some(id.as_str()));
+        assert!(has_muted_word(
+            &words,
+            "LEAK",
+            &targets(),
+            "exclude-following"
+        ));
+    }
+
+    #[test]
+    fn test_only_own_muted_words_are_removed() {
+        let mut words = vec![app_word("spoiler")];
+        upsert_muted_word(&mut words, "Spoiler", &targets(), "all");
+        assert_eq!(words.len(), 2);
+
+        words.retain(|w| !is_own_muted_word(w, "SPOILER"));
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].value, "spoiler");
+        assert!(!is_own_muted_word(&words[0], "spoiler"));
     }
-    put_preferences(agent, preferences).await
 }
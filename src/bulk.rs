@@ -0,0 +1,251 @@
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use bsky_sdk::api::app::bsky::actor::defs::ProfileViewDetailed;
+use bsky_sdk::api::types::string::AtIdentifier;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use utoipa::ToSchema;
+
+use crate::agent::{get_agent_for_profile, get_profiles};
+use crate::error::AppError;
+use crate::event::EventSource;
+use crate::expiration::{resolve_expiration, ExpirationLength};
+use crate::extract::Json;
+use crate::helper::fetch_profile_v1;
+use crate::identifier::parse_actor;
+use crate::tmute::{apply_timed_mute, get_user_id, CreateOutcome, OnConflict};
+use crate::{DBPool, APPLICATION_JSON};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Most accounts one bulk request may mute.
+const MAX_ACTORS: usize = 100;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct BulkTimedMuteItem {
+    /// Handle, DID or `bsky.app` profile link of the account to mute.
+    pub actor: String,
+    /// Overrides the request's duration for this account.
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct BulkCreateTimedMuteRequest {
+    pub actors: Vec<BulkTimedMuteItem>,
+    /// Duration for the accounts that don't set their own.
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// What to do when an account already has an active timed mute; defaults to `extend`.
+    #[serde(default)]
+    pub on_conflict: Option<OnConflict>,
+}
+
+/// Outcome for one account of a bulk request. Either `outcome` and
+/// `expiration_date`, or `error`, are set.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct BulkTimedMuteResult {
+    /// The handle or DID as sent.
+    pub actor: String,
+    pub did: Option<String>,
+    pub outcome: Option<CreateOutcome>,
+    pub expiration_date: Option<i64>,
+    pub error: Option<String>,
+}
+
+impl BulkTimedMuteResult {
+    pub(crate) fn failed(actor: &str, did: Option<String>, error: AppError) -> Self {
+        Self {
+            actor: actor.to_string(),
+            did,
+            outcome: None,
+            expiration_date: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct BulkCreateTimedMuteResponse {
+    /// One entry per requested account, in request order.
+    pub results: Vec<BulkTimedMuteResult>,
+}
+
+/// Returns the expiration for one item, from its own duration if it has one
+/// and from the request's otherwise.
+fn item_expiration(
+    item: &BulkTimedMuteItem,
+    req: &BulkCreateTimedMuteRequest,
+    now: i64,
+) -> Result<i64> {
+    if item.expiration_length.is_some() || item.expires_at.is_some() {
+        resolve_expiration(
+            now,
+            item.expiration_length.as_ref(),
+            item.expires_at.as_deref(),
+        )
+    } else {
+        resolve_expiration(
+            now,
+            req.expiration_length.as_ref(),
+            req.expires_at.as_deref(),
+        )
+    }
+}
+
+pub(crate) fn find_profile<'a>(
+    profiles: &'a [ProfileViewDetailed],
+    actor: &AtIdentifier,
+) -> Option<&'a ProfileViewDetailed> {
+    profiles.iter().find(|profile| match actor {
+        AtIdentifier::Did(did) => profile.did == *did,
+        AtIdentifier::Handle(handle) => profile
+            .handle
+            .as_str()
+            .eq_ignore_ascii_case(handle.as_str()),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/timed-mutes/bulk",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Result for each account", body = BulkCreateTimedMuteResponse),
+        (status=400, description="No accounts, or too many"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn create_bulk(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<BulkCreateTimedMuteRequest>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    if req.actors.is_empty() || req.actors.len() > MAX_ACTORS {
+        return Err(AppError::BadRequest(format!(
+            "actors must list between 1 and {} accounts",
+            MAX_ACTORS
+        )));
+    }
+    let create_time = chrono::offset::Utc::now().timestamp();
+    let items: Vec<Result<(AtIdentifier, i64)>> = req
+        .actors
+        .iter()
+        .map(|item| {
+            Ok((
+                parse_actor(&item.actor)?,
+                item_expiration(item, &req, create_time)?,
+            ))
+        })
+        .collect();
+
+    // One sign in and one profile lookup per 25 accounts for the whole request.
+    let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
+    let profile = profile_list.first().ok_or(AppError::NotFound)?;
+    let agent = get_agent_for_profile(profile).await?;
+    let mut actors: Vec<AtIdentifier> = Vec::new();
+    for (actor, _) in items.iter().flatten() {
+        if !actors.contains(actor) {
+            actors.push(actor.clone());
+        }
+    }
+    let profiles = get_profiles(&agent, actors).await?;
+
+    let on_conflict = req.on_conflict.unwrap_or_default();
+    let mut results = Vec::with_capacity(items.len());
+    for (item, parsed) in req.actors.iter().zip(items) {
+        let (actor, expire_time) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                results.push(BulkTimedMuteResult::failed(&item.actor, None, e));
+                continue;
+            }
+        };
+        let Some(muted) = find_profile(&profiles, &actor) else {
+            results.push(BulkTimedMuteResult::failed(
+                &item.actor,
+                None,
+                AppError::BadRequest(format!("Profile not found: {}", item.actor)),
+            ));
+            continue;
+        };
+        let did = Some(muted.did.to_string());
+        let result = match apply_timed_mute(
+            &mut conn,
+            &agent,
+            user_id.as_str(),
+            EventSource::Api,
+            muted,
+            create_time,
+            expire_time,
+            on_conflict,
+        )
+        .await
+        {
+            Ok(response) => BulkTimedMuteResult {
+                actor: item.actor.clone(),
+                did,
+                outcome: Some(response.outcome),
+                expiration_date: Some(response.expiration_date),
+                error: None,
+            },
+            Err(e) => BulkTimedMuteResult::failed(&item.actor, did, e),
+        };
+        results.push(result);
+    }
+
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(BulkCreateTimedMuteResponse { results }),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(actor: &str, expiration_length: Option<i64>) -> BulkTimedMuteItem {
+        BulkTimedMuteItem {
+            actor: actor.to_string(),
+            expiration_length: expiration_length.map(ExpirationLength::Seconds),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_item_expiration() {
+        let req = BulkCreateTimedMuteRequest {
+            actors: vec![item("a.bsky.social", None), item("b.bsky.social", Some(60))],
+            expiration_length: Some(ExpirationLength::Text("1h".to_string())),
+            expires_at: None,
+            on_conflict: None,
+        };
+        assert_eq!(item_expiration(&req.actors[0], &req, 1000).unwrap(), 4600);
+        assert_eq!(item_expiration(&req.actors[1], &req, 1000).unwrap(), 1060);
+
+        let no_shared = BulkCreateTimedMuteRequest {
+            expiration_length: None,
+            ..req
+        };
+        assert!(item_expiration(&no_shared.actors[0], &no_shared, 1000).is_err());
+        assert_eq!(
+            item_expiration(&no_shared.actors[1], &no_shared, 1000).unwrap(),
+            1060
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::env;
+use std::sync::OnceLock;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD_NO_PAD as BASE64;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Prefix of every value sealed by this module. Values without it are treated
+/// as legacy plain text until `rotate-encryption-key` rewrites them.
+const ENVELOPE_PREFIX: &str = "enc:v1";
+const NONCE_LEN: usize = 12;
+
+static KEYRING: OnceLock<Keyring> = OnceLock::new();
+
+/// A master key used to wrap the per-row data keys.
+struct MasterKey {
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+impl MasterKey {
+    fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = BASE64
+            .decode(encoded.trim().trim_end_matches('='))
+            .map_err(|e| AppError::EncryptionError(e.to_string()))?;
+        if bytes.len() != 32 {
+            return Err(AppError::EncryptionError(
+                "master key must be 32 bytes".to_string(),
+            ));
+        }
+        let id = Sha256::digest(&bytes)
+            .iter()
+            .take(4)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Ok(Self {
+            id,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes)),
+        })
+    }
+}
+
+/// The current master key plus the one it replaced, if a rotation is in progress.
+struct Keyring {
+    current: MasterKey,
+    previous: Option<MasterKey>,
+}
+
+impl Keyring {
+    fn find(&self, key_id: &str) -> Result<&MasterKey> {
+        if self.current.id == key_id {
+            return Ok(&self.current);
+        }
+        self.previous
+            .as_ref()
+            .filter(|k| k.id == key_id)
+            .ok_or_else(|| AppError::EncryptionError(format!("unknown master key {}", key_id)))
+    }
+
+    fn seal(&self, plaintext: &str) -> Result<String> {
+        let data_key = Aes256Gcm::generate_key(OsRng);
+        let data_cipher = Aes256Gcm::new(&data_key);
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = data_cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|e| AppError::EncryptionError(e.to_string()))?;
+        let wrapped_key = wrap(&self.current, &data_key)?;
+        Ok(format!(
+            "{}:{}:{}:{}:{}",
+            ENVELOPE_PREFIX,
+            self.current.id,
+            BASE64.encode(wrapped_key),
+            BASE64.encode(nonce),
+            BASE64.encode(ciphertext)
+        ))
+    }
+
+    fn open(&self, value: &str) -> Result<String> {
+        let Some(envelope) = Envelope::parse(value)? else {
+            return Ok(value.to_string());
+        };
+        let data_key = unwrap(self.find(envelope.key_id)?, &envelope.wrapped_key)?;
+        let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key))
+            .decrypt(
+                Nonce::from_slice(&envelope.nonce),
+                envelope.ciphertext.as_ref(),
+            )
+            .map_err(|e| AppError::EncryptionError(e.to_string()))?;
+        String::from_utf8(plaintext).map_err(|e| AppError::EncryptionError(e.to_string()))
+    }
+
+    /// Re-wraps the data key under the current master key. The data itself is
+    /// only re-encrypted when the value is still stored as plain text.
+    fn rewrap(&self, value: &str) -> Result<String> {
+        let Some(envelope) = Envelope::parse(value)? else {
+            return self.seal(value);
+        };
+        if envelope.key_id == self.current.id {
+            return Ok(value.to_string());
+        }
+        let data_key = unwrap(self.find(envelope.key_id)?, &envelope.wrapped_key)?;
+        let wrapped_key = wrap(&self.current, Key::<Aes256Gcm>::from_slice(&data_key))?;
+        Ok(format!(
+            "{}:{}:{}:{}:{}",
+            ENVELOPE_PREFIX,
+            self.current.id,
+            BASE64.encode(wrapped_key),
+            BASE64.encode(envelope.nonce),
+            BASE64.encode(envelope.ciphertext)
+        ))
+    }
+
+    fn needs_rewrap(&self, value: &str) -> bool {
+        !matches!(Envelope::parse(value), Ok(Some(e)) if e.key_id == self.current.id)
+    }
+}
+
+struct Envelope<'a> {
+    key_id: &'a str,
+    wrapped_key: Vec<u8>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+impl<'a> Envelope<'a> {
+    fn parse(value: &'a str) -> Result<Option<Self>> {
+        let Some(rest) = value.strip_prefix(ENVELOPE_PREFIX) else {
+            return Ok(None);
+        };
+        let parts: Vec<&str> = rest.trim_start_matches(':').split(':').collect();
+        let [key_id, wrapped_key, nonce, ciphertext] = parts.as_slice() else {
+            return Err(AppError::EncryptionError("malformed envelope".to_string()));
+        };
+        let decode = |part: &str| {
+            BASE64
+                .decode(part)
+                .map_err(|e| AppError::EncryptionError(e.to_string()))
+        };
+        let nonce = decode(nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(AppError::EncryptionError("malformed envelope".to_string()));
+        }
+        Ok(Some(Self {
+            key_id,
+            wrapped_key: decode(wrapped_key)?,
+            nonce,
+            ciphertext: decode(ciphertext)?,
+        }))
+    }
+}
+
+fn wrap(master: &MasterKey, data_key: &Key<Aes256Gcm>) -> Result<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let mut wrapped = nonce.to_vec();
+    wrapped.extend(
+        master
+            .cipher
+            .encrypt(&nonce, data_key.as_slice())
+            .map_err(|e| AppError::EncryptionError(e.to_string()))?,
+    );
+    Ok(wrapped)
+}
+
+fn unwrap(master: &MasterKey, wrapped: &[u8]) -> Result<Vec<u8>> {
+    if wrapped.len() <= NONCE_LEN {
+        return Err(AppError::EncryptionError("malformed data key".to_string()));
+    }
+    let (nonce, ciphertext) = wrapped.split_at(NONCE_LEN);
+    master
+        .cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|e| AppError::EncryptionError(e.to_string()))
+}
+
+fn keyring() -> Result<&'static Keyring> {
+    KEYRING.get().ok_or_else(|| {
+        AppError::EncryptionError("encryption keys have not been loaded".to_string())
+    })
+}
+
+/// Loads `ENCRYPTION_KEY` and, during a rotation, `ENCRYPTION_KEY_PREVIOUS`.
+/// Both are base64 encoded 32 byte keys.
+pub fn init() -> Result<()> {
+    let current = env::var("ENCRYPTION_KEY")
+        .map_err(|_| AppError::EncryptionError("ENCRYPTION_KEY is not set".to_string()))?;
+    let previous = match env::var("ENCRYPTION_KEY_PREVIOUS") {
+        Ok(key) if !key.is_empty() => Some(MasterKey::from_base64(key.as_str())?),
+        _ => None,
+    };
+    let keyring = Keyring {
+        current: MasterKey::from_base64(current.as_str())?,
+        previous,
+    };
+    KEYRING
+        .set(keyring)
+        .map_err(|_| AppError::EncryptionError("encryption keys already loaded".to_string()))
+}
+
+pub fn encrypt(plaintext: &str) -> Result<String> {
+    keyring()?.seal(plaintext)
+}
+
+pub fn decrypt(value: &str) -> Result<String> {
+    keyring()?.open(value)
+}
+
+pub fn reencrypt(value: &str) -> Result<String> {
+    keyring()?.rewrap(value)
+}
+
+pub fn needs_reencryption(value: &str) -> Result<bool> {
+    Ok(keyring()?.needs_rewrap(value))
+}
+
+#[cfg(test)]
+pub fn install_test_keyring() {
+    KEYRING.get_or_init(|| Keyring {
+        current: MasterKey::from_base64(tests::KEY_A).unwrap(),
+        previous: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub const KEY_A: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8";
+    const KEY_B: &str = "HyAhIiMkJSYnKCkqKywtLi8wMTIzNDU2Nzg5Ojs8PT4";
+
+    fn keyring(current: &str, previous: Option<&str>) -> Keyring {
+        Keyring {
+            current: MasterKey::from_base64(current).unwrap(),
+            previous: previous.map(|k| MasterKey::from_base64(k).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let keys = keyring(KEY_A, None);
+        let sealed = keys.seal("hunter2").unwrap();
+        assert!(sealed.starts_with(ENVELOPE_PREFIX));
+        assert!(!sealed.contains("hunter2"));
+        assert_eq!(keys.open(sealed.as_str()).unwrap(), "hunter2");
+
+        // Every value gets its own data key and nonce.
+        assert_ne!(sealed, keys.seal("hunter2").unwrap());
+    }
+
+    #[test]
+    fn test_open_legacy_plaintext() {
+        let keys = keyring(KEY_A, None);
+        assert_eq!(keys.open("plain").unwrap(), "plain");
+        assert!(keys.needs_rewrap("plain"));
+    }
+
+    #[test]
+    fn test_rotation() {
+        let old_keys = keyring(KEY_A, None);
+        let sealed = old_keys.seal("hunter2").unwrap();
+
+        let new_keys = keyring(KEY_B, Some(KEY_A));
+        assert!(new_keys.needs_rewrap(sealed.as_str()));
+        let rewrapped = new_keys.rewrap(sealed.as_str()).unwrap();
+        assert!(!new_keys.needs_rewrap(rewrapped.as_str()));
+        assert_eq!(new_keys.open(rewrapped.as_str()).unwrap(), "hunter2");
+
+        let only_new = keyring(KEY_B, None);
+        assert!(only_new.open(sealed.as_str()).is_err());
+        assert_eq!(only_new.open(rewrapped.as_str()).unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn test_bad_key_length() {
+        assert!(MasterKey::from_base64("AAEC").is_err());
+    }
+}
@@ -6,6 +6,8 @@ use derive_more::Display;
 use diesel::r2d2;
 use serde_json::json;
 
+use crate::status::MuteStatus;
+
 #[derive(Debug, Display)]
 pub enum AppError {
     #[display("Database error: {_0}")]
@@ -14,17 +16,29 @@ pub enum AppError {
     #[display("Pool error: {_0}")]
     PoolError(String),
 
+    #[display("Encryption error: {_0}")]
+    EncryptionError(String),
+
     #[display("Bskysdk error: {_0}")]
     BskyError(String),
 
+    #[display("Bad request: {_0}")]
+    BadRequest(String),
+
     #[display("Not authorized")]
     Unauthorized,
 
+    #[display("Forbidden")]
+    Forbidden,
+
     #[display("Internal server error")]
     InternalError,
 
     #[display("Not found")]
     NotFound,
+
+    #[display("Cannot change status from {_0} to {_1}")]
+    InvalidTransition(MuteStatus, MuteStatus),
 }
 
 impl std::error::Error for AppError {
@@ -39,12 +53,15 @@ impl std::error::Error for AppError {
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
-            AppError::DatabaseError(_) | AppError::InternalError | AppError::PoolError(_) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
-            }
-            AppError::BskyError(e) => (StatusCode::BAD_REQUEST, e),
+            AppError::DatabaseError(_)
+            | AppError::InternalError
+            | AppError::PoolError(_)
+            | AppError::EncryptionError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
+            AppError::BskyError(e) | AppError::BadRequest(e) => (StatusCode::BAD_REQUEST, e),
             AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden".to_string()),
             AppError::NotFound => (StatusCode::NOT_FOUND, "Not found".to_string()),
+            AppError::InvalidTransition(_, _) => (StatusCode::CONFLICT, self.to_string()),
         };
 
         let body = axum::Json(json!({
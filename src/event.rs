@@ -0,0 +1,291 @@
+use axum::extract::{Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::AppError;
+use crate::expiration::parse_timestamp;
+use crate::helper::{create_mute_event, fetch_mute_events, EventFilter};
+use crate::history::{page, paginate};
+use crate::models::{MuteEvent, NewMuteEvent};
+use crate::tmute::get_user_id;
+use crate::{DBPool, APPLICATION_JSON};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Where an action on a timed entry came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EventSource {
+    /// A request from the user.
+    Api,
+    /// The expiry resolver.
+    Scheduler,
+    /// An admin acting for the user.
+    Admin,
+    /// Reconciliation with the account's Bluesky state.
+    Reconciliation,
+}
+
+impl EventSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventSource::Api => "api",
+            EventSource::Scheduler => "scheduler",
+            EventSource::Admin => "admin",
+            EventSource::Reconciliation => "reconciliation",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventAction {
+    Create,
+    Delete,
+    Extend,
+    /// Undone on Bluesky after it expired.
+    Expire,
+    /// Undoing it on Bluesky failed; it is retried or marked failed.
+    ExpireFailed,
+    /// Found removed on Bluesky outside this service. With an error, the
+    /// reconciliation of the account failed instead.
+    Drift,
+    /// A scheduled entry was put in effect on Bluesky at its start time. With
+    /// an error, starting it failed; it is retried or marked failed.
+    Start,
+}
+
+impl EventAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventAction::Create => "create",
+            EventAction::Delete => "delete",
+            EventAction::Extend => "extend",
+            EventAction::Expire => "expire",
+            EventAction::ExpireFailed => "expire_failed",
+            EventAction::Drift => "drift",
+            EventAction::Start => "start",
+        }
+    }
+}
+
+/// Which kind of timed entry an event is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    Mute,
+    Word,
+    Block,
+    ThreadMute,
+    ListMute,
+}
+
+impl EntryKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryKind::Mute => "mute",
+            EntryKind::Word => "word",
+            EntryKind::Block => "block",
+            EntryKind::ThreadMute => "thread_mute",
+            EntryKind::ListMute => "list_mute",
+        }
+    }
+}
+
+/// An audit log entry about to be written.
+pub struct Event<'a> {
+    pub actor: &'a str,
+    pub source: EventSource,
+    pub action: EventAction,
+    pub kind: EntryKind,
+    pub target: &'a str,
+    pub entry_id: Option<i32>,
+    /// `None` when no Bluesky call was made for the action.
+    pub bsky_result: Option<std::result::Result<(), String>>,
+}
+
+impl<'a> Event<'a> {
+    pub fn new(
+        actor: &'a str,
+        source: EventSource,
+        action: EventAction,
+        kind: EntryKind,
+        target: &'a str,
+    ) -> Self {
+        Self {
+            actor,
+            source,
+            action,
+            kind,
+            target,
+            entry_id: None,
+            bsky_result: None,
+        }
+    }
+
+    pub fn entry(mut self, id: i32) -> Self {
+        self.entry_id = Some(id);
+        self
+    }
+
+    /// Keeps the result of the Bluesky call made for the action, if any.
+    pub fn bsky<T, E: ToString>(mut self, result: Option<&std::result::Result<T, E>>) -> Self {
+        self.bsky_result = result.map(|result| result.as_ref().map(|_| ()).map_err(E::to_string));
+        self
+    }
+
+    /// Writes the event. A failure to write it is ignored so the audit log
+    /// never stops the action itself.
+    pub fn record(self, conn: &mut SqliteConnection) {
+        let (result, error) = match &self.bsky_result {
+            None => ("none", None),
+            Some(Ok(())) => ("ok", None),
+            Some(Err(e)) => ("error", Some(e.as_str())),
+        };
+        let now = chrono::offset::Utc::now().timestamp();
+        let _ = create_mute_event(
+            conn,
+            &NewMuteEvent {
+                actor: self.actor,
+                source: self.source.as_str(),
+                action: self.action.as_str(),
+                kind: self.kind.as_str(),
+                target: self.target,
+                entry_id: self.entry_id,
+                result,
+                error,
+                created_date: &now,
+            },
+        );
+    }
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventQuery {
+    /// Only for `/admin/events`: events of this user's DID.
+    pub actor: Option<String>,
+    pub source: Option<EventSource>,
+    pub action: Option<EventAction>,
+    pub kind: Option<EntryKind>,
+    /// DID, word, thread or list URI the event is about.
+    pub target: Option<String>,
+    /// RFC3339 timestamp; only events at or after it.
+    pub since: Option<String>,
+    /// RFC3339 timestamp; only events at or before it.
+    pub until: Option<String>,
+    /// `cursor` returned with the previous page.
+    pub cursor: Option<String>,
+    /// Page size, up to 100. Defaults to 50.
+    pub limit: Option<i64>,
+}
+
+impl EventQuery {
+    pub(crate) fn filter<'a>(&'a self, actor: Option<&'a str>) -> Result<EventFilter<'a>> {
+        let (before_id, limit) = page(self.cursor.as_deref(), self.limit)?;
+        Ok(EventFilter {
+            actor,
+            source: self.source.map(EventSource::as_str),
+            action: self.action.map(EventAction::as_str),
+            kind: self.kind.map(EntryKind::as_str),
+            target: self.target.as_deref(),
+            since: self.since.as_deref().map(parse_timestamp).transpose()?,
+            until: self.until.as_deref().map(parse_timestamp).transpose()?,
+            before_id,
+            limit,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct MuteEvents {
+    pub events: Vec<MuteEvent>,
+    /// Pass as `cursor` to get the next page; absent on the last page.
+    pub cursor: Option<String>,
+}
+
+impl MuteEvents {
+    pub(crate) fn fetch(conn: &mut SqliteConnection, filter: &EventFilter) -> Self {
+        let mut events = fetch_mute_events(conn, filter);
+        let cursor = paginate(&mut events, filter.limit, |e| e.id);
+        Self { events, cursor }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(
+        ("bskytools" = String, Cookie,),
+        EventQuery
+    ),
+    responses(
+        (status=200, description="Audit log of the signed in user's timed entries, newest first", body = MuteEvents),
+        (status=400, description="Invalid filter or cursor"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_events(
+    session: Session,
+    State(pool): State<DBPool>,
+    Query(query): Query<EventQuery>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let filter = query.filter(Some(user_id.as_str()))?;
+    let events = MuteEvents::fetch(&mut conn, &filter);
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(events),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_bsky_result() {
+        let event = Event::new(
+            "did:plc:a",
+            EventSource::Api,
+            EventAction::Delete,
+            EntryKind::Mute,
+            "did:plc:x",
+        );
+        assert_eq!(event.bsky_result, None);
+
+        let error = AppError::BskyError("rate limited".to_string());
+        let message = error.to_string();
+        let event = event.entry(3).bsky(Some(&Err::<(), _>(error)));
+        assert_eq!(event.entry_id, Some(3));
+        assert_eq!(event.bsky_result, Some(Err(message)));
+
+        let event = event.bsky(Some(&Ok::<_, AppError>("at://record")));
+        assert_eq!(event.bsky_result, Some(Ok(())));
+    }
+
+    #[test]
+    fn test_event_query_filter() {
+        let query: EventQuery = serde_json::from_str(
+            r#"{"actor":"did:plc:b","source":"scheduler","action":"expire_failed","kind":"thread_mute"}"#,
+        )
+        .unwrap();
+        let filter = query.filter(Some("did:plc:a")).unwrap();
+        assert_eq!(filter.actor, Some("did:plc:a"));
+        assert_eq!(filter.source, Some("scheduler"));
+        assert_eq!(filter.action, Some("expire_failed"));
+        assert_eq!(filter.kind, Some("thread_mute"));
+
+        let admin = query.filter(query.actor.as_deref()).unwrap();
+        assert_eq!(admin.actor, Some("did:plc:b"));
+    }
+}
@@ -0,0 +1,365 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::AppError;
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// How long a timed entry should last: either raw seconds, a compact duration
+/// such as `3d12h`, or an ISO-8601 duration such as `P2DT3H`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum ExpirationLength {
+    Seconds(i64),
+    Text(String),
+}
+
+impl ExpirationLength {
+    pub fn to_seconds(&self) -> Result<i64> {
+        let seconds = match self {
+            ExpirationLength::Seconds(seconds) => *seconds,
+            ExpirationLength::Text(text) => parse_duration(text)?,
+        };
+        if seconds <= 0 {
+            return Err(AppError::BadRequest(
+                "expiration_length must be greater than zero".to_string(),
+            ));
+        }
+        Ok(seconds)
+    }
+
+    /// Like [`ExpirationLength::to_seconds`] but allows a leading `-` (or a
+    /// negative number) so the value can shorten an expiration.
+    pub fn to_signed_seconds(&self) -> Result<i64> {
+        let seconds = match self {
+            ExpirationLength::Seconds(seconds) => *seconds,
+            ExpirationLength::Text(text) => {
+                let text = text.trim();
+                match text.strip_prefix('-') {
+                    Some(rest) => -parse_duration(rest)?,
+                    None => parse_duration(text.strip_prefix('+').unwrap_or(text))?,
+                }
+            }
+        };
+        if seconds == 0 {
+            return Err(AppError::BadRequest("delta must not be zero".to_string()));
+        }
+        Ok(seconds)
+    }
+}
+
+/// Returns the absolute expiration timestamp for a create request. Exactly one
+/// of `expiration_length` and `expires_at` (an RFC3339 timestamp) must be set,
+/// and the result must be after `now`.
+pub fn resolve_expiration(
+    now: i64,
+    expiration_length: Option<&ExpirationLength>,
+    expires_at: Option<&str>,
+) -> Result<i64> {
+    let expiration_date = match (expiration_length, expires_at) {
+        (Some(length), None) => now
+            .checked_add(length.to_seconds()?)
+            .ok_or_else(|| AppError::BadRequest("expiration_length is too large".to_string()))?,
+        (None, Some(expires_at)) => parse_timestamp(expires_at)?,
+        (Some(_), Some(_)) => {
+            return Err(AppError::BadRequest(
+                "Only one of expiration_length and expires_at may be set".to_string(),
+            ))
+        }
+        (None, None) => {
+            return Err(AppError::BadRequest(
+                "One of expiration_length and expires_at is required".to_string(),
+            ))
+        }
+    };
+    if expiration_date <= now {
+        return Err(AppError::BadRequest(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+    Ok(expiration_date)
+}
+
+/// Returns the start and expiration timestamps for a create request. Without
+/// `starts_at`, or with one that has passed, the entry starts at `now`. An
+/// `expiration_length` counts from the start.
+pub fn resolve_schedule(
+    now: i64,
+    starts_at: Option<&str>,
+    expiration_length: Option<&ExpirationLength>,
+    expires_at: Option<&str>,
+) -> Result<(i64, i64)> {
+    let start_date = match starts_at {
+        Some(starts_at) => parse_timestamp(starts_at)?.max(now),
+        None => now,
+    };
+    if let Some(expires_at) = expires_at.filter(|_| start_date > now) {
+        if parse_timestamp(expires_at)? <= start_date {
+            return Err(AppError::BadRequest(
+                "expires_at must be after starts_at".to_string(),
+            ));
+        }
+    }
+    let expiration_date = resolve_expiration(start_date, expiration_length, expires_at)?;
+    Ok((start_date, expiration_date))
+}
+
+/// Returns the new expiration timestamp for an extend request. Exactly one of
+/// `delta` (added to `current`, may be negative) and `expires_at` must be set,
+/// and the result must be after `now`.
+pub fn resolve_new_expiration(
+    now: i64,
+    current: i64,
+    delta: Option<&ExpirationLength>,
+    expires_at: Option<&str>,
+) -> Result<i64> {
+    let expiration_date = match (delta, expires_at) {
+        (Some(delta), None) => current
+            .checked_add(delta.to_signed_seconds()?)
+            .ok_or_else(|| AppError::BadRequest("delta is too large".to_string()))?,
+        (None, Some(expires_at)) => parse_timestamp(expires_at)?,
+        (Some(_), Some(_)) => {
+            return Err(AppError::BadRequest(
+                "Only one of delta and expires_at may be set".to_string(),
+            ))
+        }
+        (None, None) => {
+            return Err(AppError::BadRequest(
+                "One of delta and expires_at is required".to_string(),
+            ))
+        }
+    };
+    if expiration_date <= now {
+        return Err(AppError::BadRequest(
+            "The new expiration must be in the future".to_string(),
+        ));
+    }
+    Ok(expiration_date)
+}
+
+/// Parses an RFC3339 timestamp into unix seconds.
+pub fn parse_timestamp(text: &str) -> Result<i64> {
+    DateTime::parse_from_rfc3339(text.trim())
+        .map(|date| date.timestamp())
+        .map_err(|e| AppError::BadRequest(format!("Invalid timestamp {:?}: {}", text, e)))
+}
+
+/// Parses `3d12h`, `90m`, `3600` or ISO-8601 `P2DT3H` style durations into
+/// seconds. Years and months are rejected because their length varies.
+pub fn parse_duration(text: &str) -> Result<i64> {
+    let text = text.trim();
+    let invalid = || AppError::BadRequest(format!("Invalid duration {:?}", text));
+    if text.is_empty() {
+        return Err(invalid());
+    }
+    // Signs are left to the caller, so "--5" can't turn into +5.
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return text.parse().map_err(|_| invalid());
+    }
+
+    let upper = text.to_ascii_uppercase();
+    let (iso, body) = match upper.strip_prefix('P') {
+        Some(rest) => (true, rest),
+        None => (false, upper.as_str()),
+    };
+
+    let mut total: i64 = 0;
+    let mut number = String::new();
+    let mut in_time = !iso;
+    let mut seen_component = false;
+    // An ISO `T` must be followed by a time component.
+    let mut time_pending = false;
+    for c in body.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        if iso && c == 'T' && number.is_empty() && !in_time {
+            in_time = true;
+            time_pending = true;
+            continue;
+        }
+        if number.is_empty() {
+            return Err(invalid());
+        }
+        let unit = match (c, in_time) {
+            ('W', _) => 604_800,
+            ('D', _) => 86_400,
+            ('H', true) => 3_600,
+            ('M', true) => 60,
+            ('S', true) => 1,
+            ('Y', _) | ('M', false) => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid duration {:?}: years and months are not supported",
+                    text
+                )))
+            }
+            _ => return Err(invalid()),
+        };
+        let value: i64 = number.parse().map_err(|_| invalid())?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+        seen_component = true;
+        time_pending = false;
+    }
+    if !number.is_empty() || !seen_component || time_pending {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_compact_duration() {
+        assert_eq!(parse_duration("3600").unwrap(), 3600);
+        assert_eq!(parse_duration("45s").unwrap(), 45);
+        assert_eq!(parse_duration("90m").unwrap(), 5400);
+        assert_eq!(parse_duration("3d12h").unwrap(), 3 * 86_400 + 12 * 3_600);
+        assert_eq!(parse_duration("1w2d").unwrap(), 9 * 86_400);
+        assert_eq!(parse_duration(" 2H30M ").unwrap(), 9_000);
+    }
+
+    #[test]
+    fn test_parse_iso_duration() {
+        assert_eq!(parse_duration("P2DT3H").unwrap(), 2 * 86_400 + 3 * 3_600);
+        assert_eq!(parse_duration("PT30M").unwrap(), 1_800);
+        assert_eq!(parse_duration("P1W").unwrap(), 604_800);
+        assert_eq!(parse_duration("pt1h1m1s").unwrap(), 3_661);
+    }
+
+    #[test]
+    fn test_parse_invalid_duration() {
+        for text in [
+            "", "abc", "3x", "h3", "3d12", "P", "PT", "P1M", "P1Y", "P3H", "3dT4h", "P1DT", "-5",
+            "+5",
+        ] {
+            assert!(parse_duration(text).is_err(), "{} should be rejected", text);
+        }
+    }
+
+    #[test]
+    fn test_resolve_expiration() {
+        let now = 1_700_000_000;
+        let length = ExpirationLength::Text("1h".to_string());
+        assert_eq!(
+            resolve_expiration(now, Some(&length), None).unwrap(),
+            now + 3_600
+        );
+        assert_eq!(
+            resolve_expiration(now, Some(&ExpirationLength::Seconds(60)), None).unwrap(),
+            now + 60
+        );
+        assert_eq!(
+            resolve_expiration(now, None, Some("2023-11-14T22:13:21Z")).unwrap(),
+            now + 1
+        );
+    }
+
+    #[test]
+    fn test_resolve_expiration_rejects_bad_input() {
+        let now = 1_700_000_000;
+        assert!(resolve_expiration(now, Some(&ExpirationLength::Seconds(0)), None).is_err());
+        assert!(resolve_expiration(now, Some(&ExpirationLength::Seconds(-5)), None).is_err());
+        assert!(resolve_expiration(now, None, Some("2020-01-01T00:00:00Z")).is_err());
+        assert!(resolve_expiration(now, None, Some("tomorrow")).is_err());
+        assert!(resolve_expiration(now, None, None).is_err());
+        assert!(resolve_expiration(
+            now,
+            Some(&ExpirationLength::Seconds(60)),
+            Some("2030-01-01T00:00:00Z")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_resolve_schedule() {
+        let now = 1_700_000_000;
+        let length = ExpirationLength::Text("2h".to_string());
+        assert_eq!(
+            resolve_schedule(now, None, Some(&length), None).unwrap(),
+            (now, now + 7_200)
+        );
+        // The length counts from the start.
+        assert_eq!(
+            resolve_schedule(now, Some("2023-11-15T00:13:20Z"), Some(&length), None).unwrap(),
+            (now + 7_200, now + 14_400)
+        );
+        // A start in the past starts now.
+        assert_eq!(
+            resolve_schedule(now, Some("2020-01-01T00:00:00Z"), Some(&length), None).unwrap(),
+            (now, now + 7_200)
+        );
+        assert_eq!(
+            resolve_schedule(
+                now,
+                Some("2023-11-15T00:13:20Z"),
+                None,
+                Some("2023-11-15T01:13:20Z")
+            )
+            .unwrap(),
+            (now + 7_200, now + 10_800)
+        );
+        assert!(resolve_schedule(
+            now,
+            Some("2023-11-15T00:13:20Z"),
+            None,
+            Some("2023-11-14T23:13:20Z")
+        )
+        .is_err());
+        assert!(resolve_schedule(now, Some("tonight"), Some(&length), None).is_err());
+    }
+
+    #[test]
+    fn test_to_signed_seconds() {
+        let signed = |text: &str| ExpirationLength::Text(text.to_string()).to_signed_seconds();
+        assert_eq!(signed("-5").unwrap(), -5);
+        assert_eq!(signed("+5").unwrap(), 5);
+        assert_eq!(signed("-PT1H").unwrap(), -3_600);
+        for text in ["--5", "+-5", "-+5", "++5", "-", "-0", "P1DT", "-P1DT"] {
+            assert!(signed(text).is_err(), "{} should be rejected", text);
+        }
+    }
+
+    #[test]
+    fn test_resolve_new_expiration() {
+        let now = 1_700_000_000;
+        let current = now + 3_600;
+        let extend = ExpirationLength::Text("+1d".to_string());
+        assert_eq!(
+            resolve_new_expiration(now, current, Some(&extend), None).unwrap(),
+            current + 86_400
+        );
+        let shorten = ExpirationLength::Text("-30m".to_string());
+        assert_eq!(
+            resolve_new_expiration(now, current, Some(&shorten), None).unwrap(),
+            current - 1_800
+        );
+        assert_eq!(
+            resolve_new_expiration(now, current, None, Some("2023-11-14T22:13:21Z")).unwrap(),
+            now + 1
+        );
+
+        let too_short = ExpirationLength::Seconds(-3_600);
+        assert!(resolve_new_expiration(now, current, Some(&too_short), None).is_err());
+        assert!(
+            resolve_new_expiration(now, current, Some(&ExpirationLength::Seconds(0)), None)
+                .is_err()
+        );
+        assert!(resolve_new_expiration(now, current, None, None).is_err());
+    }
+
+    #[test]
+    fn test_expiration_length_deserialize() {
+        let seconds: ExpirationLength = serde_json::from_str("3600").unwrap();
+        assert_eq!(seconds, ExpirationLength::Seconds(3600));
+        let text: ExpirationLength = serde_json::from_str("\"3d12h\"").unwrap();
+        assert_eq!(text.to_seconds().unwrap(), 302_400);
+    }
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use axum::extract::{FromRequest, Request};
+
+use crate::error::AppError;
+
+/// JSON body extractor that rejects a malformed or mistyped body with
+/// `AppError::BadRequest` instead of axum's plain text 4xx responses, so
+/// every handler answers bad input with the same 400 JSON error.
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Json<T>
+where
+    axum::Json<T>: FromRequest<S, Rejection = axum::extract::rejection::JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        axum::Json::<T>::from_request(req, state)
+            .await
+            .map(|axum::Json(value)| Json(value))
+            .map_err(|rejection| AppError::BadRequest(rejection.body_text()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::header::CONTENT_TYPE;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Example {
+        #[allow(dead_code)]
+        id: i32,
+    }
+
+    fn request(body: &str) -> Request {
+        Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_json_rejection() {
+        assert!(Json::<Example>::from_request(request(r#"{"id": 1}"#), &())
+            .await
+            .is_ok());
+        for body in [r#"{"id": "#, r#"{"id": "one"}"#, r#"{}"#] {
+            match Json::<Example>::from_request(request(body), &()).await {
+                Err(AppError::BadRequest(_)) => {}
+                _ => panic!("{} should be a bad request", body),
+            }
+        }
+    }
+}
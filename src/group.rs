@@ -0,0 +1,603 @@
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use bsky_sdk::api::app::bsky::actor::defs::ProfileViewDetailed;
+use bsky_sdk::api::types::string::AtIdentifier;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use utoipa::ToSchema;
+
+use crate::agent::{get_agent_for_profile, get_profiles, Agent};
+use crate::bulk::find_profile;
+use crate::error::AppError;
+use crate::event::{EntryKind, EventSource};
+use crate::expiration::{resolve_expiration, ExpirationLength};
+use crate::extract::Json;
+use crate::helper::{
+    add_mute_group_members, assign_mute_group, count_unresolved_group_rows, create_mute_group,
+    delete_mute_group, fetch_active_timed_mute, fetch_active_timed_mute_word, fetch_mute_group,
+    fetch_mute_group_by_name, fetch_mute_group_members, fetch_mute_groups, fetch_profile_v1,
+    normalize_word, update_mute_group_expiration,
+};
+use crate::identifier::parse_actor;
+use crate::models::MuteGroup;
+use crate::tmute::{
+    apply_timed_mute, apply_timed_mute_word, get_user_id, mute_word_targets, CreateOutcome,
+    CreateTimedMuteResponse, MuteWordActorTarget, OnConflict,
+};
+use crate::{DBPool, APPLICATION_JSON};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Most members one group may have.
+const MAX_MEMBERS: usize = 100;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateMuteGroupRequest {
+    pub name: String,
+    /// Handles, DIDs or `bsky.app` profile links of the accounts to mute.
+    #[serde(default)]
+    pub actors: Vec<String>,
+    /// Words to mute, in tags and post text, from everyone.
+    #[serde(default)]
+    pub words: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct AddMuteGroupMembersRequest {
+    pub id: i32,
+    #[serde(default)]
+    pub actors: Vec<String>,
+    #[serde(default)]
+    pub words: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ActivateMuteGroupRequest {
+    pub id: i32,
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// What to do when a member already has an active timed mute; defaults to `extend`.
+    #[serde(default)]
+    pub on_conflict: Option<OnConflict>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeleteMuteGroupRequest {
+    pub id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct MuteGroupView {
+    pub id: i32,
+    pub name: String,
+    pub created_date: i64,
+    /// DIDs of the member accounts.
+    pub actors: Vec<String>,
+    pub words: Vec<String>,
+    /// Whether some of the group's timed mutes or words are still in effect.
+    pub active: bool,
+    /// Expiration of the last activation.
+    pub expiration_date: Option<i64>,
+}
+
+impl MuteGroupView {
+    fn fetch(conn: &mut SqliteConnection, group: MuteGroup) -> Self {
+        let mut actors = Vec::new();
+        let mut words = Vec::new();
+        for member in fetch_mute_group_members(conn, group.id) {
+            if member.kind == EntryKind::Word.as_str() {
+                words.push(member.target);
+            } else {
+                actors.push(member.target);
+            }
+        }
+        Self {
+            active: count_unresolved_group_rows(conn, group.id) > 0,
+            id: group.id,
+            name: group.name,
+            created_date: group.created_date,
+            actors,
+            words,
+            expiration_date: group.expiration_date,
+        }
+    }
+}
+
+/// Outcome for one member when a group is activated or a member is added to
+/// an active group. Either `outcome` and `expiration_date`, or `error`, are set.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct MuteGroupResult {
+    pub kind: EntryKind,
+    /// DID of the account, or the word.
+    pub target: String,
+    pub outcome: Option<CreateOutcome>,
+    pub expiration_date: Option<i64>,
+    pub error: Option<String>,
+}
+
+impl MuteGroupResult {
+    fn new(kind: EntryKind, target: &str, result: Result<CreateTimedMuteResponse>) -> Self {
+        let (outcome, expiration_date, error) = match result {
+            Ok(response) => (Some(response.outcome), Some(response.expiration_date), None),
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+        Self {
+            kind,
+            target: target.to_string(),
+            outcome,
+            expiration_date,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct MuteGroupActivation {
+    pub group: MuteGroupView,
+    /// One entry per member muted by the request; empty when nothing was muted.
+    pub results: Vec<MuteGroupResult>,
+}
+
+/// Trims the words and drops empty ones and those repeated under
+/// `normalize_word`.
+fn normalize_words(words: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for word in words.iter().map(|w| w.trim()).filter(|w| !w.is_empty()) {
+        if !normalized
+            .iter()
+            .any(|w| normalize_word(w) == normalize_word(word))
+        {
+            normalized.push(word.to_string());
+        }
+    }
+    normalized
+}
+
+/// Looks up the profiles of the accounts as given by the user, failing on
+/// the first one that doesn't parse or doesn't exist.
+async fn resolve_actors(agent: &Agent, actors: &[String]) -> Result<Vec<ProfileViewDetailed>> {
+    let parsed = actors
+        .iter()
+        .map(|actor| parse_actor(actor))
+        .collect::<Result<Vec<AtIdentifier>>>()?;
+    let found = get_profiles(agent, parsed.clone()).await?;
+    let mut profiles: Vec<ProfileViewDetailed> = Vec::new();
+    for (input, actor) in actors.iter().zip(&parsed) {
+        let profile = find_profile(&found, actor)
+            .ok_or_else(|| AppError::BadRequest(format!("Profile not found: {}", input)))?;
+        if !profiles.iter().any(|p| p.did == profile.did) {
+            profiles.push(profile.clone());
+        }
+    }
+    Ok(profiles)
+}
+
+/// Error for a member whose account or word is already muted by another
+/// group's activation. That row is left alone so the other group keeps it.
+fn in_other_group(existing_group: Option<i32>, group_id: i32) -> Option<AppError> {
+    existing_group
+        .filter(|id| *id != group_id)
+        .map(|id| AppError::BadRequest(format!("Already muted by mute group {}", id)))
+}
+
+/// Whether the row behind `result` was written for this request, so it can
+/// be linked to the group. An extended row was muted on its own before and
+/// keeps its own expiration.
+fn is_new_row(result: &Result<CreateTimedMuteResponse>) -> bool {
+    matches!(result, Ok(response) if response.outcome != CreateOutcome::Extended)
+}
+
+/// Mutes the given accounts and words until `expire_time` and links the
+/// rows created for them to the group. Timed mutes that existed before are
+/// only extended, never linked, so ending the group doesn't cut them short.
+#[allow(clippy::too_many_arguments)]
+async fn apply_members(
+    conn: &mut SqliteConnection,
+    agent: &Agent,
+    user_id: &str,
+    group_id: i32,
+    profiles: &[ProfileViewDetailed],
+    words: &[String],
+    create_time: i64,
+    expire_time: i64,
+    on_conflict: OnConflict,
+) -> Result<Vec<MuteGroupResult>> {
+    let targets = mute_word_targets(None)?;
+    let mut results = Vec::with_capacity(profiles.len() + words.len());
+    let mut mute_ids = Vec::new();
+    let mut word_ids = Vec::new();
+    for profile in profiles {
+        let did = profile.did.as_str();
+        let existing_group = fetch_active_timed_mute(conn, user_id, did).and_then(|m| m.group_id);
+        let result = match in_other_group(existing_group, group_id) {
+            Some(e) => Err(e),
+            None => {
+                apply_timed_mute(
+                    conn,
+                    agent,
+                    user_id,
+                    EventSource::Api,
+                    profile,
+                    create_time,
+                    expire_time,
+                    on_conflict,
+                )
+                .await
+            }
+        };
+        if is_new_row(&result) {
+            mute_ids.extend(fetch_active_timed_mute(conn, user_id, did).map(|m| m.id));
+        }
+        results.push(MuteGroupResult::new(EntryKind::Mute, did, result));
+    }
+    for word in words {
+        let existing_group =
+            fetch_active_timed_mute_word(conn, user_id, word).and_then(|w| w.group_id);
+        let result = match in_other_group(existing_group, group_id) {
+            Some(e) => Err(e),
+            None => {
+                apply_timed_mute_word(
+                    conn,
+                    agent,
+                    user_id,
+                    EventSource::Api,
+                    word.as_str(),
+                    &targets,
+                    MuteWordActorTarget::All,
+                    create_time,
+                    expire_time,
+                    on_conflict,
+                )
+                .await
+            }
+        };
+        if is_new_row(&result) {
+            word_ids.extend(fetch_active_timed_mute_word(conn, user_id, word).map(|w| w.id));
+        }
+        results.push(MuteGroupResult::new(EntryKind::Word, word, result));
+    }
+    assign_mute_group(conn, user_id, &group_id, mute_ids, word_ids)?;
+    Ok(results)
+}
+
+#[utoipa::path(
+    get,
+    path = "/mute-groups",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="The user's saved mute groups", body = Vec<MuteGroupView>),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_groups(session: Session, State(pool): State<DBPool>) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let groups: Vec<MuteGroupView> = fetch_mute_groups(&mut conn, user_id.as_str())
+        .into_iter()
+        .map(|group| MuteGroupView::fetch(&mut conn, group))
+        .collect();
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(groups),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/mute-group",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Mute group saved", body = MuteGroupView),
+        (status=400, description="Invalid name or member, or the name is taken"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn create_group(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<CreateMuteGroupRequest>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let name = req.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("name must not be empty".to_string()));
+    }
+    if fetch_mute_group_by_name(&mut conn, user_id.as_str(), name).is_some() {
+        return Err(AppError::BadRequest(format!(
+            "A mute group named {} already exists",
+            name
+        )));
+    }
+    let words = normalize_words(&req.words);
+    if req.actors.len() + words.len() > MAX_MEMBERS {
+        return Err(AppError::BadRequest(format!(
+            "A mute group has at most {} members",
+            MAX_MEMBERS
+        )));
+    }
+    let profiles = if req.actors.is_empty() {
+        Vec::new()
+    } else {
+        let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
+        let profile = profile_list.first().ok_or(AppError::NotFound)?;
+        let agent = get_agent_for_profile(profile).await?;
+        resolve_actors(&agent, &req.actors).await?
+    };
+
+    let create_time = chrono::offset::Utc::now().timestamp();
+    let group = create_mute_group(&mut conn, user_id.as_str(), name, &create_time)?;
+    let dids: Vec<String> = profiles.iter().map(|p| p.did.to_string()).collect();
+    add_mute_group_members(&mut conn, &group.id, EntryKind::Mute.as_str(), &dids)?;
+    add_mute_group_members(&mut conn, &group.id, EntryKind::Word.as_str(), &words)?;
+
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(MuteGroupView::fetch(&mut conn, group)),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/mute-group/members",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Members added, and muted if the group is active", body = MuteGroupActivation),
+        (status=400, description="Invalid member, too many, or the activation has ended but is not expired yet"),
+        (status=401, description="Unauthorized"),
+        (status=404, description="No such group"),
+    ),
+)]
+pub async fn add_members(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<AddMuteGroupMembersRequest>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let group = fetch_mute_group(&mut conn, user_id.as_str(), req.id).ok_or(AppError::NotFound)?;
+    let words = normalize_words(&req.words);
+    let members = fetch_mute_group_members(&mut conn, group.id).len();
+    if members + req.actors.len() + words.len() > MAX_MEMBERS {
+        return Err(AppError::BadRequest(format!(
+            "A mute group has at most {} members",
+            MAX_MEMBERS
+        )));
+    }
+    let now = chrono::offset::Utc::now().timestamp();
+    let active = count_unresolved_group_rows(&mut conn, group.id) > 0;
+    // The rows of an activation that ended stay until the resolver gets to them.
+    if active
+        && group
+            .expiration_date
+            .is_some_and(|expire_time| expire_time <= now)
+    {
+        return Err(AppError::BadRequest(
+            "The group's activation has ended".to_string(),
+        ));
+    }
+
+    let mut results = Vec::new();
+    if !req.actors.is_empty() || (active && !words.is_empty()) {
+        let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
+        let profile = profile_list.first().ok_or(AppError::NotFound)?;
+        let agent = get_agent_for_profile(profile).await?;
+        let profiles = resolve_actors(&agent, &req.actors).await?;
+        let dids: Vec<String> = profiles.iter().map(|p| p.did.to_string()).collect();
+        add_mute_group_members(&mut conn, &group.id, EntryKind::Mute.as_str(), &dids)?;
+        add_mute_group_members(&mut conn, &group.id, EntryKind::Word.as_str(), &words)?;
+        // New members of an active group are muted until it expires.
+        if let (true, Some(expire_time)) = (active, group.expiration_date) {
+            results = apply_members(
+                &mut conn,
+                &agent,
+                user_id.as_str(),
+                group.id,
+                &profiles,
+                &words,
+                now,
+                expire_time,
+                OnConflict::Extend,
+            )
+            .await?;
+        }
+    } else {
+        add_mute_group_members(&mut conn, &group.id, EntryKind::Word.as_str(), &words)?;
+    }
+
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(MuteGroupActivation {
+            group: MuteGroupView::fetch(&mut conn, group),
+            results,
+        }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/activateMuteGroup",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Result for each member", body = MuteGroupActivation),
+        (status=400, description="Invalid expiration, or the group has no members"),
+        (status=401, description="Unauthorized"),
+        (status=404, description="No such group"),
+    ),
+)]
+pub async fn activate_group(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<ActivateMuteGroupRequest>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let group = fetch_mute_group(&mut conn, user_id.as_str(), req.id).ok_or(AppError::NotFound)?;
+    let create_time = chrono::offset::Utc::now().timestamp();
+    let expire_time = resolve_expiration(
+        create_time,
+        req.expiration_length.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
+    let view = MuteGroupView::fetch(&mut conn, group);
+    if view.actors.is_empty() && view.words.is_empty() {
+        return Err(AppError::BadRequest(
+            "The mute group has no members".to_string(),
+        ));
+    }
+
+    let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
+    let profile = profile_list.first().ok_or(AppError::NotFound)?;
+    let agent = get_agent_for_profile(profile).await?;
+    let actors: Vec<AtIdentifier> = view
+        .actors
+        .iter()
+        .filter_map(|did| did.parse().ok().map(AtIdentifier::Did))
+        .collect();
+    let profiles = get_profiles(&agent, actors).await?;
+    let mut results = apply_members(
+        &mut conn,
+        &agent,
+        user_id.as_str(),
+        view.id,
+        &profiles,
+        &view.words,
+        create_time,
+        expire_time,
+        req.on_conflict.unwrap_or_default(),
+    )
+    .await?;
+    for did in &view.actors {
+        if !profiles.iter().any(|p| p.did.as_str() == did) {
+            let missing = Err(AppError::BadRequest(format!("Profile not found: {}", did)));
+            results.push(MuteGroupResult::new(EntryKind::Mute, did, missing));
+        }
+    }
+
+    // The group is lifted when its first member is due, which is the
+    // earliest expiration among the members muted now.
+    let lifted = results.iter().filter_map(|r| r.expiration_date).min();
+    if let Some(expiration_date) = lifted {
+        update_mute_group_expiration(&mut conn, user_id.as_str(), &view.id, &expiration_date)?;
+    }
+    let group = fetch_mute_group(&mut conn, user_id.as_str(), view.id).ok_or(AppError::NotFound)?;
+
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(MuteGroupActivation {
+            group: MuteGroupView::fetch(&mut conn, group),
+            results,
+        }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/deleteMuteGroup",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Mute group deleted; a running activation still expires as planned"),
+        (status=401, description="Unauthorized"),
+        (status=404, description="No such group"),
+    ),
+)]
+pub async fn delete_group(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<DeleteMuteGroupRequest>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    if !delete_mute_group(&mut conn, user_id.as_str(), &req.id)? {
+        return Err(AppError::NotFound);
+    }
+    Ok((StatusCode::OK, [(CONTENT_TYPE, APPLICATION_JSON)]).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_words() {
+        let words = vec![
+            " Final ".to_string(),
+            "".to_string(),
+            "final".to_string(),
+            "score".to_string(),
+            "ÄPFEL".to_string(),
+            "äpfel".to_string(),
+        ];
+        // Non-ASCII case differs, as it does for the unique index.
+        assert_eq!(
+            normalize_words(&words),
+            ["Final", "score", "ÄPFEL", "äpfel"]
+        );
+    }
+
+    #[test]
+    fn test_mute_group_result() {
+        let ok = MuteGroupResult::new(
+            EntryKind::Word,
+            "spoiler",
+            Ok(CreateTimedMuteResponse {
+                outcome: CreateOutcome::Created,
+                expiration_date: 2000,
+            }),
+        );
+        assert_eq!(ok.outcome, Some(CreateOutcome::Created));
+        assert_eq!(ok.expiration_date, Some(2000));
+        assert!(ok.error.is_none());
+
+        let failed = MuteGroupResult::new(EntryKind::Mute, "did:plc:x", Err(AppError::NotFound));
+        assert_eq!(failed.outcome, None);
+        assert_eq!(failed.error.as_deref(), Some("Not found"));
+    }
+
+    #[test]
+    fn test_only_new_rows_join_the_group() {
+        let response = |outcome| {
+            Ok(CreateTimedMuteResponse {
+                outcome,
+                expiration_date: 2000,
+            })
+        };
+        assert!(is_new_row(&response(CreateOutcome::Created)));
+        assert!(is_new_row(&response(CreateOutcome::Replaced)));
+        assert!(!is_new_row(&response(CreateOutcome::Extended)));
+        assert!(!is_new_row(&Err(AppError::NotFound)));
+
+        assert!(in_other_group(None, 7).is_none());
+        assert!(in_other_group(Some(7), 7).is_none());
+        assert!(in_other_group(Some(8), 7).is_some());
+    }
+}
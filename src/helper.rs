@@ -1,11 +1,20 @@
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
 use diesel::sqlite::SqliteConnection;
-use diesel::{Connection, ExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper};
+use diesel::{
+    BoolExpressionMethods, Connection, EscapeExpressionMethods, ExpressionMethods,
+    NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper,
+    TextExpressionMethods,
+};
 
+use crate::crypto;
 use crate::error::AppError;
 use crate::models::{
-    NewProfile, NewTimedMute, NewTimedMuteWord, Profile, TimedMute, TimedMuteWord,
+    MuteEvent, MuteGroup, MuteGroupMember, NewMuteEvent, NewMuteGroup, NewMuteGroupMember,
+    NewProfile, NewProfileSession, NewRecurringMute, NewTimedBlock, NewTimedListMute, NewTimedMute,
+    NewTimedMuteWord, NewTimedThreadMute, Profile, ProfileSession, RecurringMute, TimedBlock,
+    TimedListMute, TimedMute, TimedMuteWord, TimedThreadMute,
 };
+use crate::status::MuteStatus;
 
 pub type Result<T> = std::result::Result<T, AppError>;
 pub type DBPool = Pool<ConnectionManager<SqliteConnection>>;
@@ -17,6 +26,7 @@ mod tests {
     use diesel::r2d2::{ConnectionManager, Pool};
 
     fn setup_test_pool() -> DBPool {
+        crypto::install_test_keyring();
         let manager = ConnectionManager::<SqliteConnection>::new(":memory:");
         let pool = Pool::builder()
             .build(manager)
@@ -25,11 +35,23 @@ mod tests {
 
         diesel::sql_query(
             "CREATE TABLE timed_mute (
+            rowid INTEGER PRIMARY KEY,
             actor VARCHAR NOT NULL,
             muted_actor VARCHAR NOT NULL,
             created_date BIGINT NOT NULL,
             expiration_date BIGINT NOT NULL,
-            status INTEGER NOT NULL
+            status INTEGER NOT NULL,
+            original_expiration_date BIGINT,
+            extended_date BIGINT,
+            drift_date BIGINT,
+            expired_date BIGINT,
+            cancelled_date BIGINT,
+            attempt_count INTEGER NOT NULL DEFAULT 0,
+            last_error VARCHAR,
+            next_attempt_date BIGINT,
+            was_muted_before BOOLEAN NOT NULL DEFAULT 0,
+            group_id INTEGER,
+            start_date BIGINT
         )",
         )
         .execute(&mut conn)
@@ -48,16 +70,183 @@ mod tests {
 
         diesel::sql_query(
             "CREATE TABLE timed_mute_word (
+            rowid INTEGER PRIMARY KEY,
             actor VARCHAR NOT NULL,
             muted_word VARCHAR NOT NULL,
             created_date BIGINT NOT NULL,
             expiration_date BIGINT NOT NULL,
-            status INTEGER NOT NULL
+            status INTEGER NOT NULL,
+            original_expiration_date BIGINT,
+            extended_date BIGINT,
+            targets VARCHAR NOT NULL DEFAULT 'tag,content',
+            actor_target VARCHAR NOT NULL DEFAULT 'all',
+            drift_date BIGINT,
+            expired_date BIGINT,
+            cancelled_date BIGINT,
+            attempt_count INTEGER NOT NULL DEFAULT 0,
+            last_error VARCHAR,
+            next_attempt_date BIGINT,
+            group_id INTEGER,
+            start_date BIGINT
+        )",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE TABLE timed_block (
+            rowid INTEGER PRIMARY KEY,
+            actor VARCHAR NOT NULL,
+            blocked_actor VARCHAR NOT NULL,
+            record_uri VARCHAR NOT NULL,
+            created_date BIGINT NOT NULL,
+            expiration_date BIGINT NOT NULL,
+            status INTEGER NOT NULL,
+            attempt_count INTEGER NOT NULL DEFAULT 0,
+            last_error VARCHAR,
+            next_attempt_date BIGINT
+        )",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE TABLE timed_thread_mute (
+            rowid INTEGER PRIMARY KEY,
+            actor VARCHAR NOT NULL,
+            thread_uri VARCHAR NOT NULL,
+            created_date BIGINT NOT NULL,
+            expiration_date BIGINT NOT NULL,
+            status INTEGER NOT NULL,
+            attempt_count INTEGER NOT NULL DEFAULT 0,
+            last_error VARCHAR,
+            next_attempt_date BIGINT
+        )",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE TABLE timed_list_mute (
+            rowid INTEGER PRIMARY KEY,
+            actor VARCHAR NOT NULL,
+            list_uri VARCHAR NOT NULL,
+            mode VARCHAR NOT NULL,
+            record_uri VARCHAR,
+            created_date BIGINT NOT NULL,
+            expiration_date BIGINT NOT NULL,
+            status INTEGER NOT NULL,
+            attempt_count INTEGER NOT NULL DEFAULT 0,
+            last_error VARCHAR,
+            next_attempt_date BIGINT
+        )",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE TABLE profile_session (
+            access_jwt VARCHAR NOT NULL,
+            refresh_jwt VARCHAR NOT NULL,
+            did VARCHAR NOT NULL,
+            active BOOLEAN NOT NULL,
+            status VARCHAR
+        )",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query("CREATE UNIQUE INDEX profile_session_did ON profile_session (did)")
+            .execute(&mut conn)
+            .unwrap();
+
+        diesel::sql_query(
+            "CREATE TABLE mute_event (
+            rowid INTEGER PRIMARY KEY,
+            actor VARCHAR NOT NULL,
+            source VARCHAR NOT NULL,
+            action VARCHAR NOT NULL,
+            kind VARCHAR NOT NULL,
+            target VARCHAR NOT NULL,
+            entry_id INTEGER,
+            result VARCHAR NOT NULL,
+            error VARCHAR,
+            created_date BIGINT NOT NULL
+        )",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE TABLE mute_group (
+            rowid INTEGER PRIMARY KEY,
+            actor VARCHAR NOT NULL,
+            name VARCHAR NOT NULL,
+            created_date BIGINT NOT NULL,
+            expiration_date BIGINT
+        )",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE UNIQUE INDEX mute_group_name ON mute_group (actor, name COLLATE NOCASE)",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE TABLE mute_group_member (
+            rowid INTEGER PRIMARY KEY,
+            group_id INTEGER NOT NULL,
+            kind VARCHAR NOT NULL,
+            target VARCHAR NOT NULL
+        )",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE UNIQUE INDEX mute_group_member_target
+            ON mute_group_member (group_id, kind, target COLLATE NOCASE)",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE TABLE recurring_mute (
+            rowid INTEGER PRIMARY KEY,
+            actor VARCHAR NOT NULL,
+            kind VARCHAR NOT NULL,
+            target VARCHAR NOT NULL,
+            cron VARCHAR NOT NULL,
+            timezone VARCHAR NOT NULL,
+            duration BIGINT NOT NULL,
+            next_run_date BIGINT NOT NULL,
+            last_run_date BIGINT,
+            last_error VARCHAR,
+            created_date BIGINT NOT NULL,
+            attempt_count INTEGER NOT NULL DEFAULT 0,
+            next_attempt_date BIGINT
         )",
         )
         .execute(&mut conn)
         .unwrap();
 
+        diesel::sql_query(
+            "CREATE UNIQUE INDEX timed_mute_unresolved_target
+            ON timed_mute (actor, muted_actor) WHERE status IN (0, 5)",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        diesel::sql_query(
+            "CREATE UNIQUE INDEX timed_mute_word_unresolved_target
+            ON timed_mute_word (actor, muted_word COLLATE NOCASE) WHERE status IN (0, 5)",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
         pool
     }
 
@@ -78,14 +267,18 @@ mod tests {
         assert_eq!(profiles.len(), 1);
         assert_eq!(profiles[0].did, did);
         assert_eq!(profiles[0].handle, handle);
-        assert_eq!(profiles[0].password, password);
+        assert_ne!(profiles[0].password, password);
+        assert_eq!(crypto::decrypt(&profiles[0].password).unwrap(), password);
         assert_eq!(profiles[0].status, 0);
 
         // Test update_profile
         let new_password = "newpassword";
         let _ = update_profile(&mut conn, did, handle, new_password).unwrap();
         let profiles = fetch_profile(&mut conn, did);
-        assert_eq!(profiles[0].password, new_password);
+        assert_eq!(
+            crypto::decrypt(&profiles[0].password).unwrap(),
+            new_password
+        );
 
         // Test deactivate_profile
         let _ = deactivate_profile(&mut conn, did).unwrap();
@@ -103,7 +296,7 @@ mod tests {
         let muted_actor = "did:plc:muted";
         let created_date = 1000;
         let expiration_date = 2000;
-        let status = 0;
+        let status = MuteStatus::Active;
 
         // Test create_timed_mute
         let _ = create_timed_mute(
@@ -113,6 +306,7 @@ mod tests {
             &created_date,
             &expiration_date,
             &status,
+            &false,
         )
         .unwrap();
 
@@ -121,12 +315,27 @@ mod tests {
         assert_eq!(mutes.len(), 1);
         assert_eq!(mutes[0].muted_actor, muted_actor);
 
-        // Test update_timed_mute
-        let new_status = 1;
-        let updated =
-            update_timed_mute(&mut conn, actor, muted_actor, &expiration_date, &new_status)
-                .unwrap();
+        // Test fetch_timed_mute
+        let id = mutes[0].id;
+        assert_eq!(
+            fetch_timed_mute(&mut conn, actor, id).unwrap().muted_actor,
+            muted_actor
+        );
+        assert!(fetch_timed_mute(&mut conn, "did:plc:other", id).is_none());
+
+        // Test update_timed_mute_v1
+        let new_status = MuteStatus::Expired;
+        assert!(!update_timed_mute_v1(&mut conn, "did:plc:other", &id, &new_status).unwrap());
+        let updated = update_timed_mute_v1(&mut conn, actor, &id, &new_status).unwrap();
         assert!(updated);
+        // Expired rows cannot be cancelled.
+        assert!(matches!(
+            update_timed_mute_v1(&mut conn, actor, &id, &MuteStatus::Cancelled),
+            Err(AppError::InvalidTransition(
+                MuteStatus::Expired,
+                MuteStatus::Cancelled
+            ))
+        ));
 
         let mutes = fetch_timed_mutes_for_user(&mut conn, actor);
         // fetch_timed_mutes filters by status = 0, so it should be empty now
@@ -142,13 +351,15 @@ mod tests {
         let muted_word = "badword";
         let created_date = 1000;
         let expiration_date = 2000;
-        let status = 0;
+        let status = MuteStatus::Active;
 
         // Test create_timed_mute_word
         let _ = create_timed_mute_word(
             &mut conn,
             actor,
             muted_word,
+            "tag",
+            "exclude-following",
             &created_date,
             &expiration_date,
             &status,
@@ -159,215 +370,2425 @@ mod tests {
         let words = fetch_timed_mute_words(&mut conn, actor);
         assert_eq!(words.len(), 1);
         assert_eq!(words[0].muted_word, muted_word);
+        assert_eq!(words[0].targets, "tag");
+        assert_eq!(words[0].actor_target, "exclude-following");
 
-        // Test update_timed_mute_word
-        let new_status = 1;
-        let updated = update_timed_mute_word(&mut conn, actor, muted_word, &new_status).unwrap();
+        // Test update_timed_mute_word_v1
+        let id = words[0].id;
+        let new_status = MuteStatus::Expired;
+        let updated = update_timed_mute_word_v1(&mut conn, actor, &id, &new_status).unwrap();
         assert!(updated);
 
         let words = fetch_timed_mute_words_for_user(&mut conn, actor);
         assert_eq!(words.len(), 0);
+
+        // Muting the same word again gets a new id, and cancelling it leaves
+        // the expired row alone.
+        create_timed_mute_word(
+            &mut conn,
+            actor,
+            muted_word,
+            "tag,content",
+            "all",
+            &3000,
+            &4000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+        let words = fetch_timed_mute_words(&mut conn, actor);
+        assert_ne!(words[0].id, id);
+        assert!(
+            update_timed_mute_word_v1(&mut conn, actor, &words[0].id, &MuteStatus::Cancelled)
+                .unwrap()
+        );
+        assert_eq!(
+            fetch_timed_mute_word(&mut conn, actor, id).unwrap().status,
+            new_status
+        );
     }
 
     #[test]
-    fn test_v1_operations() {
+    fn test_mark_timed_mutes_drifted() {
         let pool = setup_test_pool();
         let mut conn = pool.get().unwrap();
 
         let actor = "did:plc:actor";
-        let _ = create_timed_mute(&mut conn, actor, "muted1", &1000, &2000, &0).unwrap();
-        let _ = create_timed_mute_word(&mut conn, actor, "word1", &1000, &2000, &0).unwrap();
+        create_timed_mute(
+            &mut conn,
+            actor,
+            "did:plc:muted",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+        create_timed_mute_word(
+            &mut conn,
+            actor,
+            "badword",
+            "tag,content",
+            "all",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+        let mute_id = fetch_timed_mutes(&mut conn, actor)[0].id;
+        let word_id = fetch_timed_mute_words(&mut conn, actor)[0].id;
+
+        assert_eq!(
+            mark_timed_mutes_drifted(&mut conn, "did:plc:other", vec![mute_id], &1500).unwrap(),
+            0
+        );
+        assert_eq!(
+            mark_timed_mutes_drifted(&mut conn, actor, vec![mute_id], &1500).unwrap(),
+            1
+        );
+        assert_eq!(
+            mark_timed_mute_words_drifted(&mut conn, actor, vec![word_id], &1500).unwrap(),
+            1
+        );
+        assert!(fetch_timed_mutes(&mut conn, actor).is_empty());
+
+        let mute = fetch_timed_mute(&mut conn, actor, mute_id).unwrap();
+        assert_eq!(mute.status, MuteStatus::Drifted);
+        assert_eq!(mute.drift_date, Some(1500));
+        let word = fetch_timed_mute_word(&mut conn, actor, word_id).unwrap();
+        assert_eq!(word.status, MuteStatus::Drifted);
+        assert_eq!(word.drift_date, Some(1500));
+    }
 
-        let _ = create_profile(&mut conn, "did1", "handle1", "pass1").unwrap();
+    #[test]
+    fn test_extend_timed_mutes() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
 
-        let p = fetch_profile_v1(&mut conn, "did1");
-        assert_eq!(p.len(), 1);
+        let actor = "did:plc:actor";
+        let muted_actor = "did:plc:muted";
+        create_timed_mute(
+            &mut conn,
+            actor,
+            muted_actor,
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+        create_timed_mute_word(
+            &mut conn,
+            actor,
+            "badword",
+            "tag,content",
+            "all",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
 
-        let mutes = fetch_timed_mutes_v1(&mut conn);
-        assert_eq!(mutes.len(), 1);
+        let mute_id = fetch_timed_mutes(&mut conn, actor)[0].id;
+        let word_id = fetch_timed_mute_words(&mut conn, actor)[0].id;
 
-        let words = fetch_timed_mute_words_v1(&mut conn);
-        assert_eq!(words.len(), 1);
+        // Another user's row is not matched.
+        assert!(!extend_timed_mute(&mut conn, "did:plc:other", &mute_id, &3000, &1500).unwrap());
 
-        let _ =
-            update_timed_mute_list_v1(&mut conn, actor, vec!["muted1".to_string()], &1).unwrap();
-        let mutes = fetch_timed_mutes_v1(&mut conn);
-        assert_eq!(mutes.len(), 0);
+        assert!(extend_timed_mute(&mut conn, actor, &mute_id, &3000, &1500).unwrap());
+        assert!(extend_timed_mute(&mut conn, actor, &mute_id, &2500, &1600).unwrap());
+        let mutes = fetch_timed_mutes(&mut conn, actor);
+        assert_eq!(mutes[0].expiration_date, 2500);
+        assert_eq!(mutes[0].original_expiration_date, Some(2000));
+        assert_eq!(mutes[0].extended_date, Some(1600));
 
-        let _ = update_timed_mute_word_list_v1(&mut conn, actor, vec!["word1".to_string()], &1)
-            .unwrap();
-        let words = fetch_timed_mute_words_v1(&mut conn);
-        assert_eq!(words.len(), 0);
-    }
-}
+        assert!(extend_timed_mute_word(&mut conn, actor, &word_id, &4000, &1500).unwrap());
+        let words = fetch_timed_mute_words(&mut conn, actor);
+        assert_eq!(words[0].expiration_date, 4000);
+        assert_eq!(words[0].original_expiration_date, Some(2000));
+        assert_eq!(words[0].extended_date, Some(1500));
 
-pub fn establish_connection(database_url: &str) -> SqliteConnection {
-    SqliteConnection::establish(database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
-}
+        // Expired rows can no longer be extended.
+        update_timed_mute_word_v1(&mut conn, actor, &word_id, &MuteStatus::Expired).unwrap();
+        assert!(!extend_timed_mute_word(&mut conn, actor, &word_id, &5000, &1700).unwrap());
+    }
 
-pub fn create_timed_mute(
-    conn: &mut DBPooledConnection,
-    actor: &str,
-    muted_actor: &str,
-    created_date: &i64,
-    expiration_date: &i64,
-    other_status: &i32,
-) -> Result<usize> {
-    use crate::schema::timed_mute;
-    let new_timed_mute = NewTimedMute {
-        actor,
-        muted_actor,
-        created_date,
-        expiration_date,
-        status: other_status,
-    };
+    #[test]
+    fn test_replace_timed_mutes() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
 
-    diesel::insert_into(timed_mute::table)
-        .values(&new_timed_mute)
-        .execute(conn)
-        .map_err(AppError::from)
-}
+        let actor = "did:plc:actor";
+        let muted_actor = "did:plc:muted";
+        create_timed_mute(
+            &mut conn,
+            actor,
+            muted_actor,
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+        create_timed_mute_word(
+            &mut conn,
+            actor,
+            "Spoiler",
+            "tag,content",
+            "all",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
 
-pub fn create_timed_mute_word(
-    conn: &mut DBPooledConnection,
-    actor: &str,
-    muted_word: &str,
-    created_date: &i64,
-    expiration_date: &i64,
-    status: &i32,
-) -> Result<usize> {
-    use crate::schema::timed_mute_word;
-    let new_timed_mute = NewTimedMuteWord {
-        actor,
-        muted_word,
-        created_date,
-        expiration_date,
-        status,
-    };
+        // Only one active row per target.
+        assert!(create_timed_mute(
+            &mut conn,
+            actor,
+            muted_actor,
+            &1100,
+            &3000,
+            &MuteStatus::Active,
+            &false
+        )
+        .is_err());
+        assert!(create_timed_mute_word(
+            &mut conn,
+            actor,
+            "spoiler",
+            "tag",
+            "all",
+            &1100,
+            &3000,
+            &MuteStatus::Active,
+        )
+        .is_err());
+
+        let mute = fetch_active_timed_mute(&mut conn, actor, muted_actor).unwrap();
+        assert!(fetch_active_timed_mute(&mut conn, "did:plc:other", muted_actor).is_none());
+        let word = fetch_active_timed_mute_word(&mut conn, actor, "SPOILER").unwrap();
+        assert_eq!(word.muted_word, "Spoiler");
+        // Only ASCII case is ignored, like the unique index does.
+        assert_eq!(normalize_word(" ÄPFEL "), "Äpfel");
+        assert!(fetch_active_timed_mute_word(&mut conn, actor, "SPÖILER").is_none());
+
+        replace_timed_mute(&mut conn, actor, &mute.id, muted_actor, &1100, &3000, &true).unwrap();
+        replace_timed_mute_word(
+            &mut conn,
+            actor,
+            &word.id,
+            "spoiler",
+            "tag",
+            "exclude-following",
+            &1100,
+            &3000,
+        )
+        .unwrap();
 
-    diesel::insert_into(timed_mute_word::table)
-        .values(&new_timed_mute)
-        .execute(conn)
-        .map_err(AppError::from)
-}
+        assert_eq!(
+            fetch_timed_mute(&mut conn, actor, mute.id).unwrap().status,
+            MuteStatus::Superseded
+        );
+        let active = fetch_active_timed_mute(&mut conn, actor, muted_actor).unwrap();
+        assert_ne!(active.id, mute.id);
+        assert_eq!(active.expiration_date, 3000);
+        assert!(active.was_muted_before);
+
+        assert_eq!(
+            fetch_timed_mute_word(&mut conn, actor, word.id)
+                .unwrap()
+                .status,
+            MuteStatus::Superseded
+        );
+        let words = fetch_timed_mute_words(&mut conn, actor);
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].muted_word, "spoiler");
+        assert_eq!(words[0].targets, "tag");
 
-pub fn update_timed_mute(
-    conn: &mut DBPooledConnection,
-    _actor: &str,
-    _muted_actor: &str,
-    expiration_time: &i64,
-    status: &i32,
-) -> Result<bool> {
-    use crate::schema::timed_mute;
+        // A finished row no longer blocks a new one.
+        update_timed_mute_v1(&mut conn, actor, &active.id, &MuteStatus::Expired).unwrap();
+        create_timed_mute(
+            &mut conn,
+            actor,
+            muted_actor,
+            &3100,
+            &4000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+    }
 
-    let res = diesel::update(timed_mute::table)
-        .filter(timed_mute::expiration_date.eq(expiration_time))
-        .filter(timed_mute::actor.eq(_actor))
-        .filter(timed_mute::muted_actor.eq(_muted_actor))
-        .set(timed_mute::status.eq(status))
-        .execute(conn)?;
+    #[test]
+    fn test_expiring_row_holds_target() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
 
-    Ok(res > 0)
-}
+        let actor = "did:plc:actor";
+        let muted_actor = "did:plc:muted";
+        create_timed_mute(
+            &mut conn,
+            actor,
+            muted_actor,
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+        create_timed_mute_word(
+            &mut conn,
+            actor,
+            "spoiler",
+            "tag,content",
+            "all",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+        let mute = fetch_active_timed_mute(&mut conn, actor, muted_actor).unwrap();
+        let word = fetch_active_timed_mute_word(&mut conn, actor, "spoiler").unwrap();
+        update_timed_mute_v1(&mut conn, actor, &mute.id, &MuteStatus::Expiring).unwrap();
+        update_timed_mute_word_v1(&mut conn, actor, &word.id, &MuteStatus::Expiring).unwrap();
+
+        // The claimed rows are still found, and no second active row can sit
+        // next to them.
+        let expiring = fetch_active_timed_mute(&mut conn, actor, muted_actor).unwrap();
+        assert_eq!(expiring.id, mute.id);
+        assert_eq!(expiring.status, MuteStatus::Expiring);
+        assert_eq!(
+            fetch_active_timed_mute_word(&mut conn, actor, "SPOILER")
+                .unwrap()
+                .status,
+            MuteStatus::Expiring
+        );
+        assert!(create_timed_mute(
+            &mut conn,
+            actor,
+            muted_actor,
+            &2100,
+            &3000,
+            &MuteStatus::Active,
+            &true
+        )
+        .is_err());
+        assert!(create_timed_mute_word(
+            &mut conn,
+            actor,
+            "Spoiler",
+            "tag",
+            "all",
+            &2100,
+            &3000,
+            &MuteStatus::Active,
+        )
+        .is_err());
+        assert!(matches!(
+            replace_timed_mute(
+                &mut conn,
+                actor,
+                &mute.id,
+                muted_actor,
+                &2100,
+                &3000,
+                &false
+            ),
+            Err(AppError::InvalidTransition(
+                MuteStatus::Expiring,
+                MuteStatus::Superseded
+            ))
+        ));
+        assert!(!extend_timed_mute(&mut conn, actor, &mute.id, &3000, &2100).unwrap());
+
+        // A failed undo puts the row back to active and counts the attempt.
+        assert!(record_timed_mute_failure_v1(&mut conn, &mute.id, "busy", &2200, &5).unwrap());
+        assert!(record_timed_mute_word_failure_v1(&mut conn, &word.id, "busy", &2200, &5).unwrap());
+        let retried = fetch_active_timed_mute(&mut conn, actor, muted_actor).unwrap();
+        assert_eq!(retried.id, mute.id);
+        assert_eq!(retried.status, MuteStatus::Active);
+        assert_eq!(retried.attempt_count, 1);
+        assert_eq!(retried.last_error.as_deref(), Some("busy"));
+        let retried_word = fetch_active_timed_mute_word(&mut conn, actor, "spoiler").unwrap();
+        assert_eq!(retried_word.status, MuteStatus::Active);
+        assert_eq!(retried_word.attempt_count, 1);
+    }
 
-pub fn update_timed_mute_word(
-    conn: &mut DBPooledConnection,
-    _actor: &str,
-    _muted_word: &str,
-    status: &i32,
-) -> Result<bool> {
-    use crate::schema::timed_mute_word;
+    #[test]
+    fn test_timed_mute_history() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
 
-    let res = diesel::update(timed_mute_word::table)
-        .filter(timed_mute_word::actor.eq(_actor))
-        .filter(timed_mute_word::muted_word.eq(_muted_word))
-        .set(timed_mute_word::status.eq(status))
-        .execute(conn)?;
+        let actor = "did:plc:actor";
+        for (i, muted_actor) in ["did:plc:a", "did:plc:b", "did:plc:c"].iter().enumerate() {
+            let created = 1000 * (i as i64 + 1);
+            create_timed_mute(
+                &mut conn,
+                actor,
+                muted_actor,
+                &created,
+                &9000,
+                &MuteStatus::Active,
+                &false,
+            )
+            .unwrap();
+        }
+        create_timed_mute(
+            &mut conn,
+            "did:plc:other",
+            "did:plc:a",
+            &1000,
+            &9000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+        let ids: Vec<i32> = fetch_timed_mute_history(
+            &mut conn,
+            actor,
+            &HistoryFilter {
+                limit: 10,
+                ..Default::default()
+            },
+        )
+        .iter()
+        .map(|m| m.id)
+        .collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids.windows(2).all(|w| w[0] > w[1]));
+
+        update_timed_mute_v1(&mut conn, actor, &ids[2], &MuteStatus::Expired).unwrap();
+        update_timed_mute_v1(&mut conn, actor, &ids[1], &MuteStatus::Cancelled).unwrap();
+        let expired = fetch_timed_mute(&mut conn, actor, ids[2]).unwrap();
+        assert!(expired.expired_date.is_some());
+        assert!(expired.cancelled_date.is_none());
+        let cancelled = fetch_timed_mute(&mut conn, actor, ids[1]).unwrap();
+        assert!(cancelled.expired_date.is_none());
+        assert!(cancelled.cancelled_date.is_some());
+
+        let filter = HistoryFilter {
+            status: Some(MuteStatus::Cancelled),
+            limit: 10,
+            ..Default::default()
+        };
+        let history = fetch_timed_mute_history(&mut conn, actor, &filter);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].muted_actor, "did:plc:b");
+
+        let filter = HistoryFilter {
+            since: Some(1500),
+            until: Some(2500),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            fetch_timed_mute_history(&mut conn, actor, &filter)[0].id,
+            ids[1]
+        );
+
+        let filter = HistoryFilter {
+            target: Some("did:plc:a"),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(fetch_timed_mute_history(&mut conn, actor, &filter).len(), 1);
+
+        // Pages continue below the cursor.
+        let first = HistoryFilter {
+            limit: 2,
+            ..Default::default()
+        };
+        let page = fetch_timed_mute_history(&mut conn, actor, &first);
+        assert_eq!(page.len(), 2);
+        let next = HistoryFilter {
+            before_id: Some(page[1].id),
+            limit: 2,
+            ..Default::default()
+        };
+        let page = fetch_timed_mute_history(&mut conn, actor, &next);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, ids[2]);
+    }
+
+    #[test]
+    fn test_timed_mute_word_history() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let actor = "did:plc:actor";
+        for word in ["World Cup", "cup_final", "election"] {
+            create_timed_mute_word(
+                &mut conn,
+                actor,
+                word,
+                "tag,content",
+                "all",
+                &1000,
+                &2000,
+                &MuteStatus::Active,
+            )
+            .unwrap();
+        }
+        let filter = HistoryFilter {
+            target: Some("CUP"),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            fetch_timed_mute_word_history(&mut conn, actor, &filter).len(),
+            2
+        );
+
+        // LIKE wildcards in the target are matched literally.
+        let filter = HistoryFilter {
+            target: Some("cu_"),
+            limit: 10,
+            ..Default::default()
+        };
+        let words = fetch_timed_mute_word_history(&mut conn, actor, &filter);
+        assert!(words.is_empty());
+        let filter = HistoryFilter {
+            target: Some("_final"),
+            limit: 10,
+            ..Default::default()
+        };
+        let words = fetch_timed_mute_word_history(&mut conn, actor, &filter);
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].muted_word, "cup_final");
+    }
+
+    #[test]
+    fn test_mute_events() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let events = [
+            ("did:plc:a", "api", "create", "did:plc:x", "ok", None, 1000),
+            ("did:plc:b", "api", "create", "did:plc:y", "ok", None, 1100),
+            (
+                "did:plc:a",
+                "scheduler",
+                "expire_failed",
+                "did:plc:x",
+                "error",
+                Some("timeout"),
+                2000,
+            ),
+            (
+                "did:plc:a",
+                "scheduler",
+                "expire",
+                "did:plc:x",
+                "ok",
+                None,
+                2100,
+            ),
+        ];
+        for (actor, source, action, target, result, error, created_date) in events {
+            create_mute_event(
+                &mut conn,
+                &NewMuteEvent {
+                    actor,
+                    source,
+                    action,
+                    kind: "mute",
+                    target,
+                    entry_id: Some(1),
+                    result,
+                    error,
+                    created_date: &created_date,
+                },
+            )
+            .unwrap();
+        }
+
+        let filter = EventFilter {
+            actor: Some("did:plc:a"),
+            limit: 10,
+            ..Default::default()
+        };
+        let found = fetch_mute_events(&mut conn, &filter);
+        assert_eq!(
+            found.iter().map(|e| e.action.as_str()).collect::<Vec<_>>(),
+            vec!["expire", "expire_failed", "create"]
+        );
+        assert_eq!(found[1].error.as_deref(), Some("timeout"));
+
+        let filter = EventFilter {
+            source: Some("api"),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(fetch_mute_events(&mut conn, &filter).len(), 2);
+
+        let filter = EventFilter {
+            actor: Some("did:plc:a"),
+            before_id: Some(found[0].id),
+            since: Some(1500),
+            limit: 10,
+            ..Default::default()
+        };
+        let found = fetch_mute_events(&mut conn, &filter);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].action, "expire_failed");
+    }
+
+    #[test]
+    fn test_timed_block_operations() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let actor = "did:plc:actor";
+        let blocked_actor = "did:plc:blocked";
+        let record_uri = "at://did:plc:actor/app.bsky.graph.block/abc";
+
+        let created = create_timed_block(
+            &mut conn,
+            actor,
+            blocked_actor,
+            record_uri,
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+
+        let blocks = fetch_timed_blocks(&mut conn, actor);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].id, created.id);
+        assert_eq!(blocks[0].blocked_actor, blocked_actor);
+        assert_eq!(blocks[0].record_uri, record_uri);
+        assert_eq!(fetch_timed_blocks_v1(&mut conn).len(), 1);
+
+        update_timed_block_v1(&mut conn, actor, &created.id, &MuteStatus::Expiring).unwrap();
+        assert!(
+            update_timed_block_v1(&mut conn, actor, &created.id, &MuteStatus::Expired).unwrap()
+        );
+        assert_eq!(fetch_timed_blocks_v1(&mut conn).len(), 0);
+
+        // Already resolved blocks can no longer be cancelled.
+        let block = fetch_timed_block(&mut conn, actor, blocks[0].id).unwrap();
+        assert_eq!(block.status, MuteStatus::Expired);
+        assert!(
+            update_timed_block_v1(&mut conn, actor, &block.id, &MuteStatus::Cancelled).is_err()
+        );
+        assert!(fetch_timed_block(&mut conn, "did:plc:other", block.id).is_none());
+    }
+
+    #[test]
+    fn test_timed_thread_mute_operations() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let actor = "did:plc:actor";
+        let thread_uri = "at://did:plc:other/app.bsky.feed.post/abc";
+
+        let created = create_timed_thread_mute(
+            &mut conn,
+            actor,
+            thread_uri,
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+
+        let mutes = fetch_timed_thread_mutes(&mut conn, actor);
+        assert_eq!(mutes.len(), 1);
+        assert_eq!(mutes[0].id, created.id);
+        assert_eq!(mutes[0].thread_uri, thread_uri);
+        assert_eq!(fetch_timed_thread_mutes_v1(&mut conn).len(), 1);
+
+        let updated =
+            update_timed_thread_mute_v1(&mut conn, actor, &mutes[0].id, &MuteStatus::Cancelled)
+                .unwrap();
+        assert!(updated);
+        let cancelled = fetch_timed_thread_mute(&mut conn, actor, mutes[0].id).unwrap();
+        assert_eq!(cancelled.status, MuteStatus::Cancelled);
+        assert_eq!(fetch_timed_thread_mutes(&mut conn, actor).len(), 0);
+    }
+
+    #[test]
+    fn test_timed_list_mute_operations() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let actor = "did:plc:actor";
+        let mute_list = "at://did:plc:other/app.bsky.graph.list/mute";
+        let block_list = "at://did:plc:other/app.bsky.graph.list/block";
+        let record_uri = "at://did:plc:actor/app.bsky.graph.listblock/abc";
+
+        let _ = create_timed_list_mute(
+            &mut conn,
+            actor,
+            mute_list,
+            "mute",
+            None,
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+        let _ = create_timed_list_mute(
+            &mut conn,
+            actor,
+            block_list,
+            "block",
+            Some(record_uri),
+            &1000,
+            &3000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+
+        let lists = fetch_timed_list_mutes(&mut conn, actor);
+        assert_eq!(lists.len(), 2);
+        let block = lists.iter().find(|l| l.mode == "block").unwrap();
+        assert_eq!(block.record_uri.as_deref(), Some(record_uri));
+
+        let mute = lists.iter().find(|l| l.mode == "mute").unwrap();
+        let updated =
+            update_timed_list_mute_v1(&mut conn, actor, &mute.id, &MuteStatus::Cancelled).unwrap();
+        assert!(updated);
+        let cancelled = fetch_timed_list_mute(&mut conn, actor, mute.id).unwrap();
+        assert_eq!(cancelled.status, MuteStatus::Cancelled);
+        update_timed_list_mute_v1(&mut conn, actor, &block.id, &MuteStatus::Expiring).unwrap();
+        assert!(
+            update_timed_list_mute_v1(&mut conn, actor, &block.id, &MuteStatus::Expired).unwrap()
+        );
+        assert_eq!(fetch_timed_list_mutes_v1(&mut conn).len(), 0);
+    }
+
+    #[test]
+    fn test_record_resolve_failures() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let actor = "did:plc:actor";
+        let record_uri = "at://did:plc:actor/app.bsky.graph.block/1";
+        create_timed_block(
+            &mut conn,
+            actor,
+            "did:plc:blocked",
+            record_uri,
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+        let id = fetch_timed_blocks(&mut conn, actor)[0].id;
+
+        // Only rows claimed by the resolver can fail.
+        assert!(record_timed_block_failure_v1(&mut conn, &id, "timeout", &2060, &2).is_err());
+        assert!(update_timed_block_v1(&mut conn, actor, &id, &MuteStatus::Expiring).unwrap());
+        assert!(record_timed_block_failure_v1(&mut conn, &id, "timeout", &2060, &2).unwrap());
+        let blocks = fetch_timed_blocks(&mut conn, actor);
+        assert_eq!(blocks[0].status, MuteStatus::Active);
+        assert_eq!(blocks[0].attempt_count, 1);
+        assert_eq!(blocks[0].last_error.as_deref(), Some("timeout"));
+        assert_eq!(blocks[0].next_attempt_date, Some(2060));
+        // The retry is what the expiry scheduler waits for.
+        assert_eq!(fetch_active_expiration_dates(&mut conn), vec![2060]);
+
+        assert!(update_timed_block_v1(&mut conn, actor, &id, &MuteStatus::Expiring).unwrap());
+        assert!(record_timed_block_failure_v1(&mut conn, &id, "still down", &2180, &2).unwrap());
+        assert!(fetch_timed_blocks(&mut conn, actor).is_empty());
+        let failed = fetch_failed_timed_blocks(&mut conn, Some(actor));
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].status, MuteStatus::Failed);
+        assert_eq!(failed[0].attempt_count, 2);
+        assert_eq!(failed[0].last_error.as_deref(), Some("still down"));
+        assert_eq!(failed[0].next_attempt_date, None);
+        assert_eq!(fetch_failed_timed_blocks(&mut conn, None).len(), 1);
+        assert!(fetch_failed_timed_blocks(&mut conn, Some("did:plc:other")).is_empty());
+
+        // Failed rows are not retried and never become active again.
+        assert!(record_timed_block_failure_v1(&mut conn, &id, "again", &2400, &2).is_err());
+        assert!(matches!(
+            update_timed_block_v1(&mut conn, actor, &id, &MuteStatus::Active),
+            Err(AppError::InvalidTransition(
+                MuteStatus::Failed,
+                MuteStatus::Active
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_fetch_active_expiration_dates() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let actor = "did:plc:actor";
+        let _ = create_timed_mute(
+            &mut conn,
+            actor,
+            "muted1",
+            &1000,
+            &3000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+        let _ = create_timed_mute(
+            &mut conn,
+            actor,
+            "muted2",
+            &1000,
+            &4000,
+            &MuteStatus::Expired,
+            &false,
+        )
+        .unwrap();
+        let _ = create_timed_mute_word(
+            &mut conn,
+            actor,
+            "word1",
+            "tag,content",
+            "all",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+        let _ = create_timed_block(
+            &mut conn,
+            actor,
+            "blocked1",
+            "at://a/b/c",
+            &1000,
+            &3000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+
+        assert_eq!(fetch_active_expiration_dates(&mut conn), vec![2000, 3000]);
+    }
+
+    #[test]
+    fn test_v1_operations() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let actor = "did:plc:actor";
+        let _ = create_timed_mute(
+            &mut conn,
+            actor,
+            "muted1",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+        let _ = create_timed_mute_word(
+            &mut conn,
+            actor,
+            "word1",
+            "tag,content",
+            "all",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+
+        let _ = create_profile(&mut conn, "did1", "handle1", "pass1").unwrap();
+
+        let p = fetch_profile_v1(&mut conn, "did1");
+        assert_eq!(p.len(), 1);
+
+        let mutes = fetch_timed_mutes_v1(&mut conn);
+        assert_eq!(mutes.len(), 1);
+
+        let words = fetch_timed_mute_words_v1(&mut conn);
+        assert_eq!(words.len(), 1);
+
+        for status in [MuteStatus::Expiring, MuteStatus::Expired] {
+            update_timed_mute_v1(&mut conn, actor, &mutes[0].id, &status).unwrap();
+            update_timed_mute_word_v1(&mut conn, actor, &words[0].id, &status).unwrap();
+        }
+        let mutes = fetch_timed_mutes_v1(&mut conn);
+        assert_eq!(mutes.len(), 0);
+        let words = fetch_timed_mute_words_v1(&mut conn);
+        assert_eq!(words.len(), 0);
+    }
+
+    #[test]
+    fn test_reencrypt_profiles() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let _ = create_profile(&mut conn, "did1", "handle1", "pass1").unwrap();
+        diesel::sql_query(
+            "INSERT INTO profile (did, handle, password, status) VALUES ('did2', 'handle2', 'legacy', 0)",
+        )
+        .execute(&mut conn)
+        .unwrap();
+
+        // Only the legacy plain text row needs to change.
+        let updated = reencrypt_profiles(&mut conn).unwrap();
+        assert_eq!(updated, 1);
+        let p = fetch_profile(&mut conn, "did2");
+        assert_ne!(p[0].password, "legacy");
+        assert_eq!(crypto::decrypt(&p[0].password).unwrap(), "legacy");
+
+        assert_eq!(reencrypt_profiles(&mut conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_profile_session_operations() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        let did = "did:plc:123";
+        assert!(fetch_profile_session(&mut conn, did).unwrap().is_none());
+
+        let _ = save_profile_session(&mut conn, did, "access1", "refresh1", &true, None).unwrap();
+        let session = fetch_profile_session(&mut conn, did).unwrap().unwrap();
+        assert_eq!(session.access_jwt, "access1");
+        assert_eq!(session.refresh_jwt, "refresh1");
+        assert!(session.active);
+
+        // Saving again replaces the existing session instead of adding a row.
+        let _ = save_profile_session(&mut conn, did, "access2", "refresh2", &true, None).unwrap();
+        let session = fetch_profile_session(&mut conn, did).unwrap().unwrap();
+        assert_eq!(session.access_jwt, "access2");
+        assert_eq!(session.refresh_jwt, "refresh2");
+
+        let raw: Vec<String> = crate::schema::profile_session::table
+            .select(crate::schema::profile_session::refresh_jwt)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(raw.len(), 1);
+        assert_ne!(raw[0], "refresh2");
+
+        let _ = delete_profile_session(&mut conn, did).unwrap();
+        assert!(fetch_profile_session(&mut conn, did).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_mute_groups() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+        let actor = "did:plc:actor";
+
+        let group = create_mute_group(&mut conn, actor, "World Cup final", &1000).unwrap();
+        assert_eq!(group.name, "World Cup final");
+        assert!(create_mute_group(&mut conn, actor, "world cup FINAL", &1000).is_err());
+        assert!(fetch_mute_group_by_name(&mut conn, actor, "WORLD CUP FINAL").is_some());
+        assert!(fetch_mute_group(&mut conn, "did:plc:other", group.id).is_none());
+
+        let words = vec!["spoiler".to_string(), "score".to_string()];
+        assert_eq!(
+            add_mute_group_members(&mut conn, &group.id, "word", &words).unwrap(),
+            2
+        );
+        let more = vec!["Spoiler".to_string(), "penalties".to_string()];
+        assert_eq!(
+            add_mute_group_members(&mut conn, &group.id, "word", &more).unwrap(),
+            1
+        );
+        let dids = vec!["did:plc:muted".to_string()];
+        add_mute_group_members(&mut conn, &group.id, "mute", &dids).unwrap();
+        assert_eq!(fetch_mute_group_members(&mut conn, group.id).len(), 4);
+
+        // Activation links the rows; the group is active while any is unresolved.
+        create_timed_mute(
+            &mut conn,
+            actor,
+            "did:plc:muted",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+            &false,
+        )
+        .unwrap();
+        create_timed_mute_word(
+            &mut conn,
+            actor,
+            "spoiler",
+            "tag,content",
+            "all",
+            &1000,
+            &2000,
+            &MuteStatus::Active,
+        )
+        .unwrap();
+        let mute_id = fetch_active_timed_mute(&mut conn, actor, "did:plc:muted")
+            .unwrap()
+            .id;
+        let word_id = fetch_active_timed_mute_word(&mut conn, actor, "spoiler")
+            .unwrap()
+            .id;
+        assert_eq!(count_unresolved_group_rows(&mut conn, group.id), 0);
+        assert_eq!(
+            assign_mute_group(&mut conn, actor, &group.id, vec![mute_id], vec![word_id]).unwrap(),
+            2
+        );
+        assert_eq!(count_unresolved_group_rows(&mut conn, group.id), 2);
+        assert_eq!(fetch_timed_mutes_v1(&mut conn)[0].group_id, Some(group.id));
+        // Another group can't take them over.
+        let other = create_mute_group(&mut conn, actor, "Season finale", &1000).unwrap();
+        assert_eq!(
+            assign_mute_group(&mut conn, actor, &other.id, vec![mute_id], vec![word_id]).unwrap(),
+            0
+        );
+        assert_eq!(count_unresolved_group_rows(&mut conn, other.id), 0);
+        assert!(delete_mute_group(&mut conn, actor, &other.id).unwrap());
+
+        assert!(update_mute_group_expiration(&mut conn, actor, &group.id, &2000).unwrap());
+        let group = fetch_mute_group(&mut conn, actor, group.id).unwrap();
+        assert_eq!(group.expiration_date, Some(2000));
+
+        update_timed_mute_v1(&mut conn, actor, &mute_id, &MuteStatus::Expiring).unwrap();
+        update_timed_mute_v1(&mut conn, actor, &mute_id, &MuteStatus::Expired).unwrap();
+        assert_eq!(count_unresolved_group_rows(&mut conn, group.id), 1);
+
+        assert!(!delete_mute_group(&mut conn, "did:plc:other", &group.id).unwrap());
+        assert!(delete_mute_group(&mut conn, actor, &group.id).unwrap());
+        assert!(fetch_mute_groups(&mut conn, actor).is_empty());
+        assert!(fetch_mute_group_members(&mut conn, group.id).is_empty());
+        // The running activation keeps expiring as a unit.
+        assert_eq!(count_unresolved_group_rows(&mut conn, group.id), 1);
+    }
+
+    #[test]
+    fn test_recurring_mutes() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+        let actor = "did:plc:actor";
+        let new_rule = |kind, target, next_run_date| NewRecurringMute {
+            actor,
+            kind,
+            target,
+            cron: "0 9 * * MON-FRI",
+            timezone: "Europe/Berlin",
+            duration: &28800,
+            next_run_date,
+            created_date: &1000,
+        };
+
+        let word = create_recurring_mute(&mut conn, &new_rule("word", "standup", &2000)).unwrap();
+        assert_eq!(word.target, "standup");
+        assert_eq!(word.last_run_date, None);
+        let mute =
+            create_recurring_mute(&mut conn, &new_rule("mute", "did:plc:muted", &5000)).unwrap();
+        assert_eq!(fetch_recurring_mutes(&mut conn, actor).len(), 2);
+        assert!(fetch_recurring_mutes(&mut conn, "did:plc:other").is_empty());
+
+        let due = fetch_due_recurring_mutes(&mut conn, 2000);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, word.id);
+
+        assert!(update_recurring_mute_run(&mut conn, &word.id, &9000, &2000).unwrap());
+        // A concurrent run can't claim the same occurrence again.
+        assert!(!update_recurring_mute_run(&mut conn, &word.id, &9000, &2000).unwrap());
+        assert!(fetch_due_recurring_mutes(&mut conn, 2000).is_empty());
+        assert!(
+            record_recurring_mute_error(&mut conn, &word.id, &2000, "boom", &1, Some(&2060))
+                .unwrap()
+        );
+        let word = fetch_recurring_mutes(&mut conn, actor).remove(0);
+        assert_eq!(word.next_run_date, 9000);
+        assert_eq!(word.last_run_date, Some(2000));
+        assert_eq!(word.last_error.as_deref(), Some("boom"));
+        assert_eq!(word.attempt_count, 1);
+
+        // The failed occurrence is retried once its attempt is due, by one
+        // run only.
+        assert!(fetch_recurring_mute_retries(&mut conn, 2059).is_empty());
+        assert_eq!(fetch_recurring_mute_retries(&mut conn, 2060).len(), 1);
+        assert!(!claim_recurring_mute_retry(&mut conn, &word.id, &1000, &2060).unwrap());
+        assert!(claim_recurring_mute_retry(&mut conn, &word.id, &2000, &2060).unwrap());
+        assert!(!claim_recurring_mute_retry(&mut conn, &word.id, &2000, &2060).unwrap());
+        assert!(fetch_recurring_mute_retries(&mut conn, 2060).is_empty());
+
+        // The next occurrence drops any retry still pending.
+        assert!(
+            record_recurring_mute_error(&mut conn, &word.id, &2000, "boom", &2, Some(&2180))
+                .unwrap()
+        );
+        assert!(update_recurring_mute_run(&mut conn, &word.id, &12000, &9000).unwrap());
+        let word = fetch_recurring_mutes(&mut conn, actor).remove(0);
+        assert_eq!(word.last_error, None);
+        assert_eq!(word.attempt_count, 0);
+        assert_eq!(word.next_attempt_date, None);
+
+        // A rule whose schedule can't be read is moved on without a run.
+        assert!(postpone_recurring_mute(&mut conn, &mute.id, &5000, &8000, "bad cron").unwrap());
+        let mute = fetch_recurring_mutes(&mut conn, actor).remove(1);
+        assert_eq!(mute.next_run_date, 8000);
+        assert_eq!(mute.last_run_date, None);
+        assert_eq!(mute.last_error.as_deref(), Some("bad cron"));
+
+        assert!(!delete_recurring_mute(&mut conn, "did:plc:other", &mute.id).unwrap());
+        assert!(delete_recurring_mute(&mut conn, actor, &mute.id).unwrap());
+        assert_eq!(fetch_recurring_mutes(&mut conn, actor).len(), 1);
+    }
+
+    #[test]
+    fn test_scheduled_timed_mutes() {
+        let pool = setup_test_pool();
+        let mut conn = pool.get().unwrap();
+        let actor = "did:plc:actor";
+
+        let mute =
+            create_scheduled_timed_mute(&mut conn, actor, "did:plc:muted", &1000, &3000, &6000)
+                .unwrap();
+        assert_eq!(mute.status, MuteStatus::Scheduled);
+        assert_eq!(mute.start_date, Some(3000));
+        let word = create_scheduled_timed_mute_word(
+            &mut conn,
+            actor,
+            "finale",
+            "tag,content",
+            "all",
+            &1000,
+            &4000,
+            &6000,
+        )
+        .unwrap();
+        assert_eq!(word.start_date, Some(4000));
+
+        // Scheduled rows are listed on their own and never look active.
+        assert!(fetch_timed_mutes(&mut conn, actor).is_empty());
+        assert!(fetch_active_timed_mute(&mut conn, actor, "did:plc:muted").is_none());
+        assert_eq!(fetch_scheduled_timed_mutes(&mut conn, actor).len(), 1);
+        assert_eq!(fetch_scheduled_timed_mute_words(&mut conn, actor).len(), 1);
+        let mut dates = fetch_scheduled_start_dates(&mut conn);
+        dates.sort();
+        assert_eq!(dates, [3000, 4000]);
+
+        assert!(fetch_due_scheduled_timed_mutes(&mut conn, 2999).is_empty());
+        assert_eq!(fetch_due_scheduled_timed_mutes(&mut conn, 3000).len(), 1);
+        assert!(fetch_due_scheduled_timed_mute_words(&mut conn, 3000).is_empty());
+
+        assert!(record_timed_mute_start_failure(&mut conn, &mute.id, "busy", &3060, &5).unwrap());
+        assert!(start_timed_mute(&mut conn, &mute.id, &true).unwrap());
+        assert!(start_timed_mute(&mut conn, &mute.id, &true).is_err());
+        let active = fetch_active_timed_mute(&mut conn, actor, "did:plc:muted").unwrap();
+        assert!(active.was_muted_before);
+        assert_eq!(active.attempt_count, 0);
+        assert_eq!(active.last_error, None);
+        assert_eq!(active.next_attempt_date, None);
+        assert_eq!(fetch_scheduled_start_dates(&mut conn), [4000]);
+
+        // A failed start is retried later until the attempts run out.
+        assert!(
+            record_timed_mute_word_start_failure(&mut conn, &word.id, "busy", &4060, &2).unwrap()
+        );
+        let retried = fetch_timed_mute_word(&mut conn, actor, word.id).unwrap();
+        assert_eq!(retried.status, MuteStatus::Scheduled);
+        assert_eq!(retried.attempt_count, 1);
+        assert_eq!(retried.next_attempt_date, Some(4060));
+        assert!(fetch_due_scheduled_timed_mute_words(&mut conn, 4059).is_empty());
+        assert_eq!(
+            fetch_due_scheduled_timed_mute_words(&mut conn, 4060).len(),
+            1
+        );
+        assert_eq!(fetch_scheduled_start_dates(&mut conn), [4060]);
+
+        assert!(
+            record_timed_mute_word_start_failure(&mut conn, &word.id, "boom", &4180, &2).unwrap()
+        );
+        let failed = fetch_timed_mute_word(&mut conn, actor, word.id).unwrap();
+        assert_eq!(failed.status, MuteStatus::Failed);
+        assert_eq!(failed.attempt_count, 2);
+        assert_eq!(failed.next_attempt_date, None);
+        assert_eq!(failed.last_error.as_deref(), Some("boom"));
+        assert!(start_timed_mute_word(&mut conn, &word.id).is_err());
+        assert!(record_timed_mute_start_failure(&mut conn, &mute.id, "boom", &4060, &2).is_err());
+    }
+}
+
+pub fn establish_connection(database_url: &str) -> SqliteConnection {
+    SqliteConnection::establish(database_url)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
+}
+
+/// `was_muted_before` records that the user already muted the account, so
+/// the mute is left in place when the timed mute ends.
+pub fn create_timed_mute(
+    conn: &mut SqliteConnection,
+    actor: &str,
+    muted_actor: &str,
+    created_date: &i64,
+    expiration_date: &i64,
+    other_status: &MuteStatus,
+    was_muted_before: &bool,
+) -> Result<usize> {
+    use crate::schema::timed_mute;
+    let new_timed_mute = NewTimedMute {
+        actor,
+        muted_actor,
+        created_date,
+        expiration_date,
+        status: other_status,
+        was_muted_before,
+        start_date: None,
+    };
+
+    diesel::insert_into(timed_mute::table)
+        .values(&new_timed_mute)
+        .execute(conn)
+        .map_err(AppError::from)
+}
+
+/// `targets` is a comma separated list of mute word targets (`tag`, `content`).
+#[allow(clippy::too_many_arguments)]
+pub fn create_timed_mute_word(
+    conn: &mut SqliteConnection,
+    actor: &str,
+    muted_word: &str,
+    targets: &str,
+    actor_target: &str,
+    created_date: &i64,
+    expiration_date: &i64,
+    status: &MuteStatus,
+) -> Result<usize> {
+    use crate::schema::timed_mute_word;
+    let new_timed_mute = NewTimedMuteWord {
+        actor,
+        muted_word,
+        targets,
+        actor_target,
+        created_date,
+        expiration_date,
+        status,
+        start_date: None,
+    };
+
+    diesel::insert_into(timed_mute_word::table)
+        .values(&new_timed_mute)
+        .execute(conn)
+        .map_err(AppError::from)
+}
+
+/// `expired_date` and `cancelled_date` for a row moving to `status`: expired
+/// and cancelled record the current time.
+fn closed_dates(status: &MuteStatus) -> (Option<i64>, Option<i64>) {
+    let now = chrono::offset::Utc::now().timestamp();
+    (
+        (*status == MuteStatus::Expired).then_some(now),
+        (*status == MuteStatus::Cancelled).then_some(now),
+    )
+}
+
+pub fn update_timed_mute_v1(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_mute_id: &i32,
+    status: &MuteStatus,
+) -> Result<bool> {
+    use crate::schema::timed_mute;
+
+    let current = timed_mute::table
+        .filter(timed_mute::rowid.eq(timed_mute_id))
+        .filter(timed_mute::actor.eq(_actor))
+        .select(timed_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition(*status)?;
+    let (expired_date, cancelled_date) = closed_dates(status);
+    let res = diesel::update(timed_mute::table)
+        .filter(timed_mute::rowid.eq(timed_mute_id))
+        .filter(timed_mute::actor.eq(_actor))
+        .filter(timed_mute::status.eq(current))
+        .set((
+            timed_mute::status.eq(status),
+            timed_mute::expired_date.eq(expired_date),
+            timed_mute::cancelled_date.eq(cancelled_date),
+        ))
+        .execute(conn)?;
+
+    Ok(res > 0)
+}
+
+pub fn update_timed_mute_word_v1(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_mute_word_id: &i32,
+    status: &MuteStatus,
+) -> Result<bool> {
+    use crate::schema::timed_mute_word;
+
+    let current = timed_mute_word::table
+        .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+        .filter(timed_mute_word::actor.eq(_actor))
+        .select(timed_mute_word::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition(*status)?;
+    let (expired_date, cancelled_date) = closed_dates(status);
+    let res = diesel::update(timed_mute_word::table)
+        .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+        .filter(timed_mute_word::actor.eq(_actor))
+        .filter(timed_mute_word::status.eq(current))
+        .set((
+            timed_mute_word::status.eq(status),
+            timed_mute_word::expired_date.eq(expired_date),
+            timed_mute_word::cancelled_date.eq(cancelled_date),
+        ))
+        .execute(conn)?;
+
+    Ok(res > 0)
+}
+
+/// Moves the expiration date of an active timed mute. The first expiration
+/// date is kept in `original_expiration_date` so the history shows the change.
+pub fn extend_timed_mute(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_mute_id: &i32,
+    new_expiration_time: &i64,
+    extended_time: &i64,
+) -> Result<bool> {
+    use crate::schema::timed_mute;
+
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        diesel::update(timed_mute::table)
+            .filter(timed_mute::rowid.eq(timed_mute_id))
+            .filter(timed_mute::actor.eq(_actor))
+            .filter(timed_mute::status.eq(MuteStatus::Active))
+            .filter(timed_mute::original_expiration_date.is_null())
+            .set(timed_mute::original_expiration_date.eq(timed_mute::expiration_date.nullable()))
+            .execute(conn)?;
+        diesel::update(timed_mute::table)
+            .filter(timed_mute::rowid.eq(timed_mute_id))
+            .filter(timed_mute::actor.eq(_actor))
+            .filter(timed_mute::status.eq(MuteStatus::Active))
+            .set((
+                timed_mute::expiration_date.eq(new_expiration_time),
+                timed_mute::extended_date.eq(Some(extended_time)),
+            ))
+            .execute(conn)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Moves the expiration date of an active timed mute word, keeping the first
+/// expiration date in `original_expiration_date`.
+pub fn extend_timed_mute_word(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_mute_word_id: &i32,
+    new_expiration_time: &i64,
+    extended_time: &i64,
+) -> Result<bool> {
+    use crate::schema::timed_mute_word;
+
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        diesel::update(timed_mute_word::table)
+            .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+            .filter(timed_mute_word::actor.eq(_actor))
+            .filter(timed_mute_word::status.eq(MuteStatus::Active))
+            .filter(timed_mute_word::original_expiration_date.is_null())
+            .set(
+                timed_mute_word::original_expiration_date
+                    .eq(timed_mute_word::expiration_date.nullable()),
+            )
+            .execute(conn)?;
+        diesel::update(timed_mute_word::table)
+            .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+            .filter(timed_mute_word::actor.eq(_actor))
+            .filter(timed_mute_word::status.eq(MuteStatus::Active))
+            .set((
+                timed_mute_word::expiration_date.eq(new_expiration_time),
+                timed_mute_word::extended_date.eq(Some(extended_time)),
+            ))
+            .execute(conn)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Marks active timed mutes whose mute was removed outside this service with
+/// drifted, recording when the drift was found.
+pub fn mark_timed_mutes_drifted(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_mute_ids: Vec<i32>,
+    drift_time: &i64,
+) -> Result<usize> {
+    use crate::schema::timed_mute;
+
+    let current: Vec<MuteStatus> = timed_mute::table
+        .filter(timed_mute::rowid.eq_any(&timed_mute_ids))
+        .filter(timed_mute::actor.eq(_actor))
+        .select(timed_mute::status)
+        .load(conn)?;
+    for status in current {
+        status.transition_from(MuteStatus::Active, MuteStatus::Drifted)?;
+    }
+    diesel::update(timed_mute::table)
+        .filter(timed_mute::rowid.eq_any(timed_mute_ids))
+        .filter(timed_mute::actor.eq(_actor))
+        .filter(timed_mute::status.eq(MuteStatus::Active))
+        .set((
+            timed_mute::status.eq(MuteStatus::Drifted),
+            timed_mute::drift_date.eq(Some(drift_time)),
+        ))
+        .execute(conn)
+        .map_err(AppError::from)
+}
+
+/// Marks active timed mute words that were removed outside this service with
+/// drifted, recording when the drift was found.
+pub fn mark_timed_mute_words_drifted(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_mute_word_ids: Vec<i32>,
+    drift_time: &i64,
+) -> Result<usize> {
+    use crate::schema::timed_mute_word;
+
+    let current: Vec<MuteStatus> = timed_mute_word::table
+        .filter(timed_mute_word::rowid.eq_any(&timed_mute_word_ids))
+        .filter(timed_mute_word::actor.eq(_actor))
+        .select(timed_mute_word::status)
+        .load(conn)?;
+    for status in current {
+        status.transition_from(MuteStatus::Active, MuteStatus::Drifted)?;
+    }
+    diesel::update(timed_mute_word::table)
+        .filter(timed_mute_word::rowid.eq_any(timed_mute_word_ids))
+        .filter(timed_mute_word::actor.eq(_actor))
+        .filter(timed_mute_word::status.eq(MuteStatus::Active))
+        .set((
+            timed_mute_word::status.eq(MuteStatus::Drifted),
+            timed_mute_word::drift_date.eq(Some(drift_time)),
+        ))
+        .execute(conn)
+        .map_err(AppError::from)
+}
+
+/// Returns the user's timed mute with the given id, whatever its status.
+pub fn fetch_timed_mute(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    timed_mute_id: i32,
+) -> Option<TimedMute> {
+    use crate::schema::timed_mute;
+    timed_mute::table
+        .filter(timed_mute::rowid.eq(timed_mute_id))
+        .filter(timed_mute::actor.eq(user_id))
+        .select(TimedMute::as_select())
+        .first(conn)
+        .optional()
+        .unwrap_or_default()
+}
+
+/// Returns the user's timed mute word with the given id, whatever its status.
+pub fn fetch_timed_mute_word(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    timed_mute_word_id: i32,
+) -> Option<TimedMuteWord> {
+    use crate::schema::timed_mute_word;
+    timed_mute_word::table
+        .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+        .filter(timed_mute_word::actor.eq(user_id))
+        .select(TimedMuteWord::as_select())
+        .first(conn)
+        .optional()
+        .unwrap_or_default()
+}
+
+/// Returns the user's active timed mute for `muted_actor`, if any, or the
+/// one the resolver is expiring, which still holds the target.
+pub fn fetch_active_timed_mute(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    muted_actor: &str,
+) -> Option<TimedMute> {
+    use crate::schema::timed_mute;
+    timed_mute::table
+        .filter(timed_mute::actor.eq(user_id))
+        .filter(timed_mute::muted_actor.eq(muted_actor))
+        .filter(timed_mute::status.eq_any(MuteStatus::UNRESOLVED))
+        .select(TimedMute::as_select())
+        .first(conn)
+        .optional()
+        .unwrap_or_default()
+}
+
+/// Returns the form two muted words are compared in. Case is ignored for
+/// ASCII letters only, as the `COLLATE NOCASE` unique index on timed mute
+/// words does, so a lookup never finds a different row than the index.
+pub fn normalize_word(word: &str) -> String {
+    word.trim().to_ascii_lowercase()
+}
+
+/// Returns the user's active timed mute word for `muted_word`, if any, or the
+/// one the resolver is expiring. Words are compared with `normalize_word`.
+pub fn fetch_active_timed_mute_word(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    muted_word: &str,
+) -> Option<TimedMuteWord> {
+    use crate::schema::timed_mute_word;
+    let muted_word = normalize_word(muted_word);
+    timed_mute_word::table
+        .filter(timed_mute_word::actor.eq(user_id))
+        .filter(timed_mute_word::status.eq_any(MuteStatus::UNRESOLVED))
+        .select(TimedMuteWord::as_select())
+        .load(conn)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|w| normalize_word(&w.muted_word) == muted_word)
+}
+
+/// Marks the active timed mute `replaced_id` as superseded and
+/// inserts a new active row for the same target in its place. A row that is
+/// not active is an `InvalidTransition`; one that changed meanwhile writes
+/// nothing and returns 0.
+#[allow(clippy::too_many_arguments)]
+pub fn replace_timed_mute(
+    conn: &mut SqliteConnection,
+    actor: &str,
+    replaced_id: &i32,
+    muted_actor: &str,
+    created_date: &i64,
+    expiration_date: &i64,
+    was_muted_before: &bool,
+) -> Result<usize> {
+    use crate::schema::timed_mute;
+    let current = timed_mute::table
+        .filter(timed_mute::rowid.eq(replaced_id))
+        .filter(timed_mute::actor.eq(actor))
+        .select(timed_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(0);
+    };
+    current.transition_from(MuteStatus::Active, MuteStatus::Superseded)?;
+
+    let new_timed_mute = NewTimedMute {
+        actor,
+        muted_actor,
+        created_date,
+        expiration_date,
+        status: &MuteStatus::Active,
+        was_muted_before,
+        start_date: None,
+    };
+
+    conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let superseded = diesel::update(timed_mute::table)
+            .filter(timed_mute::rowid.eq(replaced_id))
+            .filter(timed_mute::actor.eq(actor))
+            .filter(timed_mute::status.eq(MuteStatus::Active))
+            .set(timed_mute::status.eq(MuteStatus::Superseded))
+            .execute(conn)?;
+        if superseded == 0 {
+            return Ok(0);
+        }
+        diesel::insert_into(timed_mute::table)
+            .values(&new_timed_mute)
+            .execute(conn)
+    })
+    .map_err(AppError::from)
+}
+
+/// Marks the active timed mute word `replaced_id` as superseded and
+/// inserts a new active row for the same word in its place. A row that is
+/// not active is an `InvalidTransition`; one that changed meanwhile writes
+/// nothing and returns 0.
+#[allow(clippy::too_many_arguments)]
+pub fn replace_timed_mute_word(
+    conn: &mut SqliteConnection,
+    actor: &str,
+    replaced_id: &i32,
+    muted_word: &str,
+    targets: &str,
+    actor_target: &str,
+    created_date: &i64,
+    expiration_date: &i64,
+) -> Result<usize> {
+    use crate::schema::timed_mute_word;
+    let current = timed_mute_word::table
+        .filter(timed_mute_word::rowid.eq(replaced_id))
+        .filter(timed_mute_word::actor.eq(actor))
+        .select(timed_mute_word::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(0);
+    };
+    current.transition_from(MuteStatus::Active, MuteStatus::Superseded)?;
+
+    let new_timed_mute_word = NewTimedMuteWord {
+        actor,
+        muted_word,
+        targets,
+        actor_target,
+        created_date,
+        expiration_date,
+        status: &MuteStatus::Active,
+        start_date: None,
+    };
+
+    conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let superseded = diesel::update(timed_mute_word::table)
+            .filter(timed_mute_word::rowid.eq(replaced_id))
+            .filter(timed_mute_word::actor.eq(actor))
+            .filter(timed_mute_word::status.eq(MuteStatus::Active))
+            .set(timed_mute_word::status.eq(MuteStatus::Superseded))
+            .execute(conn)?;
+        if superseded == 0 {
+            return Ok(0);
+        }
+        diesel::insert_into(timed_mute_word::table)
+            .values(&new_timed_mute_word)
+            .execute(conn)
+    })
+    .map_err(AppError::from)
+}
+
+pub fn fetch_timed_mutes(conn: &mut SqliteConnection, user_id: &str) -> Vec<TimedMute> {
+    use crate::schema::timed_mute::actor;
+    use crate::schema::timed_mute::dsl::timed_mute;
+    use crate::schema::timed_mute::status;
+    timed_mute
+        .filter(status.eq(MuteStatus::Active))
+        .filter(actor.eq(user_id))
+        .select(TimedMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+pub fn fetch_timed_mute_words(conn: &mut SqliteConnection, user_id: &str) -> Vec<TimedMuteWord> {
+    use crate::schema::timed_mute_word::actor;
+    use crate::schema::timed_mute_word::dsl::timed_mute_word;
+    use crate::schema::timed_mute_word::status;
+    timed_mute_word
+        .filter(status.eq(MuteStatus::Active))
+        .filter(actor.eq(user_id))
+        .select(TimedMuteWord::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Writes a timed mute that starts at `start_date`, with status scheduled.
+/// Nothing is muted on Bluesky until then.
+pub fn create_scheduled_timed_mute(
+    conn: &mut SqliteConnection,
+    actor: &str,
+    muted_actor: &str,
+    created_date: &i64,
+    start_date: &i64,
+    expiration_date: &i64,
+) -> Result<TimedMute> {
+    use crate::schema::timed_mute;
+    conn.transaction::<TimedMute, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(timed_mute::table)
+            .values(&NewTimedMute {
+                actor,
+                muted_actor,
+                created_date,
+                expiration_date,
+                status: &MuteStatus::Scheduled,
+                was_muted_before: &false,
+                start_date: Some(start_date),
+            })
+            .execute(conn)?;
+        timed_mute::table
+            .filter(timed_mute::actor.eq(actor))
+            .order(timed_mute::rowid.desc())
+            .select(TimedMute::as_select())
+            .first(conn)
+    })
+    .map_err(AppError::from)
+}
+
+/// Like [`create_scheduled_timed_mute`], for a word.
+#[allow(clippy::too_many_arguments)]
+pub fn create_scheduled_timed_mute_word(
+    conn: &mut SqliteConnection,
+    actor: &str,
+    muted_word: &str,
+    targets: &str,
+    actor_target: &str,
+    created_date: &i64,
+    start_date: &i64,
+    expiration_date: &i64,
+) -> Result<TimedMuteWord> {
+    use crate::schema::timed_mute_word;
+    conn.transaction::<TimedMuteWord, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(timed_mute_word::table)
+            .values(&NewTimedMuteWord {
+                actor,
+                muted_word,
+                targets,
+                actor_target,
+                created_date,
+                expiration_date,
+                status: &MuteStatus::Scheduled,
+                start_date: Some(start_date),
+            })
+            .execute(conn)?;
+        timed_mute_word::table
+            .filter(timed_mute_word::actor.eq(actor))
+            .order(timed_mute_word::rowid.desc())
+            .select(TimedMuteWord::as_select())
+            .first(conn)
+    })
+    .map_err(AppError::from)
+}
+
+/// Returns the user's scheduled timed mutes, soonest first.
+pub fn fetch_scheduled_timed_mutes(conn: &mut SqliteConnection, user_id: &str) -> Vec<TimedMute> {
+    use crate::schema::timed_mute;
+    timed_mute::table
+        .filter(timed_mute::status.eq(MuteStatus::Scheduled))
+        .filter(timed_mute::actor.eq(user_id))
+        .order(timed_mute::start_date.asc())
+        .select(TimedMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Returns the user's scheduled timed mute words, soonest first.
+pub fn fetch_scheduled_timed_mute_words(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+) -> Vec<TimedMuteWord> {
+    use crate::schema::timed_mute_word;
+    timed_mute_word::table
+        .filter(timed_mute_word::status.eq(MuteStatus::Scheduled))
+        .filter(timed_mute_word::actor.eq(user_id))
+        .order(timed_mute_word::start_date.asc())
+        .select(TimedMuteWord::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Returns every user's scheduled timed mutes whose start date has come.
+pub fn fetch_due_scheduled_timed_mutes(conn: &mut SqliteConnection, now: i64) -> Vec<TimedMute> {
+    use crate::schema::timed_mute;
+    timed_mute::table
+        .filter(timed_mute::status.eq(MuteStatus::Scheduled))
+        .filter(timed_mute::start_date.le(now))
+        .filter(
+            timed_mute::next_attempt_date
+                .is_null()
+                .or(timed_mute::next_attempt_date.le(now)),
+        )
+        .order(timed_mute::start_date.asc())
+        .select(TimedMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Returns every user's scheduled timed mute words whose start date has
+/// come, leaving out those waiting for a retry.
+pub fn fetch_due_scheduled_timed_mute_words(
+    conn: &mut SqliteConnection,
+    now: i64,
+) -> Vec<TimedMuteWord> {
+    use crate::schema::timed_mute_word;
+    timed_mute_word::table
+        .filter(timed_mute_word::status.eq(MuteStatus::Scheduled))
+        .filter(timed_mute_word::start_date.le(now))
+        .filter(
+            timed_mute_word::next_attempt_date
+                .is_null()
+                .or(timed_mute_word::next_attempt_date.le(now)),
+        )
+        .order(timed_mute_word::start_date.asc())
+        .select(TimedMuteWord::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Moves a scheduled timed mute to active once it is muted on Bluesky. The
+/// attempts made to start it are cleared, so they don't count against
+/// undoing it later.
+pub fn start_timed_mute(
+    conn: &mut SqliteConnection,
+    timed_mute_id: &i32,
+    was_muted_before: &bool,
+) -> Result<bool> {
+    use crate::schema::timed_mute;
+    let current = timed_mute::table
+        .filter(timed_mute::rowid.eq(timed_mute_id))
+        .select(timed_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Scheduled, MuteStatus::Active)?;
+
+    let res = diesel::update(timed_mute::table)
+        .filter(timed_mute::rowid.eq(timed_mute_id))
+        .filter(timed_mute::status.eq(MuteStatus::Scheduled))
+        .set((
+            timed_mute::status.eq(MuteStatus::Active),
+            timed_mute::was_muted_before.eq(was_muted_before),
+            timed_mute::attempt_count.eq(0),
+            timed_mute::last_error.eq(None::<String>),
+            timed_mute::next_attempt_date.eq(None::<i64>),
+        ))
+        .execute(conn)?;
+    Ok(res > 0)
+}
+
+/// Moves a scheduled timed mute word to active once it is in the preference,
+/// clearing the attempts made to start it.
+pub fn start_timed_mute_word(
+    conn: &mut SqliteConnection,
+    timed_mute_word_id: &i32,
+) -> Result<bool> {
+    use crate::schema::timed_mute_word;
+    let current = timed_mute_word::table
+        .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+        .select(timed_mute_word::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Scheduled, MuteStatus::Active)?;
+
+    let res = diesel::update(timed_mute_word::table)
+        .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+        .filter(timed_mute_word::status.eq(MuteStatus::Scheduled))
+        .set((
+            timed_mute_word::status.eq(MuteStatus::Active),
+            timed_mute_word::attempt_count.eq(0),
+            timed_mute_word::last_error.eq(None::<String>),
+            timed_mute_word::next_attempt_date.eq(None::<i64>),
+        ))
+        .execute(conn)?;
+    Ok(res > 0)
+}
+
+/// Records a failed attempt to start a scheduled timed mute. It stays scheduled
+/// and is tried again at `next_attempt_time`, until `max_attempts` is
+/// reached and it is marked failed.
+pub fn record_timed_mute_start_failure(
+    conn: &mut SqliteConnection,
+    timed_mute_id: &i32,
+    error: &str,
+    next_attempt_time: &i64,
+    max_attempts: &i32,
+) -> Result<bool> {
+    use crate::schema::timed_mute;
+
+    let current = timed_mute::table
+        .filter(timed_mute::rowid.eq(timed_mute_id))
+        .select(timed_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Scheduled, MuteStatus::Failed)?;
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let res = diesel::update(timed_mute::table)
+            .filter(timed_mute::rowid.eq(timed_mute_id))
+            .filter(timed_mute::status.eq(MuteStatus::Scheduled))
+            .set((
+                timed_mute::attempt_count.eq(timed_mute::attempt_count + 1),
+                timed_mute::last_error.eq(Some(error)),
+                timed_mute::next_attempt_date.eq(Some(next_attempt_time)),
+            ))
+            .execute(conn)?;
+        diesel::update(timed_mute::table)
+            .filter(timed_mute::rowid.eq(timed_mute_id))
+            .filter(timed_mute::status.eq(MuteStatus::Scheduled))
+            .filter(timed_mute::attempt_count.ge(max_attempts))
+            .set((
+                timed_mute::status.eq(MuteStatus::Failed),
+                timed_mute::next_attempt_date.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(res)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Records a failed attempt to start a scheduled timed mute word. It stays scheduled
+/// and is tried again at `next_attempt_time`, until `max_attempts` is
+/// reached and it is marked failed.
+pub fn record_timed_mute_word_start_failure(
+    conn: &mut SqliteConnection,
+    timed_mute_word_id: &i32,
+    error: &str,
+    next_attempt_time: &i64,
+    max_attempts: &i32,
+) -> Result<bool> {
+    use crate::schema::timed_mute_word;
+
+    let current = timed_mute_word::table
+        .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+        .select(timed_mute_word::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Scheduled, MuteStatus::Failed)?;
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let res = diesel::update(timed_mute_word::table)
+            .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+            .filter(timed_mute_word::status.eq(MuteStatus::Scheduled))
+            .set((
+                timed_mute_word::attempt_count.eq(timed_mute_word::attempt_count + 1),
+                timed_mute_word::last_error.eq(Some(error)),
+                timed_mute_word::next_attempt_date.eq(Some(next_attempt_time)),
+            ))
+            .execute(conn)?;
+        diesel::update(timed_mute_word::table)
+            .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+            .filter(timed_mute_word::status.eq(MuteStatus::Scheduled))
+            .filter(timed_mute_word::attempt_count.ge(max_attempts))
+            .set((
+                timed_mute_word::status.eq(MuteStatus::Failed),
+                timed_mute_word::next_attempt_date.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(res)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Start dates of every scheduled timed mute and timed mute word, or the
+/// time of the next attempt for those waiting for a retry.
+pub fn fetch_scheduled_start_dates(conn: &mut SqliteConnection) -> Vec<i64> {
+    use crate::schema::{timed_mute, timed_mute_word};
+    let mut dates: Vec<(Option<i64>, Option<i64>)> = timed_mute::table
+        .filter(timed_mute::status.eq(MuteStatus::Scheduled))
+        .select((timed_mute::start_date, timed_mute::next_attempt_date))
+        .load(conn)
+        .unwrap_or_default();
+    dates.extend(
+        timed_mute_word::table
+            .filter(timed_mute_word::status.eq(MuteStatus::Scheduled))
+            .select((
+                timed_mute_word::start_date,
+                timed_mute_word::next_attempt_date,
+            ))
+            .load::<(Option<i64>, Option<i64>)>(conn)
+            .unwrap_or_default(),
+    );
+    dates
+        .into_iter()
+        .filter_map(|(start_date, next_attempt_date)| {
+            start_date.map(|start| next_attempt_date.map_or(start, |next| next.max(start)))
+        })
+        .collect()
+}
+
+/// Filters for the timed mute history. Dates are unix timestamps compared
+/// with `created_date`; `before_id` is the pagination cursor.
+#[derive(Debug, Default)]
+pub struct HistoryFilter<'a> {
+    pub status: Option<MuteStatus>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub target: Option<&'a str>,
+    pub before_id: Option<i32>,
+    pub limit: i64,
+}
+
+/// Escapes `%`, `_` and `\` for a `LIKE` pattern using `\` as escape.
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Returns the user's timed mutes of any status, newest first.
+pub fn fetch_timed_mute_history(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    filter: &HistoryFilter,
+) -> Vec<TimedMute> {
+    use crate::schema::timed_mute;
+    let mut query = timed_mute::table
+        .filter(timed_mute::actor.eq(user_id))
+        .into_boxed();
+    if let Some(status) = filter.status {
+        query = query.filter(timed_mute::status.eq(status));
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(timed_mute::created_date.ge(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(timed_mute::created_date.le(until));
+    }
+    if let Some(target) = filter.target {
+        query = query.filter(timed_mute::muted_actor.eq(target.to_string()));
+    }
+    if let Some(before_id) = filter.before_id {
+        query = query.filter(timed_mute::rowid.lt(before_id));
+    }
+    query
+        .order(timed_mute::rowid.desc())
+        .limit(filter.limit)
+        .select(TimedMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Returns the user's timed mute words of any status, newest first. The
+/// target matches any word containing it, ignoring ASCII case.
+pub fn fetch_timed_mute_word_history(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    filter: &HistoryFilter,
+) -> Vec<TimedMuteWord> {
+    use crate::schema::timed_mute_word;
+    let mut query = timed_mute_word::table
+        .filter(timed_mute_word::actor.eq(user_id))
+        .into_boxed();
+    if let Some(status) = filter.status {
+        query = query.filter(timed_mute_word::status.eq(status));
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(timed_mute_word::created_date.ge(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(timed_mute_word::created_date.le(until));
+    }
+    if let Some(target) = filter.target {
+        query = query.filter(
+            timed_mute_word::muted_word
+                .like(format!("%{}%", escape_like(target)))
+                .escape('\\'),
+        );
+    }
+    if let Some(before_id) = filter.before_id {
+        query = query.filter(timed_mute_word::rowid.lt(before_id));
+    }
+    query
+        .order(timed_mute_word::rowid.desc())
+        .limit(filter.limit)
+        .select(TimedMuteWord::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+pub fn create_mute_event(conn: &mut SqliteConnection, event: &NewMuteEvent) -> Result<usize> {
+    use crate::schema::mute_event;
+    diesel::insert_into(mute_event::table)
+        .values(event)
+        .execute(conn)
+        .map_err(AppError::from)
+}
+
+/// Filters for the audit log. `actor` is left out to list every user's
+/// events; dates are unix timestamps compared with `created_date`.
+#[derive(Debug, Default)]
+pub struct EventFilter<'a> {
+    pub actor: Option<&'a str>,
+    pub source: Option<&'a str>,
+    pub action: Option<&'a str>,
+    pub kind: Option<&'a str>,
+    pub target: Option<&'a str>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub before_id: Option<i32>,
+    pub limit: i64,
+}
+
+/// Returns audit log events, newest first.
+pub fn fetch_mute_events(conn: &mut SqliteConnection, filter: &EventFilter) -> Vec<MuteEvent> {
+    use crate::schema::mute_event;
+    let mut query = mute_event::table.into_boxed();
+    if let Some(actor) = filter.actor {
+        query = query.filter(mute_event::actor.eq(actor.to_string()));
+    }
+    if let Some(source) = filter.source {
+        query = query.filter(mute_event::source.eq(source.to_string()));
+    }
+    if let Some(action) = filter.action {
+        query = query.filter(mute_event::action.eq(action.to_string()));
+    }
+    if let Some(kind) = filter.kind {
+        query = query.filter(mute_event::kind.eq(kind.to_string()));
+    }
+    if let Some(target) = filter.target {
+        query = query.filter(mute_event::target.eq(target.to_string()));
+    }
+    if let Some(since) = filter.since {
+        query = query.filter(mute_event::created_date.ge(since));
+    }
+    if let Some(until) = filter.until {
+        query = query.filter(mute_event::created_date.le(until));
+    }
+    if let Some(before_id) = filter.before_id {
+        query = query.filter(mute_event::rowid.lt(before_id));
+    }
+    query
+        .order(mute_event::rowid.desc())
+        .limit(filter.limit)
+        .select(MuteEvent::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Saves a new mute group and returns it. Names are unique per user,
+/// ignoring case.
+pub fn create_mute_group(
+    conn: &mut SqliteConnection,
+    actor: &str,
+    name: &str,
+    created_date: &i64,
+) -> Result<MuteGroup> {
+    use crate::schema::mute_group;
+    conn.transaction::<MuteGroup, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(mute_group::table)
+            .values(&NewMuteGroup {
+                actor,
+                name,
+                created_date,
+            })
+            .execute(conn)?;
+        mute_group::table
+            .filter(mute_group::actor.eq(actor))
+            .order(mute_group::rowid.desc())
+            .select(MuteGroup::as_select())
+            .first(conn)
+    })
+    .map_err(AppError::from)
+}
+
+/// Returns the user's mute groups, oldest first.
+pub fn fetch_mute_groups(conn: &mut SqliteConnection, user_id: &str) -> Vec<MuteGroup> {
+    use crate::schema::mute_group;
+    mute_group::table
+        .filter(mute_group::actor.eq(user_id))
+        .order(mute_group::rowid.asc())
+        .select(MuteGroup::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+pub fn fetch_mute_group(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    group_id: i32,
+) -> Option<MuteGroup> {
+    use crate::schema::mute_group;
+    mute_group::table
+        .filter(mute_group::rowid.eq(group_id))
+        .filter(mute_group::actor.eq(user_id))
+        .select(MuteGroup::as_select())
+        .first(conn)
+        .optional()
+        .unwrap_or_default()
+}
+
+/// Returns the user's mute group with the given name, ignoring ASCII case
+/// like the `COLLATE NOCASE` unique index on names.
+pub fn fetch_mute_group_by_name(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    name: &str,
+) -> Option<MuteGroup> {
+    fetch_mute_groups(conn, user_id)
+        .into_iter()
+        .find(|g| g.name.eq_ignore_ascii_case(name))
+}
+
+/// Returns the members of a group in the order they were added.
+pub fn fetch_mute_group_members(
+    conn: &mut SqliteConnection,
+    group_id: i32,
+) -> Vec<MuteGroupMember> {
+    use crate::schema::mute_group_member;
+    mute_group_member::table
+        .filter(mute_group_member::group_id.eq(group_id))
+        .order(mute_group_member::rowid.asc())
+        .select(MuteGroupMember::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Adds members of one `kind` (`mute` or `word`) to a group. Members it
+/// already has are skipped; returns how many were added.
+pub fn add_mute_group_members(
+    conn: &mut SqliteConnection,
+    group_id: &i32,
+    kind: &str,
+    targets: &[String],
+) -> Result<usize> {
+    use crate::schema::mute_group_member;
+    let members: Vec<NewMuteGroupMember> = targets
+        .iter()
+        .map(|target| NewMuteGroupMember {
+            group_id,
+            kind,
+            target: target.as_str(),
+        })
+        .collect();
+    diesel::insert_or_ignore_into(mute_group_member::table)
+        .values(&members)
+        .execute(conn)
+        .map_err(AppError::from)
+}
+
+pub fn update_mute_group_expiration(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    group_id: &i32,
+    expiration_date: &i64,
+) -> Result<bool> {
+    use crate::schema::mute_group;
+    let res = diesel::update(mute_group::table)
+        .filter(mute_group::rowid.eq(group_id))
+        .filter(mute_group::actor.eq(user_id))
+        .set(mute_group::expiration_date.eq(Some(expiration_date)))
+        .execute(conn)?;
+    Ok(res > 0)
+}
+
+/// Deletes a saved group and its members. Rows of a running activation keep
+/// their `group_id` and still expire together.
+pub fn delete_mute_group(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    group_id: &i32,
+) -> Result<bool> {
+    use crate::schema::{mute_group, mute_group_member};
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let deleted = diesel::delete(mute_group::table)
+            .filter(mute_group::rowid.eq(group_id))
+            .filter(mute_group::actor.eq(user_id))
+            .execute(conn)?;
+        if deleted > 0 {
+            diesel::delete(mute_group_member::table)
+                .filter(mute_group_member::group_id.eq(group_id))
+                .execute(conn)?;
+        }
+        Ok(deleted)
+    })?;
+    Ok(res > 0)
+}
+
+/// Links the user's active timed mutes and timed mute words with the given
+/// ids to a group, so the resolver expires them together. Rows that already
+/// belong to a group keep it.
+pub fn assign_mute_group(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    group_id: &i32,
+    timed_mute_ids: Vec<i32>,
+    timed_mute_word_ids: Vec<i32>,
+) -> Result<usize> {
+    use crate::schema::{timed_mute, timed_mute_word};
+    conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let mutes = diesel::update(timed_mute::table)
+            .filter(timed_mute::rowid.eq_any(timed_mute_ids))
+            .filter(timed_mute::actor.eq(user_id))
+            .filter(timed_mute::status.eq(MuteStatus::Active))
+            .filter(timed_mute::group_id.is_null())
+            .set(timed_mute::group_id.eq(Some(group_id)))
+            .execute(conn)?;
+        let words = diesel::update(timed_mute_word::table)
+            .filter(timed_mute_word::rowid.eq_any(timed_mute_word_ids))
+            .filter(timed_mute_word::actor.eq(user_id))
+            .filter(timed_mute_word::status.eq(MuteStatus::Active))
+            .filter(timed_mute_word::group_id.is_null())
+            .set(timed_mute_word::group_id.eq(Some(group_id)))
+            .execute(conn)?;
+        Ok(mutes + words)
+    })
+    .map_err(AppError::from)
+}
 
-    Ok(res > 0)
+/// Counts the timed mutes and words of a group that are still in effect.
+pub fn count_unresolved_group_rows(conn: &mut SqliteConnection, group_id: i32) -> i64 {
+    use crate::schema::{timed_mute, timed_mute_word};
+    let mutes: i64 = timed_mute::table
+        .filter(timed_mute::group_id.eq(group_id))
+        .filter(timed_mute::status.eq_any(MuteStatus::UNRESOLVED))
+        .count()
+        .get_result(conn)
+        .unwrap_or_default();
+    let words: i64 = timed_mute_word::table
+        .filter(timed_mute_word::group_id.eq(group_id))
+        .filter(timed_mute_word::status.eq_any(MuteStatus::UNRESOLVED))
+        .count()
+        .get_result(conn)
+        .unwrap_or_default();
+    mutes + words
 }
 
-pub fn update_timed_mute_v1(
+pub fn create_recurring_mute(
     conn: &mut SqliteConnection,
-    _actor: &str,
-    timed_mute_id: &i32,
-    status: &i32,
-) -> Result<bool> {
-    use crate::schema::timed_mute;
+    rule: &NewRecurringMute,
+) -> Result<RecurringMute> {
+    use crate::schema::recurring_mute;
+    conn.transaction::<RecurringMute, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(recurring_mute::table)
+            .values(rule)
+            .execute(conn)?;
+        recurring_mute::table
+            .filter(recurring_mute::actor.eq(rule.actor))
+            .order(recurring_mute::rowid.desc())
+            .select(RecurringMute::as_select())
+            .first(conn)
+    })
+    .map_err(AppError::from)
+}
 
-    let res = diesel::update(timed_mute::table)
-        .filter(timed_mute::rowid.eq(timed_mute_id))
-        .filter(timed_mute::actor.eq(_actor))
-        .set(timed_mute::status.eq(status))
-        .execute(conn)?;
+/// Returns the user's recurring mutes, oldest first.
+pub fn fetch_recurring_mutes(conn: &mut SqliteConnection, user_id: &str) -> Vec<RecurringMute> {
+    use crate::schema::recurring_mute;
+    recurring_mute::table
+        .filter(recurring_mute::actor.eq(user_id))
+        .order(recurring_mute::rowid.asc())
+        .select(RecurringMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
 
-    Ok(res > 0)
+/// Returns every user's recurring mutes whose next occurrence has started.
+pub fn fetch_due_recurring_mutes(conn: &mut SqliteConnection, now: i64) -> Vec<RecurringMute> {
+    use crate::schema::recurring_mute;
+    recurring_mute::table
+        .filter(recurring_mute::next_run_date.le(now))
+        .order((recurring_mute::actor.asc(), recurring_mute::rowid.asc()))
+        .select(RecurringMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
 }
 
-pub fn update_timed_mute_list_v1(
+/// Moves a recurring mute from its occurrence at `last_run_date` to the one
+/// at `next_run_date`, recording the former as run and clearing the last
+/// error and any retry of the occurrence before. Returns false when the rule
+/// was moved already, so each occurrence is claimed by one run only.
+pub fn update_recurring_mute_run(
     conn: &mut SqliteConnection,
-    _actor: &str,
-    timed_mute_id_list: Vec<String>,
-    status: &i32,
+    rule_id: &i32,
+    next_run_date: &i64,
+    last_run_date: &i64,
 ) -> Result<bool> {
-    use crate::schema::timed_mute;
-
-    let res = diesel::update(timed_mute::table)
-        .filter(timed_mute::muted_actor.eq_any(timed_mute_id_list))
-        .filter(timed_mute::actor.eq(_actor))
-        .set(timed_mute::status.eq(status))
+    use crate::schema::recurring_mute;
+    let res = diesel::update(recurring_mute::table)
+        .filter(recurring_mute::rowid.eq(rule_id))
+        .filter(recurring_mute::next_run_date.eq(last_run_date))
+        .set((
+            recurring_mute::next_run_date.eq(next_run_date),
+            recurring_mute::last_run_date.eq(Some(last_run_date)),
+            recurring_mute::last_error.eq(None::<String>),
+            recurring_mute::attempt_count.eq(0),
+            recurring_mute::next_attempt_date.eq(None::<i64>),
+        ))
         .execute(conn)?;
-
     Ok(res > 0)
 }
 
-pub fn update_timed_mute_word_list_v1(
+/// Records why the occurrence at `last_run_date` could not be materialized,
+/// and when it is tried again. `next_attempt_date` is `None` once the
+/// occurrence is given up on.
+pub fn record_recurring_mute_error(
     conn: &mut SqliteConnection,
-    _actor: &str,
-    timed_mute_word_list: Vec<String>,
-    status: &i32,
+    rule_id: &i32,
+    last_run_date: &i64,
+    last_error: &str,
+    attempt_count: &i32,
+    next_attempt_date: Option<&i64>,
 ) -> Result<bool> {
-    use crate::schema::timed_mute_word;
-
-    let res = diesel::update(timed_mute_word::table)
-        .filter(timed_mute_word::muted_word.eq_any(timed_mute_word_list))
-        .filter(timed_mute_word::actor.eq(_actor))
-        .set(timed_mute_word::status.eq(status))
+    use crate::schema::recurring_mute;
+    let res = diesel::update(recurring_mute::table)
+        .filter(recurring_mute::rowid.eq(rule_id))
+        .filter(recurring_mute::last_run_date.eq(last_run_date))
+        .set((
+            recurring_mute::last_error.eq(Some(last_error)),
+            recurring_mute::attempt_count.eq(attempt_count),
+            recurring_mute::next_attempt_date.eq(next_attempt_date),
+        ))
         .execute(conn)?;
-
     Ok(res > 0)
 }
 
-pub fn fetch_timed_mutes(conn: &mut DBPooledConnection, user_id: &str) -> Vec<TimedMute> {
-    use crate::schema::timed_mute::actor;
-    use crate::schema::timed_mute::dsl::timed_mute;
-    use crate::schema::timed_mute::status;
-    timed_mute
-        .filter(status.eq(0))
-        .filter(actor.eq(user_id))
-        .select(TimedMute::as_select())
+/// Returns every user's recurring mutes whose last occurrence failed and is
+/// due to be tried again.
+pub fn fetch_recurring_mute_retries(conn: &mut SqliteConnection, now: i64) -> Vec<RecurringMute> {
+    use crate::schema::recurring_mute;
+    recurring_mute::table
+        .filter(recurring_mute::next_attempt_date.le(now))
+        .order((recurring_mute::actor.asc(), recurring_mute::rowid.asc()))
+        .select(RecurringMute::as_select())
         .load(conn)
         .unwrap_or_default()
 }
 
-pub fn fetch_timed_mute_words(conn: &mut DBPooledConnection, user_id: &str) -> Vec<TimedMuteWord> {
-    use crate::schema::timed_mute_word::actor;
-    use crate::schema::timed_mute_word::dsl::timed_mute_word;
-    use crate::schema::timed_mute_word::status;
-    timed_mute_word
-        .filter(status.eq(0))
-        .filter(actor.eq(user_id))
-        .select(TimedMuteWord::as_select())
-        .load(conn)
-        .unwrap_or_default()
+/// Takes the retry of the occurrence at `last_run_date` that was due at
+/// `next_attempt_date`. Returns false when another run took it already or
+/// the rule moved on to a newer occurrence.
+pub fn claim_recurring_mute_retry(
+    conn: &mut SqliteConnection,
+    rule_id: &i32,
+    last_run_date: &i64,
+    next_attempt_date: &i64,
+) -> Result<bool> {
+    use crate::schema::recurring_mute;
+    let res = diesel::update(recurring_mute::table)
+        .filter(recurring_mute::rowid.eq(rule_id))
+        .filter(recurring_mute::last_run_date.eq(last_run_date))
+        .filter(recurring_mute::next_attempt_date.eq(next_attempt_date))
+        .set(recurring_mute::next_attempt_date.eq(None::<i64>))
+        .execute(conn)?;
+    Ok(res > 0)
+}
+
+/// Moves a recurring mute whose schedule can't be computed to `retry_date`
+/// without running it, recording the error.
+pub fn postpone_recurring_mute(
+    conn: &mut SqliteConnection,
+    rule_id: &i32,
+    next_run_date: &i64,
+    retry_date: &i64,
+    last_error: &str,
+) -> Result<bool> {
+    use crate::schema::recurring_mute;
+    let res = diesel::update(recurring_mute::table)
+        .filter(recurring_mute::rowid.eq(rule_id))
+        .filter(recurring_mute::next_run_date.eq(next_run_date))
+        .set((
+            recurring_mute::next_run_date.eq(retry_date),
+            recurring_mute::last_error.eq(Some(last_error)),
+        ))
+        .execute(conn)?;
+    Ok(res > 0)
+}
+
+/// Deletes a recurring mute. Timed entries it already created expire as
+/// planned.
+pub fn delete_recurring_mute(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    rule_id: &i32,
+) -> Result<bool> {
+    use crate::schema::recurring_mute;
+    let res = diesel::delete(recurring_mute::table)
+        .filter(recurring_mute::rowid.eq(rule_id))
+        .filter(recurring_mute::actor.eq(user_id))
+        .execute(conn)?;
+    Ok(res > 0)
 }
 
 pub fn fetch_timed_mutes_v1(conn: &mut SqliteConnection) -> Vec<TimedMute> {
     use crate::schema::timed_mute::dsl::timed_mute;
     use crate::schema::timed_mute::status;
     timed_mute
-        .filter(status.eq(0))
+        .filter(status.eq_any(MuteStatus::UNRESOLVED))
         .select(TimedMute::as_select())
         .load(conn)
         .unwrap_or_default()
@@ -377,7 +2798,7 @@ pub fn fetch_timed_mute_words_v1(conn: &mut SqliteConnection) -> Vec<TimedMuteWo
     use crate::schema::timed_mute_word::dsl::timed_mute_word;
     use crate::schema::timed_mute_word::status;
     timed_mute_word
-        .filter(status.eq(0))
+        .filter(status.eq_any(MuteStatus::UNRESOLVED))
         .select(TimedMuteWord::as_select())
         .load(conn)
         .unwrap_or_default()
@@ -388,7 +2809,7 @@ pub fn fetch_timed_mutes_for_user(conn: &mut DBPooledConnection, _actor: &str) -
     use crate::schema::timed_mute::dsl::timed_mute;
     use crate::schema::timed_mute::status;
     timed_mute
-        .filter(status.eq(0))
+        .filter(status.eq(MuteStatus::Active))
         .filter(actor.eq(_actor))
         .select(TimedMute::as_select())
         .load(conn)
@@ -403,13 +2824,692 @@ pub fn fetch_timed_mute_words_for_user(
     use crate::schema::timed_mute_word::dsl::timed_mute_word;
     use crate::schema::timed_mute_word::status;
     timed_mute_word
-        .filter(status.eq(0))
+        .filter(status.eq(MuteStatus::Active))
         .filter(actor.eq(_actor))
         .select(TimedMuteWord::as_select())
         .load(conn)
         .unwrap_or_default()
 }
 
+/// Saves a new timed block and returns it.
+pub fn create_timed_block(
+    conn: &mut DBPooledConnection,
+    actor: &str,
+    blocked_actor: &str,
+    record_uri: &str,
+    created_date: &i64,
+    expiration_date: &i64,
+    status: &MuteStatus,
+) -> Result<TimedBlock> {
+    use crate::schema::timed_block;
+    let new_timed_block = NewTimedBlock {
+        actor,
+        blocked_actor,
+        record_uri,
+        created_date,
+        expiration_date,
+        status,
+    };
+
+    conn.transaction::<TimedBlock, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(timed_block::table)
+            .values(&new_timed_block)
+            .execute(conn)?;
+        timed_block::table
+            .filter(timed_block::actor.eq(actor))
+            .order(timed_block::rowid.desc())
+            .select(TimedBlock::as_select())
+            .first(conn)
+    })
+    .map_err(AppError::from)
+}
+
+pub fn fetch_timed_blocks(conn: &mut DBPooledConnection, user_id: &str) -> Vec<TimedBlock> {
+    use crate::schema::timed_block::actor;
+    use crate::schema::timed_block::dsl::timed_block;
+    use crate::schema::timed_block::status;
+    timed_block
+        .filter(status.eq(MuteStatus::Active))
+        .filter(actor.eq(user_id))
+        .select(TimedBlock::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+pub fn fetch_timed_blocks_v1(conn: &mut SqliteConnection) -> Vec<TimedBlock> {
+    use crate::schema::timed_block::dsl::timed_block;
+    use crate::schema::timed_block::status;
+    timed_block
+        .filter(status.eq_any(MuteStatus::UNRESOLVED))
+        .select(TimedBlock::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Saves a new timed thread mute and returns it.
+pub fn create_timed_thread_mute(
+    conn: &mut DBPooledConnection,
+    actor: &str,
+    thread_uri: &str,
+    created_date: &i64,
+    expiration_date: &i64,
+    status: &MuteStatus,
+) -> Result<TimedThreadMute> {
+    use crate::schema::timed_thread_mute;
+    let new_timed_thread_mute = NewTimedThreadMute {
+        actor,
+        thread_uri,
+        created_date,
+        expiration_date,
+        status,
+    };
+
+    conn.transaction::<TimedThreadMute, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(timed_thread_mute::table)
+            .values(&new_timed_thread_mute)
+            .execute(conn)?;
+        timed_thread_mute::table
+            .filter(timed_thread_mute::actor.eq(actor))
+            .order(timed_thread_mute::rowid.desc())
+            .select(TimedThreadMute::as_select())
+            .first(conn)
+    })
+    .map_err(AppError::from)
+}
+
+pub fn fetch_timed_thread_mutes(
+    conn: &mut DBPooledConnection,
+    user_id: &str,
+) -> Vec<TimedThreadMute> {
+    use crate::schema::timed_thread_mute::actor;
+    use crate::schema::timed_thread_mute::dsl::timed_thread_mute;
+    use crate::schema::timed_thread_mute::status;
+    timed_thread_mute
+        .filter(status.eq(MuteStatus::Active))
+        .filter(actor.eq(user_id))
+        .select(TimedThreadMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+pub fn fetch_timed_thread_mutes_v1(conn: &mut SqliteConnection) -> Vec<TimedThreadMute> {
+    use crate::schema::timed_thread_mute::dsl::timed_thread_mute;
+    use crate::schema::timed_thread_mute::status;
+    timed_thread_mute
+        .filter(status.eq_any(MuteStatus::UNRESOLVED))
+        .select(TimedThreadMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Saves a new timed list mute and returns it.
+#[allow(clippy::too_many_arguments)]
+pub fn create_timed_list_mute(
+    conn: &mut DBPooledConnection,
+    actor: &str,
+    list_uri: &str,
+    mode: &str,
+    record_uri: Option<&str>,
+    created_date: &i64,
+    expiration_date: &i64,
+    status: &MuteStatus,
+) -> Result<TimedListMute> {
+    use crate::schema::timed_list_mute;
+    let new_timed_list_mute = NewTimedListMute {
+        actor,
+        list_uri,
+        mode,
+        record_uri,
+        created_date,
+        expiration_date,
+        status,
+    };
+
+    conn.transaction::<TimedListMute, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(timed_list_mute::table)
+            .values(&new_timed_list_mute)
+            .execute(conn)?;
+        timed_list_mute::table
+            .filter(timed_list_mute::actor.eq(actor))
+            .order(timed_list_mute::rowid.desc())
+            .select(TimedListMute::as_select())
+            .first(conn)
+    })
+    .map_err(AppError::from)
+}
+
+pub fn fetch_timed_list_mutes(conn: &mut DBPooledConnection, user_id: &str) -> Vec<TimedListMute> {
+    use crate::schema::timed_list_mute::actor;
+    use crate::schema::timed_list_mute::dsl::timed_list_mute;
+    use crate::schema::timed_list_mute::status;
+    timed_list_mute
+        .filter(status.eq(MuteStatus::Active))
+        .filter(actor.eq(user_id))
+        .select(TimedListMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+pub fn fetch_timed_list_mutes_v1(conn: &mut SqliteConnection) -> Vec<TimedListMute> {
+    use crate::schema::timed_list_mute::dsl::timed_list_mute;
+    use crate::schema::timed_list_mute::status;
+    timed_list_mute
+        .filter(status.eq_any(MuteStatus::UNRESOLVED))
+        .select(TimedListMute::as_select())
+        .load(conn)
+        .unwrap_or_default()
+}
+
+/// Records a failed attempt at resolving an expired timed mute claimed by the resolver.
+/// The row goes back to active to be retried, or to failed once it has failed
+/// `max_attempts` times.
+pub fn record_timed_mute_failure_v1(
+    conn: &mut SqliteConnection,
+    timed_mute_id: &i32,
+    error: &str,
+    next_attempt_time: &i64,
+    max_attempts: &i32,
+) -> Result<bool> {
+    use crate::schema::timed_mute;
+
+    let current = timed_mute::table
+        .filter(timed_mute::rowid.eq(timed_mute_id))
+        .select(timed_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Expiring, MuteStatus::Active)?;
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let res = diesel::update(timed_mute::table)
+            .filter(timed_mute::rowid.eq(timed_mute_id))
+            .filter(timed_mute::status.eq(MuteStatus::Expiring))
+            .set((
+                timed_mute::status.eq(MuteStatus::Active),
+                timed_mute::attempt_count.eq(timed_mute::attempt_count + 1),
+                timed_mute::last_error.eq(Some(error)),
+                timed_mute::next_attempt_date.eq(Some(next_attempt_time)),
+            ))
+            .execute(conn)?;
+        diesel::update(timed_mute::table)
+            .filter(timed_mute::rowid.eq(timed_mute_id))
+            .filter(timed_mute::status.eq(MuteStatus::Active))
+            .filter(timed_mute::attempt_count.ge(max_attempts))
+            .set((
+                timed_mute::status.eq(MuteStatus::Failed),
+                timed_mute::next_attempt_date.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(res)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Returns timed mutes that could not be resolved, for one user or for everyone.
+pub fn fetch_failed_timed_mutes(
+    conn: &mut SqliteConnection,
+    user_id: Option<&str>,
+) -> Vec<TimedMute> {
+    use crate::schema::timed_mute;
+    let mut query = timed_mute::table
+        .filter(timed_mute::status.eq(MuteStatus::Failed))
+        .select(TimedMute::as_select())
+        .into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(timed_mute::actor.eq(user_id));
+    }
+    query.load(conn).unwrap_or_default()
+}
+
+/// Records a failed attempt at resolving an expired timed mute word claimed by the resolver.
+/// The row goes back to active to be retried, or to failed once it has failed
+/// `max_attempts` times.
+pub fn record_timed_mute_word_failure_v1(
+    conn: &mut SqliteConnection,
+    timed_mute_word_id: &i32,
+    error: &str,
+    next_attempt_time: &i64,
+    max_attempts: &i32,
+) -> Result<bool> {
+    use crate::schema::timed_mute_word;
+
+    let current = timed_mute_word::table
+        .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+        .select(timed_mute_word::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Expiring, MuteStatus::Active)?;
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let res = diesel::update(timed_mute_word::table)
+            .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+            .filter(timed_mute_word::status.eq(MuteStatus::Expiring))
+            .set((
+                timed_mute_word::status.eq(MuteStatus::Active),
+                timed_mute_word::attempt_count.eq(timed_mute_word::attempt_count + 1),
+                timed_mute_word::last_error.eq(Some(error)),
+                timed_mute_word::next_attempt_date.eq(Some(next_attempt_time)),
+            ))
+            .execute(conn)?;
+        diesel::update(timed_mute_word::table)
+            .filter(timed_mute_word::rowid.eq(timed_mute_word_id))
+            .filter(timed_mute_word::status.eq(MuteStatus::Active))
+            .filter(timed_mute_word::attempt_count.ge(max_attempts))
+            .set((
+                timed_mute_word::status.eq(MuteStatus::Failed),
+                timed_mute_word::next_attempt_date.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(res)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Returns timed mute words that could not be resolved, for one user or for everyone.
+pub fn fetch_failed_timed_mute_words(
+    conn: &mut SqliteConnection,
+    user_id: Option<&str>,
+) -> Vec<TimedMuteWord> {
+    use crate::schema::timed_mute_word;
+    let mut query = timed_mute_word::table
+        .filter(timed_mute_word::status.eq(MuteStatus::Failed))
+        .select(TimedMuteWord::as_select())
+        .into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(timed_mute_word::actor.eq(user_id));
+    }
+    query.load(conn).unwrap_or_default()
+}
+
+/// Returns the user's timed block with the given id, whatever its status.
+pub fn fetch_timed_block(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    timed_block_id: i32,
+) -> Option<TimedBlock> {
+    use crate::schema::timed_block;
+    timed_block::table
+        .filter(timed_block::rowid.eq(timed_block_id))
+        .filter(timed_block::actor.eq(user_id))
+        .select(TimedBlock::as_select())
+        .first(conn)
+        .optional()
+        .unwrap_or_default()
+}
+
+pub fn update_timed_block_v1(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_block_id: &i32,
+    status: &MuteStatus,
+) -> Result<bool> {
+    use crate::schema::timed_block;
+
+    let current = timed_block::table
+        .filter(timed_block::rowid.eq(timed_block_id))
+        .filter(timed_block::actor.eq(_actor))
+        .select(timed_block::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition(*status)?;
+    let res = diesel::update(timed_block::table)
+        .filter(timed_block::rowid.eq(timed_block_id))
+        .filter(timed_block::actor.eq(_actor))
+        .filter(timed_block::status.eq(current))
+        .set(timed_block::status.eq(status))
+        .execute(conn)?;
+
+    Ok(res > 0)
+}
+
+/// Records a failed attempt at resolving an expired timed block claimed by the resolver.
+/// The row goes back to active to be retried, or to failed once it has failed
+/// `max_attempts` times.
+pub fn record_timed_block_failure_v1(
+    conn: &mut SqliteConnection,
+    timed_block_id: &i32,
+    error: &str,
+    next_attempt_time: &i64,
+    max_attempts: &i32,
+) -> Result<bool> {
+    use crate::schema::timed_block;
+
+    let current = timed_block::table
+        .filter(timed_block::rowid.eq(timed_block_id))
+        .select(timed_block::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Expiring, MuteStatus::Active)?;
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let res = diesel::update(timed_block::table)
+            .filter(timed_block::rowid.eq(timed_block_id))
+            .filter(timed_block::status.eq(MuteStatus::Expiring))
+            .set((
+                timed_block::status.eq(MuteStatus::Active),
+                timed_block::attempt_count.eq(timed_block::attempt_count + 1),
+                timed_block::last_error.eq(Some(error)),
+                timed_block::next_attempt_date.eq(Some(next_attempt_time)),
+            ))
+            .execute(conn)?;
+        diesel::update(timed_block::table)
+            .filter(timed_block::rowid.eq(timed_block_id))
+            .filter(timed_block::status.eq(MuteStatus::Active))
+            .filter(timed_block::attempt_count.ge(max_attempts))
+            .set((
+                timed_block::status.eq(MuteStatus::Failed),
+                timed_block::next_attempt_date.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(res)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Returns timed blocks that could not be resolved, for one user or for everyone.
+pub fn fetch_failed_timed_blocks(
+    conn: &mut SqliteConnection,
+    user_id: Option<&str>,
+) -> Vec<TimedBlock> {
+    use crate::schema::timed_block;
+    let mut query = timed_block::table
+        .filter(timed_block::status.eq(MuteStatus::Failed))
+        .select(TimedBlock::as_select())
+        .into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(timed_block::actor.eq(user_id));
+    }
+    query.load(conn).unwrap_or_default()
+}
+
+/// Returns the user's timed thread mute with the given id, whatever its status.
+pub fn fetch_timed_thread_mute(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    timed_thread_mute_id: i32,
+) -> Option<TimedThreadMute> {
+    use crate::schema::timed_thread_mute;
+    timed_thread_mute::table
+        .filter(timed_thread_mute::rowid.eq(timed_thread_mute_id))
+        .filter(timed_thread_mute::actor.eq(user_id))
+        .select(TimedThreadMute::as_select())
+        .first(conn)
+        .optional()
+        .unwrap_or_default()
+}
+
+pub fn update_timed_thread_mute_v1(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_thread_mute_id: &i32,
+    status: &MuteStatus,
+) -> Result<bool> {
+    use crate::schema::timed_thread_mute;
+
+    let current = timed_thread_mute::table
+        .filter(timed_thread_mute::rowid.eq(timed_thread_mute_id))
+        .filter(timed_thread_mute::actor.eq(_actor))
+        .select(timed_thread_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition(*status)?;
+    let res = diesel::update(timed_thread_mute::table)
+        .filter(timed_thread_mute::rowid.eq(timed_thread_mute_id))
+        .filter(timed_thread_mute::actor.eq(_actor))
+        .filter(timed_thread_mute::status.eq(current))
+        .set(timed_thread_mute::status.eq(status))
+        .execute(conn)?;
+
+    Ok(res > 0)
+}
+
+/// Records a failed attempt at resolving an expired timed thread mute claimed by the resolver.
+/// The row goes back to active to be retried, or to failed once it has failed
+/// `max_attempts` times.
+pub fn record_timed_thread_mute_failure_v1(
+    conn: &mut SqliteConnection,
+    timed_thread_mute_id: &i32,
+    error: &str,
+    next_attempt_time: &i64,
+    max_attempts: &i32,
+) -> Result<bool> {
+    use crate::schema::timed_thread_mute;
+
+    let current = timed_thread_mute::table
+        .filter(timed_thread_mute::rowid.eq(timed_thread_mute_id))
+        .select(timed_thread_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Expiring, MuteStatus::Active)?;
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let res = diesel::update(timed_thread_mute::table)
+            .filter(timed_thread_mute::rowid.eq(timed_thread_mute_id))
+            .filter(timed_thread_mute::status.eq(MuteStatus::Expiring))
+            .set((
+                timed_thread_mute::status.eq(MuteStatus::Active),
+                timed_thread_mute::attempt_count.eq(timed_thread_mute::attempt_count + 1),
+                timed_thread_mute::last_error.eq(Some(error)),
+                timed_thread_mute::next_attempt_date.eq(Some(next_attempt_time)),
+            ))
+            .execute(conn)?;
+        diesel::update(timed_thread_mute::table)
+            .filter(timed_thread_mute::rowid.eq(timed_thread_mute_id))
+            .filter(timed_thread_mute::status.eq(MuteStatus::Active))
+            .filter(timed_thread_mute::attempt_count.ge(max_attempts))
+            .set((
+                timed_thread_mute::status.eq(MuteStatus::Failed),
+                timed_thread_mute::next_attempt_date.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(res)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Returns timed thread mutes that could not be resolved, for one user or for everyone.
+pub fn fetch_failed_timed_thread_mutes(
+    conn: &mut SqliteConnection,
+    user_id: Option<&str>,
+) -> Vec<TimedThreadMute> {
+    use crate::schema::timed_thread_mute;
+    let mut query = timed_thread_mute::table
+        .filter(timed_thread_mute::status.eq(MuteStatus::Failed))
+        .select(TimedThreadMute::as_select())
+        .into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(timed_thread_mute::actor.eq(user_id));
+    }
+    query.load(conn).unwrap_or_default()
+}
+
+/// Returns the user's timed list mute with the given id, whatever its status.
+pub fn fetch_timed_list_mute(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    timed_list_mute_id: i32,
+) -> Option<TimedListMute> {
+    use crate::schema::timed_list_mute;
+    timed_list_mute::table
+        .filter(timed_list_mute::rowid.eq(timed_list_mute_id))
+        .filter(timed_list_mute::actor.eq(user_id))
+        .select(TimedListMute::as_select())
+        .first(conn)
+        .optional()
+        .unwrap_or_default()
+}
+
+pub fn update_timed_list_mute_v1(
+    conn: &mut SqliteConnection,
+    _actor: &str,
+    timed_list_mute_id: &i32,
+    status: &MuteStatus,
+) -> Result<bool> {
+    use crate::schema::timed_list_mute;
+
+    let current = timed_list_mute::table
+        .filter(timed_list_mute::rowid.eq(timed_list_mute_id))
+        .filter(timed_list_mute::actor.eq(_actor))
+        .select(timed_list_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition(*status)?;
+    let res = diesel::update(timed_list_mute::table)
+        .filter(timed_list_mute::rowid.eq(timed_list_mute_id))
+        .filter(timed_list_mute::actor.eq(_actor))
+        .filter(timed_list_mute::status.eq(current))
+        .set(timed_list_mute::status.eq(status))
+        .execute(conn)?;
+
+    Ok(res > 0)
+}
+
+/// Records a failed attempt at resolving an expired timed list mute claimed by the resolver.
+/// The row goes back to active to be retried, or to failed once it has failed
+/// `max_attempts` times.
+pub fn record_timed_list_mute_failure_v1(
+    conn: &mut SqliteConnection,
+    timed_list_mute_id: &i32,
+    error: &str,
+    next_attempt_time: &i64,
+    max_attempts: &i32,
+) -> Result<bool> {
+    use crate::schema::timed_list_mute;
+
+    let current = timed_list_mute::table
+        .filter(timed_list_mute::rowid.eq(timed_list_mute_id))
+        .select(timed_list_mute::status)
+        .first::<MuteStatus>(conn)
+        .optional()?;
+    let Some(current) = current else {
+        return Ok(false);
+    };
+    current.transition_from(MuteStatus::Expiring, MuteStatus::Active)?;
+    let res = conn.transaction::<usize, diesel::result::Error, _>(|conn| {
+        let res = diesel::update(timed_list_mute::table)
+            .filter(timed_list_mute::rowid.eq(timed_list_mute_id))
+            .filter(timed_list_mute::status.eq(MuteStatus::Expiring))
+            .set((
+                timed_list_mute::status.eq(MuteStatus::Active),
+                timed_list_mute::attempt_count.eq(timed_list_mute::attempt_count + 1),
+                timed_list_mute::last_error.eq(Some(error)),
+                timed_list_mute::next_attempt_date.eq(Some(next_attempt_time)),
+            ))
+            .execute(conn)?;
+        diesel::update(timed_list_mute::table)
+            .filter(timed_list_mute::rowid.eq(timed_list_mute_id))
+            .filter(timed_list_mute::status.eq(MuteStatus::Active))
+            .filter(timed_list_mute::attempt_count.ge(max_attempts))
+            .set((
+                timed_list_mute::status.eq(MuteStatus::Failed),
+                timed_list_mute::next_attempt_date.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+        Ok(res)
+    })?;
+
+    Ok(res > 0)
+}
+
+/// Returns timed list mutes that could not be resolved, for one user or for everyone.
+pub fn fetch_failed_timed_list_mutes(
+    conn: &mut SqliteConnection,
+    user_id: Option<&str>,
+) -> Vec<TimedListMute> {
+    use crate::schema::timed_list_mute;
+    let mut query = timed_list_mute::table
+        .filter(timed_list_mute::status.eq(MuteStatus::Failed))
+        .select(TimedListMute::as_select())
+        .into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(timed_list_mute::actor.eq(user_id));
+    }
+    query.load(conn).unwrap_or_default()
+}
+
+/// Returns the date every active row across all timed tables is next due.
+pub fn fetch_active_expiration_dates(conn: &mut SqliteConnection) -> Vec<i64> {
+    use crate::schema::{
+        timed_block, timed_list_mute, timed_mute, timed_mute_word, timed_thread_mute,
+    };
+
+    let mut dates: Vec<(i64, Option<i64>)> = Vec::new();
+    dates.extend(
+        timed_mute::table
+            .filter(timed_mute::status.eq_any(MuteStatus::UNRESOLVED))
+            .select((timed_mute::expiration_date, timed_mute::next_attempt_date))
+            .load::<(i64, Option<i64>)>(conn)
+            .unwrap_or_default(),
+    );
+    dates.extend(
+        timed_mute_word::table
+            .filter(timed_mute_word::status.eq_any(MuteStatus::UNRESOLVED))
+            .select((
+                timed_mute_word::expiration_date,
+                timed_mute_word::next_attempt_date,
+            ))
+            .load::<(i64, Option<i64>)>(conn)
+            .unwrap_or_default(),
+    );
+    dates.extend(
+        timed_block::table
+            .filter(timed_block::status.eq_any(MuteStatus::UNRESOLVED))
+            .select((timed_block::expiration_date, timed_block::next_attempt_date))
+            .load::<(i64, Option<i64>)>(conn)
+            .unwrap_or_default(),
+    );
+    dates.extend(
+        timed_thread_mute::table
+            .filter(timed_thread_mute::status.eq_any(MuteStatus::UNRESOLVED))
+            .select((
+                timed_thread_mute::expiration_date,
+                timed_thread_mute::next_attempt_date,
+            ))
+            .load::<(i64, Option<i64>)>(conn)
+            .unwrap_or_default(),
+    );
+    dates.extend(
+        timed_list_mute::table
+            .filter(timed_list_mute::status.eq_any(MuteStatus::UNRESOLVED))
+            .select((
+                timed_list_mute::expiration_date,
+                timed_list_mute::next_attempt_date,
+            ))
+            .load::<(i64, Option<i64>)>(conn)
+            .unwrap_or_default(),
+    );
+    // Rows waiting on a retry are due again at their next attempt.
+    let mut dates: Vec<i64> = dates
+        .into_iter()
+        .map(|(expiration_date, next_attempt_date)| {
+            next_attempt_date.map_or(expiration_date, |next| next.max(expiration_date))
+        })
+        .collect();
+    dates.sort_unstable();
+    dates.dedup();
+    dates
+}
+
 pub fn fetch_profile(conn: &mut DBPooledConnection, _did: &str) -> Vec<Profile> {
     use crate::schema::profile::did;
     use crate::schema::profile::dsl::profile;
@@ -437,10 +3537,11 @@ pub fn create_profile(
     password: &str,
 ) -> Result<usize> {
     use crate::schema::profile;
+    let encrypted_password = crypto::encrypt(password)?;
     let new_profile = NewProfile {
         did,
         handle,
-        password,
+        password: encrypted_password.as_str(),
         status: &0,
     };
 
@@ -457,10 +3558,116 @@ pub fn update_profile(
     password: &str,
 ) -> Result<usize> {
     use crate::schema::profile;
+    let encrypted_password = crypto::encrypt(password)?;
 
     diesel::update(profile::table)
         .filter(profile::did.eq(did))
-        .set(profile::password.eq(password))
+        .set(profile::password.eq(encrypted_password))
+        .execute(conn)
+        .map_err(AppError::from)
+}
+
+/// Re-encrypts every stored password and session token under the current
+/// master key. Rows that are still plain text are encrypted, rows sealed with
+/// the previous key are re-wrapped. Returns the number of rows that changed.
+pub fn reencrypt_profiles(conn: &mut SqliteConnection) -> Result<usize> {
+    use crate::schema::{profile, profile_session};
+
+    conn.transaction(|conn| {
+        let rows = profile::table
+            .select((profile::rowid, profile::password))
+            .load::<(i32, String)>(conn)?;
+        let mut updated = 0;
+        for (id, password) in rows {
+            if password.is_empty() || !crypto::needs_reencryption(password.as_str())? {
+                continue;
+            }
+            updated += diesel::update(profile::table)
+                .filter(profile::rowid.eq(id))
+                .set(profile::password.eq(crypto::reencrypt(password.as_str())?))
+                .execute(conn)?;
+        }
+
+        let sessions = profile_session::table
+            .select((
+                profile_session::rowid,
+                profile_session::access_jwt,
+                profile_session::refresh_jwt,
+            ))
+            .load::<(i32, String, String)>(conn)?;
+        for (id, access_jwt, refresh_jwt) in sessions {
+            if !crypto::needs_reencryption(access_jwt.as_str())?
+                && !crypto::needs_reencryption(refresh_jwt.as_str())?
+            {
+                continue;
+            }
+            updated += diesel::update(profile_session::table)
+                .filter(profile_session::rowid.eq(id))
+                .set((
+                    profile_session::access_jwt.eq(crypto::reencrypt(access_jwt.as_str())?),
+                    profile_session::refresh_jwt.eq(crypto::reencrypt(refresh_jwt.as_str())?),
+                ))
+                .execute(conn)?;
+        }
+        Ok(updated)
+    })
+}
+
+/// Returns the stored Bluesky session for a user with its tokens decrypted.
+pub fn fetch_profile_session(
+    conn: &mut SqliteConnection,
+    _did: &str,
+) -> Result<Option<ProfileSession>> {
+    use crate::schema::profile_session::did;
+    use crate::schema::profile_session::dsl::profile_session;
+    let session = profile_session
+        .filter(did.eq(_did))
+        .select(ProfileSession::as_select())
+        .first::<ProfileSession>(conn)
+        .optional()?;
+    match session {
+        Some(mut session) => {
+            session.access_jwt = crypto::decrypt(session.access_jwt.as_str())?;
+            session.refresh_jwt = crypto::decrypt(session.refresh_jwt.as_str())?;
+            Ok(Some(session))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn save_profile_session(
+    conn: &mut SqliteConnection,
+    did: &str,
+    access_jwt: &str,
+    refresh_jwt: &str,
+    active: &bool,
+    status: Option<&str>,
+) -> Result<usize> {
+    use crate::schema::profile_session;
+    let encrypted_access_jwt = crypto::encrypt(access_jwt)?;
+    let encrypted_refresh_jwt = crypto::encrypt(refresh_jwt)?;
+    let new_session = NewProfileSession {
+        access_jwt: encrypted_access_jwt.as_str(),
+        refresh_jwt: encrypted_refresh_jwt.as_str(),
+        did,
+        active,
+        status,
+    };
+
+    diesel::insert_into(profile_session::table)
+        .values(&new_session)
+        .on_conflict(profile_session::did)
+        .do_update()
+        .set(&new_session)
+        .execute(conn)
+        .map_err(AppError::from)
+}
+
+pub fn delete_profile_session(conn: &mut SqliteConnection, did: &str) -> Result<usize> {
+    use crate::schema::profile_session;
+
+    diesel::delete(profile_session::table)
+        .filter(profile_session::did.eq(did))
         .execute(conn)
         .map_err(AppError::from)
 }
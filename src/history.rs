@@ -0,0 +1,202 @@
+use axum::extract::{Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::AppError;
+use crate::expiration::parse_timestamp;
+use crate::helper::{fetch_timed_mute_history, fetch_timed_mute_word_history, HistoryFilter};
+use crate::models::{TimedMute, TimedMuteWord};
+use crate::status::MuteStatus;
+use crate::tmute::get_user_id;
+use crate::{DBPool, APPLICATION_JSON};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 100;
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    /// Only rows with this status, e.g. `1` for expired or `9` for deleted.
+    #[param(value_type = Option<i32>)]
+    pub status: Option<MuteStatus>,
+    /// RFC3339 timestamp; only rows created at or after it.
+    pub since: Option<String>,
+    /// RFC3339 timestamp; only rows created at or before it.
+    pub until: Option<String>,
+    /// DID of the muted account, or part of the muted word.
+    pub target: Option<String>,
+    /// `cursor` returned with the previous page.
+    pub cursor: Option<String>,
+    /// Page size, up to 100. Defaults to 50.
+    pub limit: Option<i64>,
+}
+
+impl HistoryQuery {
+    fn filter(&self) -> Result<HistoryFilter<'_>> {
+        let (before_id, limit) = page(self.cursor.as_deref(), self.limit)?;
+        Ok(HistoryFilter {
+            status: self.status,
+            since: self.since.as_deref().map(parse_timestamp).transpose()?,
+            until: self.until.as_deref().map(parse_timestamp).transpose()?,
+            target: self.target.as_deref(),
+            before_id,
+            limit,
+        })
+    }
+}
+
+/// Parses the `cursor` and `limit` query parameters into the row id to start
+/// before and the number of rows to fetch. One extra row is fetched to tell
+/// whether there is a next page.
+pub(crate) fn page(cursor: Option<&str>, limit: Option<i64>) -> Result<(Option<i32>, i64)> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(AppError::BadRequest(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    let before_id = cursor
+        .map(|cursor| {
+            cursor
+                .parse::<i32>()
+                .map_err(|_| AppError::BadRequest(format!("Invalid cursor: {}", cursor)))
+        })
+        .transpose()?;
+    Ok((before_id, limit + 1))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct TimedMuteHistory {
+    pub mutes: Vec<TimedMute>,
+    /// Pass as `cursor` to get the next page; absent on the last page.
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct TimedMuteWordHistory {
+    pub words: Vec<TimedMuteWord>,
+    /// Pass as `cursor` to get the next page; absent on the last page.
+    pub cursor: Option<String>,
+}
+
+/// Drops the extra row fetched past the page size and returns the cursor
+/// for the next page, if there is one. `limit` is the number of rows fetched.
+pub(crate) fn paginate<T>(rows: &mut Vec<T>, limit: i64, id: impl Fn(&T) -> i32) -> Option<String> {
+    let page_size = (limit - 1) as usize;
+    if rows.len() <= page_size {
+        return None;
+    }
+    rows.truncate(page_size);
+    rows.last().map(|row| id(row).to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/timed-mutes/history",
+    params(
+        ("bskytools" = String, Cookie,),
+        HistoryQuery
+    ),
+    responses(
+        (status=200, description="Timed mutes of any status, newest first", body = TimedMuteHistory),
+        (status=400, description="Invalid filter or cursor"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_history(
+    session: Session,
+    State(pool): State<DBPool>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let filter = query.filter()?;
+    let mut mutes = fetch_timed_mute_history(&mut conn, user_id.as_str(), &filter);
+    let cursor = paginate(&mut mutes, filter.limit, |m| m.id);
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(TimedMuteHistory { mutes, cursor }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/timed-mute-words/history",
+    params(
+        ("bskytools" = String, Cookie,),
+        HistoryQuery
+    ),
+    responses(
+        (status=200, description="Timed mute words of any status, newest first", body = TimedMuteWordHistory),
+        (status=400, description="Invalid filter or cursor"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_word_history(
+    session: Session,
+    State(pool): State<DBPool>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let filter = query.filter()?;
+    let mut words = fetch_timed_mute_word_history(&mut conn, user_id.as_str(), &filter);
+    let cursor = paginate(&mut words, filter.limit, |w| w.id);
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(TimedMuteWordHistory { words, cursor }),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_query_filter() {
+        let query = HistoryQuery {
+            since: Some("2026-09-01T00:00:00Z".to_string()),
+            cursor: Some("42".to_string()),
+            ..Default::default()
+        };
+        let filter = query.filter().unwrap();
+        assert_eq!(filter.since, Some(1788220800));
+        assert_eq!(filter.before_id, Some(42));
+        assert_eq!(filter.limit, DEFAULT_LIMIT + 1);
+
+        let bad_cursor = HistoryQuery {
+            cursor: Some("abc".to_string()),
+            ..Default::default()
+        };
+        assert!(bad_cursor.filter().is_err());
+        let bad_limit = HistoryQuery {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert!(bad_limit.filter().is_err());
+    }
+
+    #[test]
+    fn test_paginate() {
+        let mut rows = vec![9, 8, 7];
+        assert_eq!(paginate(&mut rows, 3, |r| *r), Some("8".to_string()));
+        assert_eq!(rows, vec![9, 8]);
+
+        let mut rows = vec![9, 8];
+        assert_eq!(paginate(&mut rows, 3, |r| *r), None);
+        assert_eq!(rows, vec![9, 8]);
+    }
+}
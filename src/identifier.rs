@@ -0,0 +1,167 @@
+use bsky_sdk::api::types::string::AtIdentifier;
+
+use crate::error::AppError;
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Hosts whose `/profile/<actor>` links are accepted as actors.
+const PROFILE_HOSTS: [&str; 2] = ["bsky.app", "www.bsky.app"];
+
+/// Collection of the records `bsky.app/profile/<actor>/post/<rkey>` links to.
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+/// Returns the path after `/profile/` of a `bsky.app` link, without query or
+/// fragment.
+fn profile_path(input: &str) -> Option<&str> {
+    let without_scheme = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+        .unwrap_or(input);
+    PROFILE_HOSTS.iter().find_map(|host| {
+        without_scheme
+            .strip_prefix(host)
+            .and_then(|path| path.strip_prefix("/profile/"))
+            .and_then(|path| path.split(['?', '#']).next())
+    })
+}
+
+/// Returns the actor part of a `bsky.app/profile/<actor>` link or an
+/// `at://<actor>` URI, or the input itself.
+fn strip_link(input: &str) -> &str {
+    if let Some(uri) = input.strip_prefix("at://") {
+        return uri.split('/').next().unwrap_or_default();
+    }
+    match profile_path(input) {
+        Some(path) => path.split('/').next().unwrap_or_default(),
+        None => input,
+    }
+}
+
+/// Parses an account as users paste it: a DID (`did:plc:...`, `did:web:...`),
+/// a handle with or without a leading `@`, a `https://bsky.app/profile/...`
+/// link, or an `at://` URI. Handles are lowercased.
+pub fn parse_actor(input: &str) -> Result<AtIdentifier> {
+    let actor = strip_link(input.trim()).trim_start_matches('@');
+    let parsed = if actor.starts_with("did:") {
+        actor.parse().map(AtIdentifier::Did)
+    } else {
+        actor.to_lowercase().parse().map(AtIdentifier::Handle)
+    };
+    parsed
+        .map_err(|_| AppError::BadRequest(format!("Not a handle, DID or profile link: {}", input)))
+}
+
+fn is_record_key(rkey: &str) -> bool {
+    !rkey.is_empty()
+        && rkey
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._:~-".contains(c))
+}
+
+/// Parses a post as users paste it: an `at://<actor>/app.bsky.feed.post/<rkey>`
+/// URI or a `https://bsky.app/profile/<actor>/post/<rkey>` link. Returns the
+/// author and the record key.
+pub fn parse_post(input: &str) -> Result<(AtIdentifier, String)> {
+    let input = input.trim();
+    let invalid = || AppError::BadRequest(format!("Not a post URI or link: {}", input));
+    let (path, collection) = match input.strip_prefix("at://") {
+        Some(uri) => (uri, POST_COLLECTION),
+        None => (profile_path(input).ok_or_else(invalid)?, "post"),
+    };
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    let [actor, segment, rkey] = segments[..] else {
+        return Err(invalid());
+    };
+    if segment != collection || !is_record_key(rkey) {
+        return Err(invalid());
+    }
+    let actor = parse_actor(actor).map_err(|_| invalid())?;
+    Ok((actor, rkey.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(input: &str) -> String {
+        match parse_actor(input).unwrap() {
+            AtIdentifier::Did(did) => did.to_string(),
+            AtIdentifier::Handle(handle) => handle.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_actor() {
+        assert_eq!(parsed("alice.bsky.social"), "alice.bsky.social");
+        assert_eq!(parsed(" @Alice.bsky.social "), "alice.bsky.social");
+        assert_eq!(
+            parsed("did:plc:z72i7hdynmk6r22z27h6tvur"),
+            "did:plc:z72i7hdynmk6r22z27h6tvur"
+        );
+        assert_eq!(parsed("did:web:example.com"), "did:web:example.com");
+        assert!(matches!(
+            parse_actor("did:web:example.com"),
+            Ok(AtIdentifier::Did(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_actor_links() {
+        assert_eq!(
+            parsed("https://bsky.app/profile/alice.bsky.social"),
+            "alice.bsky.social"
+        );
+        assert_eq!(
+            parsed("bsky.app/profile/did:plc:z72i7hdynmk6r22z27h6tvur/post/3k2"),
+            "did:plc:z72i7hdynmk6r22z27h6tvur"
+        );
+        assert_eq!(
+            parsed("https://www.bsky.app/profile/Bob.example.com?ref=share"),
+            "bob.example.com"
+        );
+        assert_eq!(
+            parsed("at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/3k2"),
+            "did:plc:z72i7hdynmk6r22z27h6tvur"
+        );
+    }
+
+    #[test]
+    fn test_parse_actor_rejects() {
+        for input in [
+            "",
+            "not a handle",
+            "did:",
+            "https://example.com/profile/alice.bsky.social",
+            "https://bsky.app/profile/",
+        ] {
+            assert!(parse_actor(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_post() {
+        let (actor, rkey) =
+            parse_post("https://bsky.app/profile/Alice.bsky.social/post/3kxyz").unwrap();
+        assert!(matches!(actor, AtIdentifier::Handle(h) if h.as_str() == "alice.bsky.social"));
+        assert_eq!(rkey, "3kxyz");
+
+        let (actor, rkey) =
+            parse_post("at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/3k2").unwrap();
+        assert!(matches!(actor, AtIdentifier::Did(_)));
+        assert_eq!(rkey, "3k2");
+
+        let (_, rkey) =
+            parse_post("bsky.app/profile/alice.bsky.social/post/3k2/?ref=share").unwrap();
+        assert_eq!(rkey, "3k2");
+
+        for input in [
+            "https://bsky.app/profile/alice.bsky.social",
+            "https://bsky.app/profile/alice.bsky.social/lists/3k2",
+            "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.graph.list/3k2",
+            "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post",
+            "https://example.com/profile/alice.bsky.social/post/3k2",
+        ] {
+            assert!(parse_post(input).is_err(), "{}", input);
+        }
+    }
+}
@@ -1,14 +1,65 @@
+use crate::expiration::ExpirationLength;
+use crate::models::MuteEvent;
+use crate::models::RecurringMute;
+use crate::models::TimedBlock;
+use crate::models::TimedListMute;
 use crate::models::TimedMute;
 use crate::models::TimedMuteWord;
+use crate::models::TimedThreadMute;
+use crate::tmute::CreateOutcome;
+use crate::tmute::CreateTimedBlockRequest;
+use crate::tmute::CreateTimedListMuteRequest;
 use crate::tmute::CreateTimedMuteRequest;
+use crate::tmute::CreateTimedMuteResponse;
+use crate::tmute::CreateTimedThreadMuteRequest;
+use crate::tmute::DeleteTimedBlockRequest;
+use crate::tmute::DeleteTimedListMuteRequest;
 use crate::tmute::DeleteTimedMuteRequest;
+use crate::tmute::DeleteTimedThreadMuteRequest;
+use crate::tmute::ExtendTimedMuteRequest;
+use crate::tmute::ExtendTimedMuteResponse;
+use crate::tmute::ExtendTimedMuteWordRequest;
+use crate::tmute::FailedTimedMutes;
+use crate::tmute::ListMuteMode;
+use crate::tmute::MuteWordActorTarget;
+use crate::tmute::MuteWordTarget;
+use crate::tmute::OnConflict;
 use crate::user::IsActiveSuccessResponse;
 use crate::user::LoginRequest;
 use std::env;
 
-use crate::scheduler::start_scheduler;
-use crate::tmute::{create, create_word, delete, delete_word, list, list_word, trigger};
+use crate::bulk::BulkCreateTimedMuteRequest;
+use crate::bulk::BulkCreateTimedMuteResponse;
+use crate::bulk::BulkTimedMuteItem;
+use crate::bulk::BulkTimedMuteResult;
+use crate::event::EntryKind;
+use crate::event::EventAction;
+use crate::event::EventSource;
+use crate::event::MuteEvents;
+use crate::group::ActivateMuteGroupRequest;
+use crate::group::AddMuteGroupMembersRequest;
+use crate::group::CreateMuteGroupRequest;
+use crate::group::DeleteMuteGroupRequest;
+use crate::group::MuteGroupActivation;
+use crate::group::MuteGroupResult;
+use crate::group::MuteGroupView;
+use crate::history::TimedMuteHistory;
+use crate::history::TimedMuteWordHistory;
+use crate::pileon::PileOnMuteRequest;
+use crate::pileon::PileOnMuteResponse;
+use crate::reconcile::ReconcileReport;
+use crate::recurring::CreateRecurringMuteRequest;
+use crate::recurring::DeleteRecurringMuteRequest;
+use crate::scheduler::{
+    start_expiry_scheduler, start_reconcile_scheduler, start_recurring_scheduler, start_scheduler,
+};
+use crate::tmute::{
+    create, create_block, create_list, create_thread, create_word, delete, delete_block,
+    delete_list, delete_thread, delete_word, extend, extend_word, list, list_block, list_failed,
+    list_list, list_thread, list_word, trigger,
+};
 use crate::user::{is_active, login, logout};
+use axum::http::{header, Method};
 use axum::{
     routing::{get, post},
     Router,
@@ -20,14 +71,29 @@ use tower_http::cors::CorsLayer;
 use tower_sessions::{cookie::SameSite, Expiry, MemoryStore, SessionManagerLayer};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
-use axum::http::{header, Method};
 
+mod admin;
 pub mod agent;
+mod bulk;
+pub mod crypto;
 pub mod error;
+mod event;
+pub mod expiration;
+mod extract;
+mod group;
 pub mod helper;
+mod history;
+mod identifier;
 pub mod models;
+mod pileon;
+mod ratelimit;
+mod reconcile;
+mod recurring;
+mod resolver;
+mod scheduled;
 mod scheduler;
 pub mod schema;
+pub mod status;
 mod tmute;
 mod user;
 
@@ -48,11 +114,39 @@ pub const COOKIE_DATE_KEY: &str = "cookie_date";
 #[openapi(
     paths(
         tmute::create,
+        bulk::create_bulk,
+        pileon::create_pile_on,
+        group::list_groups,
+        group::create_group,
+        group::add_members,
+        group::activate_group,
+        group::delete_group,
+        recurring::list_recurring,
+        recurring::create_recurring,
+        recurring::delete_recurring,
         tmute::list,
         tmute::delete,
         tmute::list_word,
         tmute::create_word,
         tmute::delete_word,
+        tmute::extend,
+        tmute::extend_word,
+        history::list_history,
+        history::list_word_history,
+        reconcile::reconcile,
+        tmute::list_failed,
+        admin::list_failed,
+        event::list_events,
+        admin::list_events,
+        tmute::list_block,
+        tmute::create_block,
+        tmute::delete_block,
+        tmute::list_thread,
+        tmute::create_thread,
+        tmute::delete_thread,
+        tmute::list_list,
+        tmute::create_list,
+        tmute::delete_list,
         user::login,
         user::logout,
         user::is_active
@@ -60,9 +154,53 @@ pub const COOKIE_DATE_KEY: &str = "cookie_date";
     components(schemas(
         TimedMute,
         TimedMuteWord,
+        MuteWordTarget,
+        MuteWordActorTarget,
+        ExpirationLength,
+        TimedBlock,
         CreateTimedMuteRequest,
+        OnConflict,
+        CreateOutcome,
+        CreateTimedMuteResponse,
+        BulkTimedMuteItem,
+        BulkCreateTimedMuteRequest,
+        BulkTimedMuteResult,
+        BulkCreateTimedMuteResponse,
+        PileOnMuteRequest,
+        PileOnMuteResponse,
+        CreateMuteGroupRequest,
+        AddMuteGroupMembersRequest,
+        ActivateMuteGroupRequest,
+        DeleteMuteGroupRequest,
+        MuteGroupView,
+        MuteGroupResult,
+        MuteGroupActivation,
+        RecurringMute,
+        CreateRecurringMuteRequest,
+        DeleteRecurringMuteRequest,
+        CreateTimedBlockRequest,
+        DeleteTimedBlockRequest,
+        TimedThreadMute,
+        CreateTimedThreadMuteRequest,
+        DeleteTimedThreadMuteRequest,
+        TimedListMute,
+        ListMuteMode,
+        CreateTimedListMuteRequest,
+        DeleteTimedListMuteRequest,
         LoginRequest,
         DeleteTimedMuteRequest,
+        ExtendTimedMuteRequest,
+        ExtendTimedMuteWordRequest,
+        ExtendTimedMuteResponse,
+        TimedMuteHistory,
+        TimedMuteWordHistory,
+        ReconcileReport,
+        FailedTimedMutes,
+        MuteEvent,
+        MuteEvents,
+        EventSource,
+        EventAction,
+        EntryKind,
         IsActiveSuccessResponse,
     ))
 )]
@@ -84,8 +222,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Get Environment Variables
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL");
+    crypto::init()?;
+
+    // Admin commands
+    if env::args().nth(1).as_deref() == Some("rotate-encryption-key") {
+        let mut conn = helper::establish_connection(database_url.as_str());
+        let updated = helper::reencrypt_profiles(&mut conn)?;
+        println!("Re-encrypted {} profile rows", updated);
+        return Ok(());
+    }
     let db_min_idle = env::var("DB_MIN_IDLE").unwrap_or("1".to_string());
     let cron_schedule = env::var("CRON_SCHEDULE").unwrap_or("0 1 * * * * *".to_string());
+    let reconcile_schedule = env::var("RECONCILE_SCHEDULE").unwrap_or("0 31 * * * * *".to_string());
+    let recurring_schedule = env::var("RECURRING_SCHEDULE").unwrap_or("0 * * * * * *".to_string());
     let allowed_origin =
         env::var("ALLOWED_ORIGIN").unwrap_or("http://frontend.ripp.internal".to_string());
     let server_port = env::var("SERVER_PORT").unwrap_or("9090".to_string());
@@ -93,10 +242,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Create DB Pool
     let db_pool = init_db(database_url.as_str(), db_min_idle.as_str());
+    agent::init(db_pool.clone());
 
     // Start Scheduler
     if cron_enabled {
+        start_expiry_scheduler();
         start_scheduler(cron_schedule.as_str()).await;
+        start_reconcile_scheduler(reconcile_schedule.as_str()).await;
+        start_recurring_scheduler(recurring_schedule.as_str()).await;
     }
 
     // CORS
@@ -111,7 +264,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let session_layer = SessionManagerLayer::new(session_store)
         .with_secure(env::var("HTTPS_ENABLED").unwrap_or("1".to_string()) == "1")
         .with_same_site(SameSite::Strict)
-        .with_expiry(Expiry::OnInactivity(tower_sessions::cookie::time::Duration::weeks(1)));
+        .with_expiry(Expiry::OnInactivity(
+            tower_sessions::cookie::time::Duration::weeks(1),
+        ));
 
     // Router
     let app = Router::new()
@@ -119,12 +274,40 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/logout", post(logout))
         .route("/timed-mutes", get(list))
         .route("/timed-mute", post(create))
+        .route("/timed-mutes/bulk", post(bulk::create_bulk))
+        .route("/timed-mutes/pile-on", post(pileon::create_pile_on))
+        .route("/mute-groups", get(group::list_groups))
+        .route("/mute-group", post(group::create_group))
+        .route("/mute-group/members", post(group::add_members))
+        .route("/activateMuteGroup", post(group::activate_group))
+        .route("/deleteMuteGroup", post(group::delete_group))
+        .route("/recurring-mutes", get(recurring::list_recurring))
+        .route("/recurring-mute", post(recurring::create_recurring))
+        .route("/deleteRecurringMute", post(recurring::delete_recurring))
         .route("/deleteTimedMute", post(delete))
         .route("/trigger", post(trigger))
         .route("/active", get(is_active))
         .route("/timed-mute-words", get(list_word))
         .route("/timed-mute-word", post(create_word))
         .route("/deleteTimedMuteWord", post(delete_word))
+        .route("/extendTimedMute", post(extend))
+        .route("/extendTimedMuteWord", post(extend_word))
+        .route("/timed-mutes/history", get(history::list_history))
+        .route("/timed-mute-words/history", get(history::list_word_history))
+        .route("/reconcile", post(reconcile::reconcile))
+        .route("/timed-failures", get(list_failed))
+        .route("/admin/timed-failures", get(admin::list_failed))
+        .route("/events", get(event::list_events))
+        .route("/admin/events", get(admin::list_events))
+        .route("/timed-blocks", get(list_block))
+        .route("/timed-block", post(create_block))
+        .route("/deleteTimedBlock", post(delete_block))
+        .route("/timed-thread-mutes", get(list_thread))
+        .route("/timed-thread-mute", post(create_thread))
+        .route("/deleteTimedThreadMute", post(delete_thread))
+        .route("/timed-list-mutes", get(list_list))
+        .route("/timed-list-mute", post(create_list))
+        .route("/deleteTimedListMute", post(delete_list))
         .route("/deactivate", post(crate::user::deactivate))
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .layer(session_layer)
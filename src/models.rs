@@ -1,35 +1,83 @@
+use crate::schema::mute_event;
+use crate::schema::mute_group;
+use crate::schema::mute_group_member;
 use crate::schema::profile;
+use crate::schema::profile_session;
+use crate::schema::recurring_mute;
+use crate::schema::timed_block;
+use crate::schema::timed_list_mute;
 use crate::schema::timed_mute;
 use crate::schema::timed_mute_word;
+use crate::schema::timed_thread_mute;
+use crate::status::MuteStatus;
 use diesel::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use utoipa::ToSchema;
 
 #[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
 #[diesel(table_name = crate::schema::timed_mute)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct TimedMute {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
     pub actor: String,
     pub muted_actor: String,
     pub created_date: i64,
     pub expiration_date: i64,
-    pub status: i32,
+    /// See `MuteStatus` for the codes.
+    #[schema(value_type = i32)]
+    pub status: MuteStatus,
+    /// Expiration date the mute was created with, set the first time it is extended.
+    pub original_expiration_date: Option<i64>,
+    /// When the expiration date was last changed.
+    pub extended_date: Option<i64>,
+    /// When reconciliation found the mute had been removed outside this service.
+    pub drift_date: Option<i64>,
+    /// When the resolver undid the mute on Bluesky.
+    pub expired_date: Option<i64>,
+    /// When the user deleted the mute before it expired.
+    pub cancelled_date: Option<i64>,
+    /// Failed attempts at undoing the mute on Bluesky after it expired.
+    pub attempt_count: i32,
+    pub last_error: Option<String>,
+    /// When the resolver will retry, after a failed attempt.
+    pub next_attempt_date: Option<i64>,
+    /// The account was already muted when the timed mute was created, so it
+    /// stays muted when the timed mute ends.
+    pub was_muted_before: bool,
+    /// Mute group the mute was activated with; it expires with the group.
+    pub group_id: Option<i32>,
+    /// When a scheduled mute starts; only set for mutes created with `starts_at`.
+    pub start_date: Option<i64>,
 }
 
 impl TimedMute {
     pub fn new(
+        id: i32,
         actor: String,
         muted_actor: String,
         created_date: i64,
         expiration_date: i64,
-        status: i32,
+        status: MuteStatus,
     ) -> Self {
         Self {
+            id,
             actor,
             muted_actor,
             created_date,
             expiration_date,
             status,
+            original_expiration_date: None,
+            extended_date: None,
+            drift_date: None,
+            expired_date: None,
+            cancelled_date: None,
+            attempt_count: 0,
+            last_error: None,
+            next_attempt_date: None,
+            was_muted_before: false,
+            group_id: None,
+            start_date: None,
         }
     }
 }
@@ -41,7 +89,9 @@ pub struct NewTimedMute<'a> {
     pub muted_actor: &'a str,
     pub created_date: &'a i64,
     pub expiration_date: &'a i64,
-    pub status: &'a i32,
+    pub status: &'a MuteStatus,
+    pub was_muted_before: &'a bool,
+    pub start_date: Option<&'a i64>,
 }
 
 #[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
@@ -74,43 +124,437 @@ pub struct NewProfile<'a> {
     pub status: &'a i32,
 }
 
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::schema::profile_session)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ProfileSession {
+    pub access_jwt: String,
+    pub refresh_jwt: String,
+    pub did: String,
+    pub active: bool,
+    pub status: Option<String>,
+}
+
+impl ProfileSession {
+    pub fn new(
+        access_jwt: String,
+        refresh_jwt: String,
+        did: String,
+        active: bool,
+        status: Option<String>,
+    ) -> Self {
+        Self {
+            access_jwt,
+            refresh_jwt,
+            did,
+            active,
+            status,
+        }
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = profile_session)]
+pub struct NewProfileSession<'a> {
+    pub access_jwt: &'a str,
+    pub refresh_jwt: &'a str,
+    pub did: &'a str,
+    pub active: &'a bool,
+    pub status: Option<&'a str>,
+}
+
 #[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
 #[diesel(table_name = crate::schema::timed_mute_word)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct TimedMuteWord {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
     pub actor: String,
     pub muted_word: String,
     pub created_date: i64,
     pub expiration_date: i64,
-    pub status: i32,
+    /// See `MuteStatus` for the codes.
+    #[schema(value_type = i32)]
+    pub status: MuteStatus,
+    /// Expiration date the mute was created with, set the first time it is extended.
+    pub original_expiration_date: Option<i64>,
+    /// When the expiration date was last changed.
+    pub extended_date: Option<i64>,
+    /// Stored comma separated, returned as a list of `tag` and `content`.
+    #[serde(
+        serialize_with = "serialize_targets",
+        deserialize_with = "deserialize_targets"
+    )]
+    #[schema(value_type = Vec<String>)]
+    pub targets: String,
+    /// `all`, or `exclude-following` to keep showing posts from followed accounts.
+    pub actor_target: String,
+    /// When reconciliation found the word had been removed outside this service.
+    pub drift_date: Option<i64>,
+    /// When the resolver undid the mute on Bluesky.
+    pub expired_date: Option<i64>,
+    /// When the user deleted the mute before it expired.
+    pub cancelled_date: Option<i64>,
+    /// Failed attempts at undoing the mute on Bluesky after it expired.
+    pub attempt_count: i32,
+    pub last_error: Option<String>,
+    /// When the resolver will retry, after a failed attempt.
+    pub next_attempt_date: Option<i64>,
+    /// Mute group the word was activated with; it expires with the group.
+    pub group_id: Option<i32>,
+    /// When a scheduled word starts; only set for words created with `starts_at`.
+    pub start_date: Option<i64>,
 }
 
 impl TimedMuteWord {
     pub fn new(
+        id: i32,
         actor: String,
         muted_word: String,
         created_date: i64,
         expiration_date: i64,
-        status: i32,
+        status: MuteStatus,
     ) -> Self {
         Self {
+            id,
             actor,
             muted_word,
             created_date,
             expiration_date,
             status,
+            original_expiration_date: None,
+            extended_date: None,
+            targets: "tag,content".to_string(),
+            actor_target: "all".to_string(),
+            drift_date: None,
+            expired_date: None,
+            cancelled_date: None,
+            attempt_count: 0,
+            last_error: None,
+            next_attempt_date: None,
+            group_id: None,
+            start_date: None,
         }
     }
 }
 
+fn serialize_targets<S: Serializer>(targets: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(targets.split(',').filter(|t| !t.is_empty()))
+}
+
+fn deserialize_targets<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Vec::<String>::deserialize(deserializer)?.join(","))
+}
+
 #[derive(Insertable)]
 #[diesel(table_name = timed_mute_word)]
 pub struct NewTimedMuteWord<'a> {
     pub actor: &'a str,
     pub muted_word: &'a str,
+    pub targets: &'a str,
+    pub actor_target: &'a str,
     pub created_date: &'a i64,
     pub expiration_date: &'a i64,
-    pub status: &'a i32,
+    pub status: &'a MuteStatus,
+    pub start_date: Option<&'a i64>,
+}
+
+#[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
+#[diesel(table_name = crate::schema::timed_block)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TimedBlock {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
+    pub actor: String,
+    pub blocked_actor: String,
+    pub record_uri: String,
+    pub created_date: i64,
+    pub expiration_date: i64,
+    /// See `MuteStatus` for the codes.
+    #[schema(value_type = i32)]
+    pub status: MuteStatus,
+    /// Failed attempts at undoing the mute on Bluesky after it expired.
+    pub attempt_count: i32,
+    pub last_error: Option<String>,
+    /// When the resolver will retry, after a failed attempt.
+    pub next_attempt_date: Option<i64>,
+}
+
+impl TimedBlock {
+    pub fn new(
+        id: i32,
+        actor: String,
+        blocked_actor: String,
+        record_uri: String,
+        created_date: i64,
+        expiration_date: i64,
+        status: MuteStatus,
+    ) -> Self {
+        Self {
+            id,
+            actor,
+            blocked_actor,
+            record_uri,
+            created_date,
+            expiration_date,
+            status,
+            attempt_count: 0,
+            last_error: None,
+            next_attempt_date: None,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = timed_block)]
+pub struct NewTimedBlock<'a> {
+    pub actor: &'a str,
+    pub blocked_actor: &'a str,
+    pub record_uri: &'a str,
+    pub created_date: &'a i64,
+    pub expiration_date: &'a i64,
+    pub status: &'a MuteStatus,
+}
+
+#[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
+#[diesel(table_name = crate::schema::timed_thread_mute)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TimedThreadMute {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
+    pub actor: String,
+    pub thread_uri: String,
+    pub created_date: i64,
+    pub expiration_date: i64,
+    /// See `MuteStatus` for the codes.
+    #[schema(value_type = i32)]
+    pub status: MuteStatus,
+    /// Failed attempts at undoing the mute on Bluesky after it expired.
+    pub attempt_count: i32,
+    pub last_error: Option<String>,
+    /// When the resolver will retry, after a failed attempt.
+    pub next_attempt_date: Option<i64>,
+}
+
+impl TimedThreadMute {
+    pub fn new(
+        id: i32,
+        actor: String,
+        thread_uri: String,
+        created_date: i64,
+        expiration_date: i64,
+        status: MuteStatus,
+    ) -> Self {
+        Self {
+            id,
+            actor,
+            thread_uri,
+            created_date,
+            expiration_date,
+            status,
+            attempt_count: 0,
+            last_error: None,
+            next_attempt_date: None,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = timed_thread_mute)]
+pub struct NewTimedThreadMute<'a> {
+    pub actor: &'a str,
+    pub thread_uri: &'a str,
+    pub created_date: &'a i64,
+    pub expiration_date: &'a i64,
+    pub status: &'a MuteStatus,
+}
+
+/// A moderation list applied for a limited time. `mode` is either `mute`
+/// (`muteActorList`) or `block` (a `listblock` record stored in `record_uri`).
+#[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
+#[diesel(table_name = crate::schema::timed_list_mute)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TimedListMute {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
+    pub actor: String,
+    pub list_uri: String,
+    pub mode: String,
+    pub record_uri: Option<String>,
+    pub created_date: i64,
+    pub expiration_date: i64,
+    /// See `MuteStatus` for the codes.
+    #[schema(value_type = i32)]
+    pub status: MuteStatus,
+    /// Failed attempts at undoing the mute on Bluesky after it expired.
+    pub attempt_count: i32,
+    pub last_error: Option<String>,
+    /// When the resolver will retry, after a failed attempt.
+    pub next_attempt_date: Option<i64>,
+}
+
+impl TimedListMute {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: i32,
+        actor: String,
+        list_uri: String,
+        mode: String,
+        record_uri: Option<String>,
+        created_date: i64,
+        expiration_date: i64,
+        status: MuteStatus,
+    ) -> Self {
+        Self {
+            id,
+            actor,
+            list_uri,
+            mode,
+            record_uri,
+            created_date,
+            expiration_date,
+            status,
+            attempt_count: 0,
+            last_error: None,
+            next_attempt_date: None,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = timed_list_mute)]
+pub struct NewTimedListMute<'a> {
+    pub actor: &'a str,
+    pub list_uri: &'a str,
+    pub mode: &'a str,
+    pub record_uri: Option<&'a str>,
+    pub created_date: &'a i64,
+    pub expiration_date: &'a i64,
+    pub status: &'a MuteStatus,
+}
+
+/// One action on a timed entry, kept for the audit log. `result` is `ok` or
+/// `error` for the Bluesky call made for the action, or `none` if there was
+/// no call.
+#[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
+#[diesel(table_name = crate::schema::mute_event)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MuteEvent {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
+    /// The user whose entry it is.
+    pub actor: String,
+    pub source: String,
+    pub action: String,
+    pub kind: String,
+    /// DID, word, thread or list URI, depending on `kind`.
+    pub target: String,
+    /// Row of the timed entry, where known.
+    pub entry_id: Option<i32>,
+    pub result: String,
+    pub error: Option<String>,
+    pub created_date: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = mute_event)]
+pub struct NewMuteEvent<'a> {
+    pub actor: &'a str,
+    pub source: &'a str,
+    pub action: &'a str,
+    pub kind: &'a str,
+    pub target: &'a str,
+    pub entry_id: Option<i32>,
+    pub result: &'a str,
+    pub error: Option<&'a str>,
+    pub created_date: &'a i64,
+}
+
+/// A saved set of accounts and words muted together with one expiration.
+#[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
+#[diesel(table_name = crate::schema::mute_group)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MuteGroup {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
+    pub actor: String,
+    pub name: String,
+    pub created_date: i64,
+    /// Expiration of the last activation.
+    pub expiration_date: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = mute_group)]
+pub struct NewMuteGroup<'a> {
+    pub actor: &'a str,
+    pub name: &'a str,
+    pub created_date: &'a i64,
+}
+
+#[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
+#[diesel(table_name = crate::schema::mute_group_member)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MuteGroupMember {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
+    pub group_id: i32,
+    /// `mute` or `word`.
+    pub kind: String,
+    /// DID of the account, or the word.
+    pub target: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = mute_group_member)]
+pub struct NewMuteGroupMember<'a> {
+    pub group_id: &'a i32,
+    pub kind: &'a str,
+    pub target: &'a str,
+}
+
+#[derive(Queryable, Selectable, Debug, Deserialize, Serialize, ToSchema)]
+#[diesel(table_name = crate::schema::recurring_mute)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct RecurringMute {
+    #[diesel(column_name = rowid)]
+    pub id: i32,
+    pub actor: String,
+    /// `mute` or `word`.
+    pub kind: String,
+    /// DID of the account, or the word.
+    pub target: String,
+    /// Five-field cron expression for when each occurrence starts.
+    pub cron: String,
+    /// IANA timezone the cron expression is read in.
+    pub timezone: String,
+    /// How long each occurrence lasts, in seconds.
+    pub duration: i64,
+    /// Start of the next occurrence to materialize.
+    pub next_run_date: i64,
+    /// Start of the last occurrence materialized.
+    pub last_run_date: Option<i64>,
+    /// Why the last occurrence could not be muted on Bluesky.
+    pub last_error: Option<String>,
+    pub created_date: i64,
+    /// Failed attempts to materialize the last occurrence.
+    pub attempt_count: i32,
+    /// When the last occurrence is tried again, if it failed and has
+    /// attempts left.
+    pub next_attempt_date: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = recurring_mute)]
+pub struct NewRecurringMute<'a> {
+    pub actor: &'a str,
+    pub kind: &'a str,
+    pub target: &'a str,
+    pub cron: &'a str,
+    pub timezone: &'a str,
+    pub duration: &'a i64,
+    pub next_run_date: &'a i64,
+    pub created_date: &'a i64,
 }
 
 #[cfg(test)]
@@ -119,12 +563,26 @@ mod tests {
 
     #[test]
     fn test_timed_mute_new() {
-        let tm = TimedMute::new("actor1".to_string(), "muted1".to_string(), 1000, 2000, 0);
+        let tm = TimedMute::new(
+            1,
+            "actor1".to_string(),
+            "muted1".to_string(),
+            1000,
+            2000,
+            MuteStatus::Active,
+        );
+        assert_eq!(tm.id, 1);
         assert_eq!(tm.actor, "actor1");
         assert_eq!(tm.muted_actor, "muted1");
         assert_eq!(tm.created_date, 1000);
         assert_eq!(tm.expiration_date, 2000);
-        assert_eq!(tm.status, 0);
+        assert_eq!(tm.status, MuteStatus::Active);
+        assert_eq!(tm.attempt_count, 0);
+        assert_eq!(tm.last_error, None);
+        assert_eq!(tm.next_attempt_date, None);
+        assert_eq!(tm.original_expiration_date, None);
+        assert_eq!(tm.extended_date, None);
+        assert_eq!(tm.drift_date, None);
     }
 
     #[test]
@@ -141,13 +599,136 @@ mod tests {
         assert_eq!(p.status, 0);
     }
 
+    #[test]
+    fn test_profile_session_new() {
+        let ps = ProfileSession::new(
+            "access1".to_string(),
+            "refresh1".to_string(),
+            "did1".to_string(),
+            true,
+            None,
+        );
+        assert_eq!(ps.access_jwt, "access1");
+        assert_eq!(ps.refresh_jwt, "refresh1");
+        assert_eq!(ps.did, "did1");
+        assert!(ps.active);
+        assert_eq!(ps.status, None);
+    }
+
     #[test]
     fn test_timed_mute_word_new() {
-        let tmw = TimedMuteWord::new("actor1".to_string(), "word1".to_string(), 1000, 2000, 0);
+        let tmw = TimedMuteWord::new(
+            1,
+            "actor1".to_string(),
+            "word1".to_string(),
+            1000,
+            2000,
+            MuteStatus::Active,
+        );
+        assert_eq!(tmw.id, 1);
         assert_eq!(tmw.actor, "actor1");
         assert_eq!(tmw.muted_word, "word1");
         assert_eq!(tmw.created_date, 1000);
         assert_eq!(tmw.expiration_date, 2000);
-        assert_eq!(tmw.status, 0);
+        assert_eq!(tmw.status, MuteStatus::Active);
+        assert_eq!(tmw.attempt_count, 0);
+        assert_eq!(tmw.last_error, None);
+        assert_eq!(tmw.next_attempt_date, None);
+        assert_eq!(tmw.original_expiration_date, None);
+        assert_eq!(tmw.extended_date, None);
+        assert_eq!(tmw.targets, "tag,content");
+        assert_eq!(tmw.actor_target, "all");
+        assert_eq!(tmw.drift_date, None);
+    }
+
+    #[test]
+    fn test_timed_mute_word_targets_serialize_as_list() {
+        let mut tmw = TimedMuteWord::new(
+            1,
+            "actor1".to_string(),
+            "word1".to_string(),
+            1000,
+            2000,
+            MuteStatus::Active,
+        );
+        tmw.targets = "tag".to_string();
+        let json = serde_json::to_value(&tmw).unwrap();
+        assert_eq!(json["targets"], serde_json::json!(["tag"]));
+        assert_eq!(json["actor_target"], "all");
+
+        let parsed: TimedMuteWord = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.targets, "tag");
+    }
+
+    #[test]
+    fn test_timed_block_new() {
+        let tb = TimedBlock::new(
+            1,
+            "actor1".to_string(),
+            "blocked1".to_string(),
+            "at://actor1/app.bsky.graph.block/1".to_string(),
+            1000,
+            2000,
+            MuteStatus::Active,
+        );
+        assert_eq!(tb.id, 1);
+        assert_eq!(tb.actor, "actor1");
+        assert_eq!(tb.blocked_actor, "blocked1");
+        assert_eq!(tb.record_uri, "at://actor1/app.bsky.graph.block/1");
+        assert_eq!(tb.created_date, 1000);
+        assert_eq!(tb.expiration_date, 2000);
+        assert_eq!(tb.status, MuteStatus::Active);
+        assert_eq!(tb.attempt_count, 0);
+        assert_eq!(tb.last_error, None);
+        assert_eq!(tb.next_attempt_date, None);
+    }
+
+    #[test]
+    fn test_timed_thread_mute_new() {
+        let ttm = TimedThreadMute::new(
+            1,
+            "actor1".to_string(),
+            "at://did1/app.bsky.feed.post/1".to_string(),
+            1000,
+            2000,
+            MuteStatus::Active,
+        );
+        assert_eq!(ttm.id, 1);
+        assert_eq!(ttm.actor, "actor1");
+        assert_eq!(ttm.thread_uri, "at://did1/app.bsky.feed.post/1");
+        assert_eq!(ttm.created_date, 1000);
+        assert_eq!(ttm.expiration_date, 2000);
+        assert_eq!(ttm.status, MuteStatus::Active);
+        assert_eq!(ttm.attempt_count, 0);
+        assert_eq!(ttm.last_error, None);
+        assert_eq!(ttm.next_attempt_date, None);
+    }
+
+    #[test]
+    fn test_timed_list_mute_new() {
+        let tlm = TimedListMute::new(
+            1,
+            "actor1".to_string(),
+            "at://did1/app.bsky.graph.list/1".to_string(),
+            "block".to_string(),
+            Some("at://actor1/app.bsky.graph.listblock/1".to_string()),
+            1000,
+            2000,
+            MuteStatus::Active,
+        );
+        assert_eq!(tlm.id, 1);
+        assert_eq!(tlm.actor, "actor1");
+        assert_eq!(tlm.list_uri, "at://did1/app.bsky.graph.list/1");
+        assert_eq!(tlm.mode, "block");
+        assert_eq!(
+            tlm.record_uri.as_deref(),
+            Some("at://actor1/app.bsky.graph.listblock/1")
+        );
+        assert_eq!(tlm.created_date, 1000);
+        assert_eq!(tlm.expiration_date, 2000);
+        assert_eq!(tlm.status, MuteStatus::Active);
+        assert_eq!(tlm.attempt_count, 0);
+        assert_eq!(tlm.last_error, None);
+        assert_eq!(tlm.next_attempt_date, None);
     }
 }
@@ -0,0 +1,287 @@
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use bsky_sdk::api::app::bsky::actor::defs::ProfileViewDetailed;
+use bsky_sdk::api::types::string::{AtIdentifier, Did};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tower_sessions::Session;
+use utoipa::ToSchema;
+
+use crate::agent::{
+    get_agent_for_profile, get_profiles, get_quote_authors, get_reply_authors, get_reposted_by,
+    resolve_handle, PROFILES_PER_REQUEST,
+};
+use crate::bulk::BulkTimedMuteResult;
+use crate::error::AppError;
+use crate::event::EventSource;
+use crate::expiration::{resolve_expiration, ExpirationLength};
+use crate::extract::Json;
+use crate::helper::fetch_profile_v1;
+use crate::identifier::parse_post;
+use crate::tmute::{apply_timed_mute, get_user_id, OnConflict};
+use crate::{DBPool, APPLICATION_JSON};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Most accounts one pile-on request may mute.
+const MAX_ACCOUNTS: usize = 250;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct PileOnMuteRequest {
+    /// `at://` URI or `bsky.app` link of the post.
+    pub post: String,
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// What to do when an account already has an active timed mute; defaults to `extend`.
+    #[serde(default)]
+    pub on_conflict: Option<OnConflict>,
+    /// Leave out accounts the user follows.
+    #[serde(default)]
+    pub skip_following: bool,
+    /// Leave out accounts the user follows that follow the user back.
+    #[serde(default)]
+    pub skip_mutuals: bool,
+    /// Most accounts to mute, up to 250. Defaults to 250.
+    #[serde(default)]
+    pub max_accounts: Option<usize>,
+}
+
+impl PileOnMuteRequest {
+    fn max_accounts(&self) -> Result<usize> {
+        match self.max_accounts {
+            None => Ok(MAX_ACCOUNTS),
+            Some(max) if (1..=MAX_ACCOUNTS).contains(&max) => Ok(max),
+            Some(_) => Err(AppError::BadRequest(format!(
+                "max_accounts must be between 1 and {}",
+                MAX_ACCOUNTS
+            ))),
+        }
+    }
+
+    fn skips(&self, profile: &ProfileViewDetailed) -> bool {
+        let viewer = profile.viewer.as_ref();
+        let following = viewer.is_some_and(|v| v.following.is_some());
+        let followed_by = viewer.is_some_and(|v| v.followed_by.is_some());
+        (self.skip_following && following) || (self.skip_mutuals && following && followed_by)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct PileOnMuteResponse {
+    /// AT-URI of the post.
+    pub post: String,
+    /// Accounts that replied to, quoted or reposted the post, other than the
+    /// user and the post's author.
+    pub found: usize,
+    /// Accounts left out by `skip_following` or `skip_mutuals`.
+    pub skipped: usize,
+    /// One entry per account a timed mute was attempted for, replies first,
+    /// then quotes, then reposts.
+    pub results: Vec<BulkTimedMuteResult>,
+}
+
+/// Merges lists of DIDs in order, keeping the first of each and leaving out
+/// `exclude`.
+fn merge_dids(lists: Vec<Vec<Did>>, exclude: &[&str]) -> Vec<Did> {
+    let mut seen: HashSet<String> = exclude.iter().map(|did| did.to_string()).collect();
+    lists
+        .into_iter()
+        .flatten()
+        .filter(|did| seen.insert(did.to_string()))
+        .collect()
+}
+
+#[utoipa::path(
+    post,
+    path = "/timed-mutes/pile-on",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Result for each account muted from the post", body = PileOnMuteResponse),
+        (status=400, description="Invalid post, duration or max_accounts"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn create_pile_on(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<PileOnMuteRequest>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let max_accounts = req.max_accounts()?;
+    let create_time = chrono::offset::Utc::now().timestamp();
+    let expire_time = resolve_expiration(
+        create_time,
+        req.expiration_length.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
+    let (author, rkey) = parse_post(&req.post)?;
+
+    let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
+    let profile = profile_list.first().ok_or(AppError::NotFound)?;
+    let agent = get_agent_for_profile(profile).await?;
+    let author = match author {
+        AtIdentifier::Did(did) => did.to_string(),
+        AtIdentifier::Handle(handle) => resolve_handle(&agent, handle).await?,
+    };
+    let post_uri = format!("at://{}/app.bsky.feed.post/{}", author, rkey);
+
+    // The thread is read first so the access token is fresh for getQuotes.
+    let replies = get_reply_authors(&agent, post_uri.as_str()).await?;
+    let quotes = get_quote_authors(&agent, post_uri.as_str()).await?;
+    let reposts = get_reposted_by(&agent, post_uri.as_str()).await?;
+    let candidates = merge_dids(
+        vec![replies, quotes, reposts],
+        &[user_id.as_str(), author.as_str()],
+    );
+
+    let on_conflict = req.on_conflict.unwrap_or_default();
+    let mut skipped = 0;
+    let mut results = Vec::new();
+    for chunk in candidates.chunks(PROFILES_PER_REQUEST) {
+        if results.len() >= max_accounts {
+            break;
+        }
+        let actors = chunk.iter().cloned().map(AtIdentifier::Did).collect();
+        for muted in get_profiles(&agent, actors).await? {
+            if results.len() >= max_accounts {
+                break;
+            }
+            if req.skips(&muted) {
+                skipped += 1;
+                continue;
+            }
+            let actor = muted.handle.to_string();
+            let did = Some(muted.did.to_string());
+            let result = match apply_timed_mute(
+                &mut conn,
+                &agent,
+                user_id.as_str(),
+                EventSource::Api,
+                &muted,
+                create_time,
+                expire_time,
+                on_conflict,
+            )
+            .await
+            {
+                Ok(response) => BulkTimedMuteResult {
+                    actor,
+                    did,
+                    outcome: Some(response.outcome),
+                    expiration_date: Some(response.expiration_date),
+                    error: None,
+                },
+                Err(e) => BulkTimedMuteResult::failed(&actor, did, e),
+            };
+            results.push(result);
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(PileOnMuteResponse {
+            post: post_uri,
+            found: candidates.len(),
+            skipped,
+            results,
+        }),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn did(value: &str) -> Did {
+        value.parse().unwrap()
+    }
+
+    fn request(skip_following: bool, skip_mutuals: bool) -> PileOnMuteRequest {
+        PileOnMuteRequest {
+            post: "at://did:plc:author/app.bsky.feed.post/3k2".to_string(),
+            expiration_length: None,
+            expires_at: None,
+            on_conflict: None,
+            skip_following,
+            skip_mutuals,
+            max_accounts: None,
+        }
+    }
+
+    fn profile(following: bool, followed_by: bool) -> ProfileViewDetailed {
+        let mut viewer = serde_json::Map::new();
+        if following {
+            viewer.insert("following".into(), "at://did:plc:me/follow/1".into());
+        }
+        if followed_by {
+            viewer.insert("followedBy".into(), "at://did:plc:x/follow/1".into());
+        }
+        serde_json::from_value(serde_json::json!({
+            "did": "did:plc:x",
+            "handle": "x.bsky.social",
+            "viewer": viewer,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_merge_dids() {
+        let merged = merge_dids(
+            vec![
+                vec![did("did:plc:a"), did("did:plc:me"), did("did:plc:b")],
+                vec![did("did:plc:b"), did("did:plc:author")],
+                vec![did("did:plc:c"), did("did:plc:a")],
+            ],
+            &["did:plc:me", "did:plc:author"],
+        );
+        let merged: Vec<&str> = merged.iter().map(Did::as_str).collect();
+        assert_eq!(merged, ["did:plc:a", "did:plc:b", "did:plc:c"]);
+    }
+
+    #[test]
+    fn test_skips() {
+        let stranger = profile(false, false);
+        let follower = profile(false, true);
+        let followed = profile(true, false);
+        let mutual = profile(true, true);
+
+        let keep_all = request(false, false);
+        assert!(![&stranger, &follower, &followed, &mutual]
+            .iter()
+            .any(|p| keep_all.skips(p)));
+
+        let skip_mutuals = request(false, true);
+        assert!(skip_mutuals.skips(&mutual));
+        assert!(!skip_mutuals.skips(&followed));
+        assert!(!skip_mutuals.skips(&follower));
+
+        let skip_following = request(true, false);
+        assert!(skip_following.skips(&mutual));
+        assert!(skip_following.skips(&followed));
+        assert!(!skip_following.skips(&follower));
+        assert!(!skip_following.skips(&stranger));
+    }
+
+    #[test]
+    fn test_max_accounts() {
+        let mut req = request(false, false);
+        assert_eq!(req.max_accounts().unwrap(), MAX_ACCOUNTS);
+        req.max_accounts = Some(10);
+        assert_eq!(req.max_accounts().unwrap(), 10);
+        for max in [0, MAX_ACCOUNTS + 1] {
+            req.max_accounts = Some(max);
+            assert!(req.max_accounts().is_err());
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use atrium_xrpc_client::reqwest::ReqwestClient;
+use bsky_sdk::api::xrpc::http::{HeaderMap, Request, Response, StatusCode};
+use bsky_sdk::api::xrpc::{HttpClient, XrpcClient};
+
+static RATE_LIMITS: LazyLock<RateLimits> = LazyLock::new(RateLimits::default);
+
+const DEFAULT_PAUSE: i64 = 60;
+const DEFAULT_MAX_WAIT: i64 = 300;
+
+/// Unix timestamps until which requests are held back, for the whole instance
+/// and for single accounts.
+#[derive(Default)]
+struct RateLimits {
+    instance_until: Mutex<i64>,
+    accounts: Mutex<HashMap<String, i64>>,
+}
+
+impl RateLimits {
+    fn paused_until(&self, account: &str) -> i64 {
+        let instance = *self
+            .instance_until
+            .lock()
+            .expect("Error locking rate limits");
+        let account = self
+            .accounts
+            .lock()
+            .expect("Error locking rate limits")
+            .get(account)
+            .copied()
+            .unwrap_or(0);
+        instance.max(account)
+    }
+
+    fn pause_instance(&self, until: i64) {
+        let mut instance = self
+            .instance_until
+            .lock()
+            .expect("Error locking rate limits");
+        *instance = (*instance).max(until);
+    }
+
+    fn pause_account(&self, account: &str, until: i64) {
+        let mut accounts = self.accounts.lock().expect("Error locking rate limits");
+        let now = chrono::offset::Utc::now().timestamp();
+        accounts.retain(|_, until| *until > now);
+        let paused = accounts.entry(account.to_string()).or_default();
+        *paused = (*paused).max(until);
+    }
+}
+
+/// What a response says about rate limits.
+#[derive(Debug, PartialEq)]
+enum Throttle {
+    /// Rejected with 429: the instance waits until the given time.
+    Instance(i64),
+    /// Accepted, but the account has no requests left until the given time.
+    Account(i64),
+}
+
+/// Reads `ratelimit-remaining`, `ratelimit-reset` and `retry-after` from a
+/// response. A 429 pauses the whole instance, since every account is served
+/// from the same address; an exhausted quota on a successful response only
+/// pauses the account that used it up.
+fn throttle_for(status: StatusCode, headers: &HeaderMap, now: i64) -> Option<Throttle> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<i64>().ok())
+    };
+    let reset = header("ratelimit-reset").filter(|reset| *reset > now);
+    let retry_after = header("retry-after").map(|seconds| now + seconds.max(0));
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let until = reset.or(retry_after).unwrap_or(now + DEFAULT_PAUSE);
+        return Some(Throttle::Instance(until));
+    }
+    match (header("ratelimit-remaining"), reset) {
+        (Some(remaining), Some(reset)) if remaining <= 0 => Some(Throttle::Account(reset)),
+        _ => None,
+    }
+}
+
+fn max_wait() -> i64 {
+    env::var("RATE_LIMIT_MAX_WAIT")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_WAIT)
+}
+
+async fn wait_until(until: i64) {
+    let wait = until - chrono::offset::Utc::now().timestamp();
+    if wait > 0 {
+        tokio::time::sleep(Duration::from_secs(wait as u64)).await;
+    }
+}
+
+fn copy_request(request: &Request<Vec<u8>>) -> Request<Vec<u8>> {
+    let mut copy = Request::new(request.body().clone());
+    *copy.method_mut() = request.method().clone();
+    *copy.uri_mut() = request.uri().clone();
+    *copy.headers_mut() = request.headers().clone();
+    copy
+}
+
+/// XRPC client for one account that waits out Bluesky rate limits. Requests
+/// are held while the instance or the account is paused, and a request
+/// rejected with 429 is retried once if the pause is shorter than
+/// `RATE_LIMIT_MAX_WAIT` seconds.
+pub struct RateLimitedClient {
+    inner: ReqwestClient,
+    account: String,
+}
+
+impl RateLimitedClient {
+    pub fn new(base_uri: impl AsRef<str>, account: &str) -> Self {
+        Self {
+            inner: ReqwestClient::new(base_uri),
+            account: account.to_string(),
+        }
+    }
+
+    fn observe(&self, response: &Response<Vec<u8>>) -> Option<i64> {
+        let now = chrono::offset::Utc::now().timestamp();
+        match throttle_for(response.status(), response.headers(), now)? {
+            Throttle::Instance(until) => {
+                RATE_LIMITS.pause_instance(until);
+                Some(until)
+            }
+            Throttle::Account(until) => {
+                RATE_LIMITS.pause_account(self.account.as_str(), until);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl HttpClient for RateLimitedClient {
+    async fn send_http(
+        &self,
+        request: Request<Vec<u8>>,
+    ) -> Result<Response<Vec<u8>>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        wait_until(RATE_LIMITS.paused_until(self.account.as_str())).await;
+        let retry = copy_request(&request);
+        let response = self.inner.send_http(request).await?;
+        let Some(until) = self.observe(&response) else {
+            return Ok(response);
+        };
+        if until - chrono::offset::Utc::now().timestamp() > max_wait() {
+            return Ok(response);
+        }
+        wait_until(RATE_LIMITS.paused_until(self.account.as_str())).await;
+        let response = self.inner.send_http(retry).await?;
+        self.observe(&response);
+        Ok(response)
+    }
+}
+
+impl XrpcClient for RateLimitedClient {
+    fn base_uri(&self) -> String {
+        self.inner.base_uri()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(values: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_throttle_on_429() {
+        let now = 1_000;
+        let reset = headers(&[("ratelimit-remaining", "0"), ("ratelimit-reset", "1030")]);
+        assert_eq!(
+            throttle_for(StatusCode::TOO_MANY_REQUESTS, &reset, now),
+            Some(Throttle::Instance(1_030))
+        );
+        let retry_after = headers(&[("retry-after", "5")]);
+        assert_eq!(
+            throttle_for(StatusCode::TOO_MANY_REQUESTS, &retry_after, now),
+            Some(Throttle::Instance(1_005))
+        );
+        assert_eq!(
+            throttle_for(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), now),
+            Some(Throttle::Instance(now + DEFAULT_PAUSE))
+        );
+    }
+
+    #[test]
+    fn test_throttle_on_exhausted_quota() {
+        let now = 1_000;
+        let exhausted = headers(&[("ratelimit-remaining", "0"), ("ratelimit-reset", "1200")]);
+        assert_eq!(
+            throttle_for(StatusCode::OK, &exhausted, now),
+            Some(Throttle::Account(1_200))
+        );
+        let remaining = headers(&[("ratelimit-remaining", "12"), ("ratelimit-reset", "1200")]);
+        assert_eq!(throttle_for(StatusCode::OK, &remaining, now), None);
+        assert_eq!(throttle_for(StatusCode::OK, &HeaderMap::new(), now), None);
+    }
+
+    #[test]
+    fn test_pauses() {
+        let limits = RateLimits::default();
+        let far_future = chrono::offset::Utc::now().timestamp() + 3_600;
+        limits.pause_account("did:plc:a", far_future);
+        assert_eq!(limits.paused_until("did:plc:a"), far_future);
+        assert_eq!(limits.paused_until("did:plc:b"), 0);
+
+        limits.pause_instance(far_future + 10);
+        limits.pause_instance(far_future - 10);
+        assert_eq!(limits.paused_until("did:plc:b"), far_future + 10);
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{BTreeSet, HashSet};
+use std::env;
+
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use utoipa::ToSchema;
+
+use crate::agent::{get_agent_for_profile, get_muted_actors, get_muted_words};
+use crate::error::AppError;
+use crate::event::{EntryKind, Event, EventAction, EventSource};
+use crate::helper::{
+    establish_connection, fetch_profile_v1, fetch_timed_mute_words, fetch_timed_mute_words_v1,
+    fetch_timed_mutes, fetch_timed_mutes_v1, mark_timed_mute_words_drifted,
+    mark_timed_mutes_drifted, normalize_word,
+};
+use crate::models::{TimedMute, TimedMuteWord};
+use crate::resolver::RESOLVER_LOCK;
+use crate::tmute::get_user_id;
+use crate::{DBPool, APPLICATION_JSON};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Differences between a user's active timed mutes and their Bluesky state.
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct ReconcileReport {
+    /// Timed mutes whose mute was removed outside this service. They are now status 8.
+    pub mutes_removed_outside: Vec<TimedMute>,
+    /// Timed mute words removed from the preference outside this service. They are now status 8.
+    pub words_removed_outside: Vec<TimedMuteWord>,
+    /// Accounts muted on Bluesky that have no active timed mute.
+    pub untracked_mutes: Vec<String>,
+    /// Muted words in the preference that have no active timed mute word.
+    pub untracked_words: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/reconcile",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Drift between timed mutes and Bluesky", body = ReconcileReport),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn reconcile(session: Session, State(pool): State<DBPool>) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let report = reconcile_actor(&mut conn, user_id.as_str()).await?;
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(report),
+    )
+        .into_response())
+}
+
+/// Reconciles every user that has an active timed mute or mute word.
+pub async fn reconcile_timed_mutes() {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL");
+    let mut conn = establish_connection(database_url.as_str());
+
+    let actors: BTreeSet<String> = fetch_timed_mutes_v1(&mut conn)
+        .into_iter()
+        .map(|m| m.actor)
+        .chain(
+            fetch_timed_mute_words_v1(&mut conn)
+                .into_iter()
+                .map(|w| w.actor),
+        )
+        .collect();
+    for actor in actors {
+        // A failed run is logged against the account itself; its rows are
+        // checked again on the next run.
+        let reconciled = reconcile_actor(&mut conn, actor.as_str()).await;
+        if reconciled.is_err() {
+            Event::new(
+                actor.as_str(),
+                EventSource::Reconciliation,
+                EventAction::Drift,
+                EntryKind::Mute,
+                actor.as_str(),
+            )
+            .bsky(Some(&reconciled))
+            .record(&mut conn);
+        }
+    }
+}
+
+/// Compares the user's active rows with `getMutes` and the muted words
+/// preference, and marks rows whose mute was removed outside this service.
+/// Rows that are already due are left to the expiry resolver. Bluesky is read
+/// without `RESOLVER_LOCK`; it is only taken to mark the rows, which are
+/// checked again first so one the resolver claimed meanwhile is left alone.
+async fn reconcile_actor(conn: &mut SqliteConnection, actor: &str) -> Result<ReconcileReport> {
+    let profile_list = fetch_profile_v1(conn, actor);
+    let profile = profile_list.first().ok_or(AppError::NotFound)?;
+    let agent = get_agent_for_profile(profile).await?;
+    let now = chrono::offset::Utc::now().timestamp();
+
+    let mutes: Vec<TimedMute> = fetch_timed_mutes(conn, actor)
+        .into_iter()
+        .filter(|m| m.expiration_date >= now)
+        .collect();
+    let (removed_mutes, untracked_mutes) = find_drift(
+        mutes.iter().map(|m| m.muted_actor.clone()),
+        get_muted_actors(&agent).await?,
+    );
+
+    let words: Vec<TimedMuteWord> = fetch_timed_mute_words(conn, actor)
+        .into_iter()
+        .filter(|w| w.expiration_date >= now)
+        .collect();
+    // Bluesky matches muted words case-insensitively.
+    let (removed_words, untracked_words) = find_drift(
+        words.iter().map(|w| normalize_word(&w.muted_word)),
+        get_muted_words(&agent)
+            .await?
+            .iter()
+            .map(|w| normalize_word(w)),
+    );
+
+    let _guard = RESOLVER_LOCK.lock().await;
+    let active_mutes: HashSet<i32> = fetch_timed_mutes(conn, actor)
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    let active_words: HashSet<i32> = fetch_timed_mute_words(conn, actor)
+        .into_iter()
+        .map(|w| w.id)
+        .collect();
+    let mutes_removed_outside: Vec<TimedMute> = mutes
+        .into_iter()
+        .filter(|m| removed_mutes.contains(&m.muted_actor) && active_mutes.contains(&m.id))
+        .collect();
+    let words_removed_outside: Vec<TimedMuteWord> = words
+        .into_iter()
+        .filter(|w| {
+            removed_words.contains(&normalize_word(&w.muted_word)) && active_words.contains(&w.id)
+        })
+        .collect();
+
+    mark_timed_mutes_drifted(
+        conn,
+        actor,
+        mutes_removed_outside.iter().map(|m| m.id).collect(),
+        &now,
+    )?;
+    mark_timed_mute_words_drifted(
+        conn,
+        actor,
+        words_removed_outside.iter().map(|w| w.id).collect(),
+        &now,
+    )?;
+
+    for mute in &mutes_removed_outside {
+        Event::new(
+            actor,
+            EventSource::Reconciliation,
+            EventAction::Drift,
+            EntryKind::Mute,
+            mute.muted_actor.as_str(),
+        )
+        .entry(mute.id)
+        .record(conn);
+    }
+    for word in &words_removed_outside {
+        Event::new(
+            actor,
+            EventSource::Reconciliation,
+            EventAction::Drift,
+            EntryKind::Word,
+            word.muted_word.as_str(),
+        )
+        .entry(word.id)
+        .record(conn);
+    }
+
+    Ok(ReconcileReport {
+        mutes_removed_outside,
+        words_removed_outside,
+        untracked_mutes,
+        untracked_words,
+    })
+}
+
+/// Returns the tracked values missing from `remote`, and the remote values
+/// that are not tracked, both sorted.
+fn find_drift(
+    tracked: impl IntoIterator<Item = String>,
+    remote: impl IntoIterator<Item = String>,
+) -> (BTreeSet<String>, Vec<String>) {
+    let tracked: HashSet<String> = tracked.into_iter().collect();
+    let remote: BTreeSet<String> = remote.into_iter().collect();
+    let removed = tracked
+        .iter()
+        .filter(|value| !remote.contains(*value))
+        .cloned()
+        .collect();
+    let untracked = remote
+        .into_iter()
+        .filter(|value| !tracked.contains(value))
+        .collect();
+    (removed, untracked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_find_drift() {
+        let (removed, untracked) = find_drift(
+            strings(&["did:plc:a", "did:plc:b"]),
+            strings(&["did:plc:b", "did:plc:d", "did:plc:c"]),
+        );
+        assert_eq!(
+            removed.into_iter().collect::<Vec<_>>(),
+            strings(&["did:plc:a"])
+        );
+        assert_eq!(untracked, strings(&["did:plc:c", "did:plc:d"]));
+    }
+
+    #[test]
+    fn test_find_drift_in_sync() {
+        let (removed, untracked) = find_drift(strings(&["spoiler"]), strings(&["spoiler"]));
+        assert!(removed.is_empty());
+        assert!(untracked.is_empty());
+    }
+}
@@ -0,0 +1,456 @@
+use std::env;
+
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use bsky_sdk::api::types::string::{AtIdentifier, Did};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use croner::parser::{CronParser, Seconds, Year};
+use croner::Cron;
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use utoipa::ToSchema;
+
+use crate::agent::{get_agent_for_actor, get_profiles, Agent};
+use crate::bulk::find_profile;
+use crate::error::AppError;
+use crate::event::{EntryKind, Event, EventAction, EventSource};
+use crate::expiration::ExpirationLength;
+use crate::extract::Json;
+use crate::helper::{
+    claim_recurring_mute_retry, create_recurring_mute, delete_recurring_mute, establish_connection,
+    fetch_due_recurring_mutes, fetch_recurring_mute_retries, fetch_recurring_mutes,
+    postpone_recurring_mute, record_recurring_mute_error, update_recurring_mute_run,
+};
+use crate::identifier::parse_actor;
+use crate::models::{NewRecurringMute, RecurringMute};
+use crate::resolver::{max_attempts, retry_delay};
+use crate::tmute::{
+    apply_timed_mute, apply_timed_mute_word, get_user_id, mute_word_targets, MuteWordActorTarget,
+    OnConflict,
+};
+use crate::{DBPool, APPLICATION_JSON};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Most recurring mutes one user may have.
+const MAX_RULES: usize = 50;
+/// Seconds until a rule whose schedule can't be computed is tried again.
+const INVALID_RULE_RETRY_DELAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateRecurringMuteRequest {
+    /// Handle, DID or `bsky.app` profile link of the account to mute. Set
+    /// either this or `word`.
+    #[serde(default)]
+    pub actor: Option<String>,
+    /// Word to mute, in tags and post text, from everyone.
+    #[serde(default)]
+    pub word: Option<String>,
+    /// Five-field cron expression (minute, hour, day of month, month, day of
+    /// week) for when each occurrence starts, e.g. `0 9 * * MON-FRI`.
+    pub cron: String,
+    /// How long each occurrence lasts.
+    pub duration: ExpirationLength,
+    /// IANA timezone the cron expression is read in, e.g. `Europe/Berlin`.
+    pub timezone: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeleteRecurringMuteRequest {
+    pub id: i32,
+}
+
+fn parse_cron(cron: &str) -> Result<Cron> {
+    CronParser::builder()
+        .seconds(Seconds::Disallowed)
+        .year(Year::Disallowed)
+        .build()
+        .parse(cron)
+        .map_err(|e| AppError::BadRequest(format!("Invalid cron expression {}: {}", cron, e)))
+}
+
+fn parse_timezone(timezone: &str) -> Result<Tz> {
+    timezone
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("Unknown timezone: {}", timezone)))
+}
+
+/// Returns the start of the first occurrence after `after`.
+fn next_occurrence(cron: &Cron, timezone: Tz, after: i64) -> Result<i64> {
+    let after = DateTime::from_timestamp(after, 0)
+        .ok_or(AppError::InternalError)?
+        .with_timezone(&timezone);
+    cron.find_next_occurrence(&after, false)
+        .map(|next| next.timestamp())
+        .map_err(|e| AppError::BadRequest(format!("Cron expression never matches: {}", e)))
+}
+
+/// Returns the start of the first occurrence to materialize for a new rule:
+/// the one in progress at `now`, if any, so a work hours mute created at noon
+/// starts right away, or else the next one.
+fn first_occurrence(cron: &Cron, timezone: Tz, duration: i64, now: i64) -> Result<i64> {
+    let running = DateTime::from_timestamp(now, 0)
+        .map(|now| now.with_timezone(&timezone))
+        .and_then(|now| cron.find_previous_occurrence(&now, true).ok())
+        .map(|previous| previous.timestamp())
+        .filter(|previous| previous + duration > now);
+    match running {
+        Some(previous) => Ok(previous),
+        None => next_occurrence(cron, timezone, now),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/recurring-mutes",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="The user's recurring mutes", body = Vec<RecurringMute>),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_recurring(session: Session, State(pool): State<DBPool>) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let rules = fetch_recurring_mutes(&mut conn, user_id.as_str());
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(rules),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/recurring-mute",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Recurring mute saved", body = RecurringMute),
+        (status=400, description="Invalid account, word, cron expression, duration or timezone, or too many recurring mutes"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn create_recurring(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<CreateRecurringMuteRequest>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let cron = parse_cron(req.cron.trim())?;
+    let timezone = parse_timezone(req.timezone.trim())?;
+    let duration = req.duration.to_seconds()?;
+    if fetch_recurring_mutes(&mut conn, user_id.as_str()).len() >= MAX_RULES {
+        return Err(AppError::BadRequest(format!(
+            "A user has at most {} recurring mutes",
+            MAX_RULES
+        )));
+    }
+    let (kind, target) = match (&req.actor, req.word.as_deref().map(str::trim)) {
+        (Some(actor), None) => {
+            let parsed = parse_actor(actor)?;
+            let agent = get_agent_for_actor(&mut conn, user_id.as_str()).await?;
+            let profiles = get_profiles(&agent, vec![parsed.clone()]).await?;
+            let profile = find_profile(&profiles, &parsed)
+                .ok_or_else(|| AppError::BadRequest(format!("Profile not found: {}", actor)))?;
+            (EntryKind::Mute, profile.did.to_string())
+        }
+        (None, Some(word)) if !word.is_empty() => (EntryKind::Word, word.to_string()),
+        _ => {
+            return Err(AppError::BadRequest(
+                "Set either actor or a non-empty word".to_string(),
+            ))
+        }
+    };
+
+    let create_time = chrono::offset::Utc::now().timestamp();
+    let next_run_date = first_occurrence(&cron, timezone, duration, create_time)?;
+    let rule = create_recurring_mute(
+        &mut conn,
+        &NewRecurringMute {
+            actor: user_id.as_str(),
+            kind: kind.as_str(),
+            target: target.as_str(),
+            cron: req.cron.trim(),
+            timezone: timezone.name(),
+            duration: &duration,
+            next_run_date: &next_run_date,
+            created_date: &create_time,
+        },
+    )?;
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(rule),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/deleteRecurringMute",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Recurring mute deleted; an occurrence in progress still expires as planned"),
+        (status=401, description="Unauthorized"),
+        (status=404, description="No such recurring mute"),
+    ),
+)]
+pub async fn delete_recurring(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<DeleteRecurringMuteRequest>,
+) -> Result<Response> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    if !delete_recurring_mute(&mut conn, user_id.as_str(), &req.id)? {
+        return Err(AppError::NotFound);
+    }
+    Ok((StatusCode::OK, [(CONTENT_TYPE, APPLICATION_JSON)]).into_response())
+}
+
+/// Creates the timed mute or timed mute word for the rule's occurrence
+/// starting at `run_date`, lasting until the occurrence ends. An occurrence
+/// that ended while the service was down is skipped.
+async fn materialize(
+    conn: &mut SqliteConnection,
+    agent: &Agent,
+    rule: &RecurringMute,
+    run_date: i64,
+    now: i64,
+) -> Result<()> {
+    let expire_time = run_date + rule.duration;
+    if expire_time <= now {
+        return Ok(());
+    }
+    if rule.kind == EntryKind::Word.as_str() {
+        apply_timed_mute_word(
+            conn,
+            agent,
+            rule.actor.as_str(),
+            EventSource::Scheduler,
+            rule.target.as_str(),
+            &mute_word_targets(None)?,
+            MuteWordActorTarget::All,
+            now,
+            expire_time,
+            OnConflict::Extend,
+        )
+        .await?;
+    } else {
+        let did: Did = rule.target.parse().map_err(|_| AppError::InternalError)?;
+        let profiles = get_profiles(agent, vec![AtIdentifier::Did(did)]).await?;
+        let profile = profiles
+            .first()
+            .ok_or_else(|| AppError::BadRequest(format!("Profile not found: {}", rule.target)))?;
+        apply_timed_mute(
+            conn,
+            agent,
+            rule.actor.as_str(),
+            EventSource::Scheduler,
+            profile,
+            now,
+            expire_time,
+            OnConflict::Extend,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Computes the start of the rule's occurrence after `after`.
+fn next_run_date(rule: &RecurringMute, after: i64) -> Result<i64> {
+    let cron = parse_cron(rule.cron.as_str())?;
+    next_occurrence(&cron, parse_timezone(rule.timezone.as_str())?, after)
+}
+
+/// Materializes the due occurrence of every user's recurring mutes and moves
+/// each rule to its next occurrence. Occurrences missed while the service was
+/// down are not caught up on. Each rule is moved before its occurrence is
+/// materialized, so overlapping runs never materialize it twice. An
+/// occurrence that fails is retried with the resolver's backoff until it
+/// ends, runs out of attempts or the next occurrence starts. A rule whose
+/// cron expression or timezone no longer parses is retried a day later.
+pub async fn materialize_recurring_mutes() {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL");
+    let mut conn = establish_connection(database_url.as_str());
+    let now = chrono::offset::Utc::now().timestamp();
+
+    // Each due rule with the start of its occurrence and the attempts made
+    // on it before.
+    let mut due: Vec<(RecurringMute, i64, i32)> = Vec::new();
+    for rule in fetch_due_recurring_mutes(&mut conn, now) {
+        let claimed = match next_run_date(&rule, now) {
+            Ok(next_run_date) => {
+                update_recurring_mute_run(&mut conn, &rule.id, &next_run_date, &rule.next_run_date)
+            }
+            Err(e) => postpone_recurring_mute(
+                &mut conn,
+                &rule.id,
+                &rule.next_run_date,
+                &(now + INVALID_RULE_RETRY_DELAY),
+                e.to_string().as_str(),
+            )
+            .map(|_| false),
+        };
+        if claimed.unwrap_or_default() {
+            let run_date = rule.next_run_date;
+            due.push((rule, run_date, 0));
+        }
+    }
+    // Rules moved on above no longer have a retry pending.
+    for rule in fetch_recurring_mute_retries(&mut conn, now) {
+        let (Some(run_date), Some(next_attempt_date)) =
+            (rule.last_run_date, rule.next_attempt_date)
+        else {
+            continue;
+        };
+        if claim_recurring_mute_retry(&mut conn, &rule.id, &run_date, &next_attempt_date)
+            .unwrap_or_default()
+        {
+            let attempt_count = rule.attempt_count;
+            due.push((rule, run_date, attempt_count));
+        }
+    }
+    due.sort_by(|(a, ..), (b, ..)| (a.actor.as_str(), a.id).cmp(&(b.actor.as_str(), b.id)));
+
+    let max_attempts = max_attempts();
+    // Each user signs in once.
+    for rules in due.chunk_by(|(a, ..), (b, ..)| a.actor == b.actor) {
+        let agent = get_agent_for_actor(&mut conn, rules[0].0.actor.as_str()).await;
+        for (rule, run_date, attempt_count) in rules {
+            let result = match &agent {
+                Ok(agent) => materialize(&mut conn, agent, rule, *run_date, now).await,
+                Err(e) => Err(AppError::BskyError(e.to_string())),
+            };
+            let Err(e) = result else {
+                continue;
+            };
+            let attempt_count = attempt_count + 1;
+            let next_attempt_date =
+                (attempt_count < max_attempts).then(|| now + retry_delay(attempt_count));
+            let recorded = record_recurring_mute_error(
+                &mut conn,
+                &rule.id,
+                run_date,
+                e.to_string().as_str(),
+                &attempt_count,
+                next_attempt_date.as_ref(),
+            );
+            if recorded.is_err() {
+                // The occurrence can't be retried; leave a trace of why.
+                Event::new(
+                    rule.actor.as_str(),
+                    EventSource::Scheduler,
+                    EventAction::Create,
+                    entry_kind(rule),
+                    rule.target.as_str(),
+                )
+                .bsky(Some(&recorded))
+                .record(&mut conn);
+            }
+        }
+    }
+}
+
+fn entry_kind(rule: &RecurringMute) -> EntryKind {
+    if rule.kind == EntryKind::Word.as_str() {
+        EntryKind::Word
+    } else {
+        EntryKind::Mute
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(text: &str) -> i64 {
+        DateTime::parse_from_rfc3339(text).unwrap().timestamp()
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert!(parse_cron("0 9 * * MON-FRI").is_ok());
+        assert!(parse_cron("30 18 * * SUN").is_ok());
+        for cron in ["", "0 0 9 * * MON-FRI", "0 25 * * *", "every day"] {
+            assert!(parse_cron(cron).is_err(), "{}", cron);
+        }
+        assert!(parse_timezone("Europe/Berlin").is_ok());
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let weekdays = parse_cron("0 9 * * MON-FRI").unwrap();
+        let berlin = parse_timezone("Europe/Berlin").unwrap();
+        // Friday evening moves to Monday morning, in Berlin summer time.
+        assert_eq!(
+            next_occurrence(&weekdays, berlin, timestamp("2026-07-03T18:00:00Z")).unwrap(),
+            timestamp("2026-07-06T07:00:00Z")
+        );
+        // An occurrence starting at `after` is not the next one.
+        assert_eq!(
+            next_occurrence(&weekdays, berlin, timestamp("2026-07-06T07:00:00Z")).unwrap(),
+            timestamp("2026-07-07T07:00:00Z")
+        );
+        // Winter time.
+        assert_eq!(
+            next_occurrence(&weekdays, berlin, timestamp("2026-12-01T12:00:00Z")).unwrap(),
+            timestamp("2026-12-02T08:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_first_occurrence() {
+        let weekdays = parse_cron("0 9 * * MON-FRI").unwrap();
+        let utc = parse_timezone("UTC").unwrap();
+        let eight_hours = 8 * 60 * 60;
+        // During work hours the running occurrence is materialized first.
+        assert_eq!(
+            first_occurrence(
+                &weekdays,
+                utc,
+                eight_hours,
+                timestamp("2026-07-06T12:00:00Z")
+            )
+            .unwrap(),
+            timestamp("2026-07-06T09:00:00Z")
+        );
+        assert_eq!(
+            first_occurrence(
+                &weekdays,
+                utc,
+                eight_hours,
+                timestamp("2026-07-06T09:00:00Z")
+            )
+            .unwrap(),
+            timestamp("2026-07-06T09:00:00Z")
+        );
+        // After work hours it waits for the next one.
+        assert_eq!(
+            first_occurrence(
+                &weekdays,
+                utc,
+                eight_hours,
+                timestamp("2026-07-06T17:00:00Z")
+            )
+            .unwrap(),
+            timestamp("2026-07-07T09:00:00Z")
+        );
+    }
+}
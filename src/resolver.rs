@@ -0,0 +1,421 @@
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+
+use diesel::SqliteConnection;
+use futures::stream::{self, StreamExt};
+
+use crate::agent::{
+    get_agent_for_profile, remove_mute_word_from_pref, unblock_actor, unmute_actor, unmute_thread,
+    Agent,
+};
+use crate::error::AppError;
+use crate::event::{EntryKind, Event, EventAction, EventSource};
+use crate::helper::{
+    establish_connection, fetch_profile_v1, fetch_timed_blocks_v1, fetch_timed_list_mutes_v1,
+    fetch_timed_mute_words_v1, fetch_timed_mutes_v1, fetch_timed_thread_mutes_v1,
+    record_timed_block_failure_v1, record_timed_list_mute_failure_v1, record_timed_mute_failure_v1,
+    record_timed_mute_word_failure_v1, record_timed_thread_mute_failure_v1, update_timed_block_v1,
+    update_timed_list_mute_v1, update_timed_mute_v1, update_timed_mute_word_v1,
+    update_timed_thread_mute_v1,
+};
+use crate::models::{
+    Profile, TimedBlock, TimedListMute, TimedMute, TimedMuteWord, TimedThreadMute,
+};
+use crate::scheduler::reload_expiries;
+use crate::status::MuteStatus;
+use crate::tmute::undo_list_mute;
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Serializes resolver runs, which can be started by the cron sweep, the
+/// expiry scheduler and `/trigger` at the same time. Reconciliation takes it
+/// while it marks rows, never across its Bluesky calls, so it never marks a
+/// row the resolver is halfway through.
+pub(crate) static RESOLVER_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+const DEFAULT_CONCURRENCY: usize = 8;
+const RETRY_BASE_DELAY: i64 = 60;
+const RETRY_MAX_DELAY: i64 = 6 * 60 * 60;
+
+/// An unresolved row whose expiration has passed and which still has to be undone
+/// on Bluesky.
+enum ExpiredRow {
+    Mute(TimedMute),
+    Word(TimedMuteWord),
+    Block(TimedBlock),
+    ThreadMute(TimedThreadMute),
+    ListMute(TimedListMute),
+}
+
+impl ExpiredRow {
+    fn actor(&self) -> &str {
+        match self {
+            ExpiredRow::Mute(row) => row.actor.as_str(),
+            ExpiredRow::Word(row) => row.actor.as_str(),
+            ExpiredRow::Block(row) => row.actor.as_str(),
+            ExpiredRow::ThreadMute(row) => row.actor.as_str(),
+            ExpiredRow::ListMute(row) => row.actor.as_str(),
+        }
+    }
+
+    fn id(&self) -> i32 {
+        match self {
+            ExpiredRow::Mute(row) => row.id,
+            ExpiredRow::Word(row) => row.id,
+            ExpiredRow::Block(row) => row.id,
+            ExpiredRow::ThreadMute(row) => row.id,
+            ExpiredRow::ListMute(row) => row.id,
+        }
+    }
+
+    fn kind(&self) -> EntryKind {
+        match self {
+            ExpiredRow::Mute(_) => EntryKind::Mute,
+            ExpiredRow::Word(_) => EntryKind::Word,
+            ExpiredRow::Block(_) => EntryKind::Block,
+            ExpiredRow::ThreadMute(_) => EntryKind::ThreadMute,
+            ExpiredRow::ListMute(_) => EntryKind::ListMute,
+        }
+    }
+
+    /// What the row mutes or blocks, as recorded in the audit log.
+    fn target(&self) -> &str {
+        match self {
+            ExpiredRow::Mute(row) => row.muted_actor.as_str(),
+            ExpiredRow::Word(row) => row.muted_word.as_str(),
+            ExpiredRow::Block(row) => row.blocked_actor.as_str(),
+            ExpiredRow::ThreadMute(row) => row.thread_uri.as_str(),
+            ExpiredRow::ListMute(row) => row.list_uri.as_str(),
+        }
+    }
+
+    /// Whether undoing the row calls Bluesky at all.
+    fn calls_bsky(&self) -> bool {
+        !matches!(self, ExpiredRow::Mute(row) if row.was_muted_before)
+    }
+
+    fn attempt_count(&self) -> i32 {
+        match self {
+            ExpiredRow::Mute(row) => row.attempt_count,
+            ExpiredRow::Word(row) => row.attempt_count,
+            ExpiredRow::Block(row) => row.attempt_count,
+            ExpiredRow::ThreadMute(row) => row.attempt_count,
+            ExpiredRow::ListMute(row) => row.attempt_count,
+        }
+    }
+
+    async fn undo(&self, agent: &Agent) -> Result<()> {
+        match self {
+            // The user muted the account before the timed mute; keep it.
+            ExpiredRow::Mute(row) if row.was_muted_before => Ok(()),
+            ExpiredRow::Mute(row) => unmute_actor(agent, row.muted_actor.as_str()).await,
+            ExpiredRow::Word(row) => {
+                remove_mute_word_from_pref(agent, row.muted_word.clone()).await
+            }
+            ExpiredRow::Block(row) => unblock_actor(agent, row.record_uri.as_str()).await,
+            ExpiredRow::ThreadMute(row) => unmute_thread(agent, row.thread_uri.as_str()).await,
+            ExpiredRow::ListMute(row) => undo_list_mute(agent, row).await,
+        }
+    }
+
+    fn status(&self) -> MuteStatus {
+        match self {
+            ExpiredRow::Mute(row) => row.status,
+            ExpiredRow::Word(row) => row.status,
+            ExpiredRow::Block(row) => row.status,
+            ExpiredRow::ThreadMute(row) => row.status,
+            ExpiredRow::ListMute(row) => row.status,
+        }
+    }
+
+    fn set_status(&self, conn: &mut SqliteConnection, status: MuteStatus) -> Result<bool> {
+        match self {
+            ExpiredRow::Mute(row) => update_timed_mute_v1(conn, &row.actor, &row.id, &status),
+            ExpiredRow::Word(row) => update_timed_mute_word_v1(conn, &row.actor, &row.id, &status),
+            ExpiredRow::Block(row) => update_timed_block_v1(conn, &row.actor, &row.id, &status),
+            ExpiredRow::ThreadMute(row) => {
+                update_timed_thread_mute_v1(conn, &row.actor, &row.id, &status)
+            }
+            ExpiredRow::ListMute(row) => {
+                update_timed_list_mute_v1(conn, &row.actor, &row.id, &status)
+            }
+        }
+    }
+
+    /// Marks the row expiring before it is undone, so a user deleting or
+    /// extending it meanwhile is turned away. Rows still expiring from an
+    /// interrupted run are already claimed.
+    fn claim(&self, conn: &mut SqliteConnection) -> bool {
+        self.status() == MuteStatus::Expiring
+            || matches!(self.set_status(conn, MuteStatus::Expiring), Ok(true))
+    }
+
+    fn record_failure(
+        &self,
+        conn: &mut SqliteConnection,
+        error: &str,
+        next_attempt_time: &i64,
+        max_attempts: &i32,
+    ) -> Result<bool> {
+        match self {
+            ExpiredRow::Mute(row) => {
+                record_timed_mute_failure_v1(conn, &row.id, error, next_attempt_time, max_attempts)
+            }
+            ExpiredRow::Word(row) => record_timed_mute_word_failure_v1(
+                conn,
+                &row.id,
+                error,
+                next_attempt_time,
+                max_attempts,
+            ),
+            ExpiredRow::Block(row) => {
+                record_timed_block_failure_v1(conn, &row.id, error, next_attempt_time, max_attempts)
+            }
+            ExpiredRow::ThreadMute(row) => record_timed_thread_mute_failure_v1(
+                conn,
+                &row.id,
+                error,
+                next_attempt_time,
+                max_attempts,
+            ),
+            ExpiredRow::ListMute(row) => record_timed_list_mute_failure_v1(
+                conn,
+                &row.id,
+                error,
+                next_attempt_time,
+                max_attempts,
+            ),
+        }
+    }
+}
+
+/// A row is due once its expiration has passed and any retry backoff is over.
+fn is_due(expiration_date: i64, next_attempt_date: Option<i64>, now: i64) -> bool {
+    now > expiration_date && next_attempt_date.is_none_or(|next| now >= next)
+}
+
+/// Members of a mute group are lifted as a unit: once one of them is due,
+/// the others are too, unless they are waiting out a retry backoff.
+fn is_group_due(
+    group_id: Option<i32>,
+    next_attempt_date: Option<i64>,
+    due_groups: &HashSet<i32>,
+    now: i64,
+) -> bool {
+    group_id.is_some_and(|id| due_groups.contains(&id))
+        && next_attempt_date.is_none_or(|next| now >= next)
+}
+
+/// Seconds to wait before the next attempt after `attempt_count` failures:
+/// one minute, doubling each time, capped at six hours.
+pub(crate) fn retry_delay(attempt_count: i32) -> i64 {
+    let exponent = attempt_count.saturating_sub(1).clamp(0, 20) as u32;
+    RETRY_BASE_DELAY
+        .saturating_mul(1 << exponent)
+        .min(RETRY_MAX_DELAY)
+}
+
+pub(crate) fn max_attempts() -> i32 {
+    env::var("RESOLVER_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|value: &i32| *value > 0)
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+}
+
+fn concurrency() -> usize {
+    env::var("RESOLVER_CONCURRENCY")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|value: &usize| *value > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+}
+
+/// Returns every due row, grouped by the account that owns it.
+fn fetch_due_rows(conn: &mut SqliteConnection, now: i64) -> BTreeMap<String, Vec<ExpiredRow>> {
+    let mutes = fetch_timed_mutes_v1(conn);
+    let words = fetch_timed_mute_words_v1(conn);
+    let due_groups: HashSet<i32> = mutes
+        .iter()
+        .filter(|r| is_due(r.expiration_date, r.next_attempt_date, now))
+        .filter_map(|r| r.group_id)
+        .chain(
+            words
+                .iter()
+                .filter(|r| is_due(r.expiration_date, r.next_attempt_date, now))
+                .filter_map(|r| r.group_id),
+        )
+        .collect();
+
+    let mut rows: Vec<ExpiredRow> = Vec::new();
+    rows.extend(
+        mutes
+            .into_iter()
+            .filter(|r| {
+                is_due(r.expiration_date, r.next_attempt_date, now)
+                    || is_group_due(r.group_id, r.next_attempt_date, &due_groups, now)
+            })
+            .map(ExpiredRow::Mute),
+    );
+    rows.extend(
+        words
+            .into_iter()
+            .filter(|r| {
+                is_due(r.expiration_date, r.next_attempt_date, now)
+                    || is_group_due(r.group_id, r.next_attempt_date, &due_groups, now)
+            })
+            .map(ExpiredRow::Word),
+    );
+    rows.extend(
+        fetch_timed_blocks_v1(conn)
+            .into_iter()
+            .filter(|r| is_due(r.expiration_date, r.next_attempt_date, now))
+            .map(ExpiredRow::Block),
+    );
+    rows.extend(
+        fetch_timed_thread_mutes_v1(conn)
+            .into_iter()
+            .filter(|r| is_due(r.expiration_date, r.next_attempt_date, now))
+            .map(ExpiredRow::ThreadMute),
+    );
+    rows.extend(
+        fetch_timed_list_mutes_v1(conn)
+            .into_iter()
+            .filter(|r| is_due(r.expiration_date, r.next_attempt_date, now))
+            .map(ExpiredRow::ListMute),
+    );
+
+    let mut by_actor: BTreeMap<String, Vec<ExpiredRow>> = BTreeMap::new();
+    for row in rows {
+        by_actor
+            .entry(row.actor().to_string())
+            .or_default()
+            .push(row);
+    }
+    by_actor
+}
+
+/// Signs in as one account and undoes its rows in order. Calls for a single
+/// account stay sequential so its rate limit is spent one request at a time.
+async fn undo_rows(
+    profile: Option<Profile>,
+    rows: Vec<ExpiredRow>,
+) -> Vec<(ExpiredRow, Result<()>)> {
+    let agent = match profile {
+        Some(profile) => get_agent_for_profile(&profile).await,
+        None => Err(AppError::NotFound),
+    };
+    let mut results = Vec::with_capacity(rows.len());
+    for row in rows {
+        let result = match &agent {
+            Ok(agent) => row.undo(agent).await,
+            Err(e) => Err(AppError::BskyError(format!("Could not sign in: {}", e))),
+        };
+        results.push((row, result));
+    }
+    results
+}
+
+/// Undoes every due row on Bluesky. Accounts are processed concurrently, up
+/// to `RESOLVER_CONCURRENCY` at a time. Rows are expiring while this runs and
+/// are marked expired only once Bluesky accepted the change; failures go back
+/// to active and are retried with exponential backoff until
+/// `RESOLVER_MAX_ATTEMPTS`, after which the row is marked failed.
+pub async fn resolve_timed_mutes() {
+    let _guard = RESOLVER_LOCK.lock().await;
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL");
+    let mut conn = establish_connection(database_url.as_str());
+    let current_timestamp = chrono::offset::Utc::now().timestamp();
+    let max_attempts = max_attempts();
+
+    let accounts: Vec<(Option<Profile>, Vec<ExpiredRow>)> =
+        fetch_due_rows(&mut conn, current_timestamp)
+            .into_iter()
+            .map(|(actor, rows)| {
+                let profile = fetch_profile_v1(&mut conn, actor.as_str())
+                    .into_iter()
+                    .next();
+                let rows: Vec<ExpiredRow> = rows
+                    .into_iter()
+                    .filter(|row| row.claim(&mut conn))
+                    .collect();
+                (profile, rows)
+            })
+            .collect();
+    let mut outcomes = stream::iter(accounts)
+        .map(|(profile, rows)| undo_rows(profile, rows))
+        .buffer_unordered(concurrency());
+
+    while let Some(results) = outcomes.next().await {
+        for (row, result) in results {
+            let updated = match &result {
+                Ok(()) => row.set_status(&mut conn, MuteStatus::Expired),
+                Err(e) => {
+                    let next_attempt = current_timestamp + retry_delay(row.attempt_count() + 1);
+                    row.record_failure(
+                        &mut conn,
+                        e.to_string().as_str(),
+                        &next_attempt,
+                        &max_attempts,
+                    )
+                }
+            };
+            // A row whose update failed stays expiring and is picked up again
+            // on the next run; its event carries the error.
+            let outcome = match updated {
+                Ok(true) => result,
+                Ok(false) => result.and(Err(AppError::NotFound)),
+                Err(e) => Err(e),
+            };
+            let action = match outcome {
+                Ok(()) => EventAction::Expire,
+                Err(_) => EventAction::ExpireFailed,
+            };
+            Event::new(
+                row.actor(),
+                EventSource::Scheduler,
+                action,
+                row.kind(),
+                row.target(),
+            )
+            .entry(row.id())
+            .bsky((row.calls_bsky() || outcome.is_err()).then_some(&outcome))
+            .record(&mut conn);
+        }
+    }
+
+    reload_expiries(&mut conn);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_due() {
+        assert!(!is_due(1000, None, 1000));
+        assert!(is_due(1000, None, 1001));
+        assert!(!is_due(1000, Some(1100), 1050));
+        assert!(is_due(1000, Some(1100), 1100));
+    }
+
+    #[test]
+    fn test_is_group_due() {
+        let due_groups = HashSet::from([7]);
+        assert!(is_group_due(Some(7), None, &due_groups, 1000));
+        assert!(!is_group_due(Some(8), None, &due_groups, 1000));
+        assert!(!is_group_due(None, None, &due_groups, 1000));
+        assert!(!is_group_due(Some(7), Some(1100), &due_groups, 1000));
+        assert!(is_group_due(Some(7), Some(1000), &due_groups, 1000));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), 60);
+        assert_eq!(retry_delay(2), 120);
+        assert_eq!(retry_delay(4), 480);
+        assert_eq!(retry_delay(10), RETRY_MAX_DELAY);
+        assert_eq!(retry_delay(i32::MAX), RETRY_MAX_DELAY);
+    }
+}
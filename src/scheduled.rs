@@ -0,0 +1,257 @@
+use std::collections::BTreeSet;
+use std::env;
+
+use bsky_sdk::api::types::string::{AtIdentifier, Did};
+use diesel::SqliteConnection;
+
+use crate::agent::{add_mute_word_to_pref, get_agent_for_actor, get_profiles, mute_actor, Agent};
+use crate::error::AppError;
+use crate::event::{EntryKind, Event, EventAction, EventSource};
+use crate::helper::{
+    establish_connection, extend_timed_mute, extend_timed_mute_word, fetch_active_timed_mute,
+    fetch_active_timed_mute_word, fetch_due_scheduled_timed_mute_words,
+    fetch_due_scheduled_timed_mutes, record_timed_mute_start_failure,
+    record_timed_mute_word_start_failure, start_timed_mute, start_timed_mute_word,
+    update_timed_mute_v1, update_timed_mute_word_v1,
+};
+use crate::models::{TimedMute, TimedMuteWord};
+use crate::resolver::{max_attempts, retry_delay, RESOLVER_LOCK};
+use crate::scheduler::schedule_expiry;
+use crate::status::MuteStatus;
+use crate::tmute::{expiring_conflict, is_muted_by_user};
+
+pub type Result<T> = std::result::Result<T, AppError>;
+
+/// Why a scheduled row whose expiration passed before it was started, for
+/// example while the service was down, is marked failed.
+const ENDED_BEFORE_START: &str = "Ended before it could be started";
+
+/// Attempts allowed for a row that could not be started. One that has
+/// already ended gets none, so it is marked failed at once.
+fn attempts_left(expiration_date: i64, now: i64, max_attempts: i32) -> i32 {
+    if expiration_date <= now {
+        0
+    } else {
+        max_attempts
+    }
+}
+
+/// Mutes the account of a scheduled timed mute on Bluesky, unless the user
+/// already did, and makes the row active. When the account has an active
+/// timed mute already, that one is extended instead and the scheduled row is
+/// superseded, and the extension is recorded if it moved the expiration.
+/// While that mute is being undone the start is refused.
+async fn start_mute(conn: &mut SqliteConnection, agent: &Agent, row: &TimedMute) -> Result<()> {
+    let now = chrono::offset::Utc::now().timestamp();
+    if let Some(existing) = fetch_active_timed_mute(conn, &row.actor, &row.muted_actor) {
+        if existing.status == MuteStatus::Expiring {
+            return Err(expiring_conflict());
+        }
+        let extended = row.expiration_date > existing.expiration_date
+            && extend_timed_mute(conn, &row.actor, &existing.id, &row.expiration_date, &now)?;
+        update_timed_mute_v1(conn, &row.actor, &row.id, &MuteStatus::Superseded)?;
+        if extended {
+            schedule_expiry(row.expiration_date);
+            Event::new(
+                row.actor.as_str(),
+                EventSource::Scheduler,
+                EventAction::Extend,
+                EntryKind::Mute,
+                row.muted_actor.as_str(),
+            )
+            .entry(existing.id)
+            .record(conn);
+        }
+        return Ok(());
+    }
+
+    let did: Did = row
+        .muted_actor
+        .parse()
+        .map_err(|_| AppError::InternalError)?;
+    let profiles = get_profiles(agent, vec![AtIdentifier::Did(did)]).await?;
+    let profile = profiles
+        .first()
+        .ok_or_else(|| AppError::BadRequest(format!("Profile not found: {}", row.muted_actor)))?;
+    let muted_already = is_muted_by_user(profile);
+    let muted = if muted_already {
+        None
+    } else {
+        Some(mute_actor(agent, row.muted_actor.as_str()).await)
+    };
+    let event = Event::new(
+        row.actor.as_str(),
+        EventSource::Scheduler,
+        EventAction::Start,
+        EntryKind::Mute,
+        row.muted_actor.as_str(),
+    )
+    .entry(row.id)
+    .bsky(muted.as_ref());
+    if let Some(Err(e)) = muted {
+        return Err(e);
+    }
+    start_timed_mute(conn, &row.id, &muted_already)?;
+    schedule_expiry(row.expiration_date);
+    event.record(conn);
+    Ok(())
+}
+
+/// Adds the word of a scheduled timed mute word to the user's muted words
+/// and makes the row active. When the word has an active timed mute word
+/// already, that one is extended instead and the scheduled row is superseded,
+/// and the extension is recorded if it moved the expiration. While that word
+/// is being removed the start is refused.
+async fn start_word(conn: &mut SqliteConnection, agent: &Agent, row: &TimedMuteWord) -> Result<()> {
+    let now = chrono::offset::Utc::now().timestamp();
+    if let Some(existing) = fetch_active_timed_mute_word(conn, &row.actor, &row.muted_word) {
+        if existing.status == MuteStatus::Expiring {
+            return Err(expiring_conflict());
+        }
+        let extended = row.expiration_date > existing.expiration_date
+            && extend_timed_mute_word(conn, &row.actor, &existing.id, &row.expiration_date, &now)?;
+        update_timed_mute_word_v1(conn, &row.actor, &row.id, &MuteStatus::Superseded)?;
+        if extended {
+            schedule_expiry(row.expiration_date);
+            Event::new(
+                row.actor.as_str(),
+                EventSource::Scheduler,
+                EventAction::Extend,
+                EntryKind::Word,
+                row.muted_word.as_str(),
+            )
+            .entry(existing.id)
+            .record(conn);
+        }
+        return Ok(());
+    }
+
+    let added = add_mute_word_to_pref(
+        agent,
+        row.muted_word.clone(),
+        row.targets.split(',').map(str::to_string).collect(),
+        row.actor_target.as_str(),
+    )
+    .await;
+    let event = Event::new(
+        row.actor.as_str(),
+        EventSource::Scheduler,
+        EventAction::Start,
+        EntryKind::Word,
+        row.muted_word.as_str(),
+    )
+    .entry(row.id)
+    .bsky(Some(&added));
+    added?;
+    start_timed_mute_word(conn, &row.id)?;
+    schedule_expiry(row.expiration_date);
+    event.record(conn);
+    Ok(())
+}
+
+/// Writes the start event of a row that could not be started. When storing
+/// the failure on the row failed too, the event says so and that error is
+/// returned.
+fn record_start_failure(
+    conn: &mut SqliteConnection,
+    event: Event,
+    error: String,
+    recorded: Result<bool>,
+) -> Result<()> {
+    let logged = match &recorded {
+        Ok(_) => error,
+        Err(e) => format!("{}; recording the failure failed: {}", error, e),
+    };
+    event.bsky(Some(&Err::<(), _>(logged))).record(conn);
+    recorded.map(|_| ())
+}
+
+/// Puts every scheduled timed mute and timed mute word whose start date has
+/// come in effect on Bluesky, signing in once per user. Rows that cannot be
+/// started keep the error in `last_error` and are retried with the resolver's
+/// backoff until `RESOLVER_MAX_ATTEMPTS`, after which they are marked failed.
+/// A row whose expiration passed first is marked failed right away. Every
+/// failed start is logged; the first failure that could not be stored on its
+/// row is returned once all rows were tried.
+pub async fn start_scheduled_entries() -> Result<()> {
+    let _guard = RESOLVER_LOCK.lock().await;
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL");
+    let mut conn = establish_connection(database_url.as_str());
+    let now = chrono::offset::Utc::now().timestamp();
+    let max_attempts = max_attempts();
+    let mut unrecorded = None;
+
+    let mutes = fetch_due_scheduled_timed_mutes(&mut conn, now);
+    let words = fetch_due_scheduled_timed_mute_words(&mut conn, now);
+    let actors: BTreeSet<&str> = mutes
+        .iter()
+        .map(|m| m.actor.as_str())
+        .chain(words.iter().map(|w| w.actor.as_str()))
+        .collect();
+    for actor in actors {
+        let agent = get_agent_for_actor(&mut conn, actor).await;
+        for row in mutes.iter().filter(|m| m.actor == actor) {
+            let result = match &agent {
+                _ if row.expiration_date <= now => Err(ENDED_BEFORE_START.to_string()),
+                Ok(agent) => start_mute(&mut conn, agent, row)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = result {
+                let next_attempt = now + retry_delay(row.attempt_count + 1);
+                let max_attempts = attempts_left(row.expiration_date, now, max_attempts);
+                let recorded = record_timed_mute_start_failure(
+                    &mut conn,
+                    &row.id,
+                    e.as_str(),
+                    &next_attempt,
+                    &max_attempts,
+                );
+                let event = Event::new(
+                    row.actor.as_str(),
+                    EventSource::Scheduler,
+                    EventAction::Start,
+                    EntryKind::Mute,
+                    row.muted_actor.as_str(),
+                )
+                .entry(row.id);
+                if let Err(e) = record_start_failure(&mut conn, event, e, recorded) {
+                    unrecorded.get_or_insert(e);
+                }
+            }
+        }
+        for row in words.iter().filter(|w| w.actor == actor) {
+            let result = match &agent {
+                _ if row.expiration_date <= now => Err(ENDED_BEFORE_START.to_string()),
+                Ok(agent) => start_word(&mut conn, agent, row)
+                    .await
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            if let Err(e) = result {
+                let next_attempt = now + retry_delay(row.attempt_count + 1);
+                let max_attempts = attempts_left(row.expiration_date, now, max_attempts);
+                let recorded = record_timed_mute_word_start_failure(
+                    &mut conn,
+                    &row.id,
+                    e.as_str(),
+                    &next_attempt,
+                    &max_attempts,
+                );
+                let event = Event::new(
+                    row.actor.as_str(),
+                    EventSource::Scheduler,
+                    EventAction::Start,
+                    EntryKind::Word,
+                    row.muted_word.as_str(),
+                )
+                .entry(row.id);
+                if let Err(e) = record_start_failure(&mut conn, event, e, recorded) {
+                    unrecorded.get_or_insert(e);
+                }
+            }
+        }
+    }
+    unrecorded.map_or(Ok(()), Err)
+}
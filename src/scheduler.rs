@@ -1,10 +1,72 @@
-use crate::tmute::resolve_timed_mutes;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use crate::helper::{
+    establish_connection, fetch_active_expiration_dates, fetch_scheduled_start_dates,
+};
+use crate::reconcile::reconcile_timed_mutes;
+use crate::recurring::materialize_recurring_mutes;
+use crate::resolver::resolve_timed_mutes;
+use crate::scheduled::start_scheduled_entries;
+use diesel::SqliteConnection;
+use tokio::sync::Notify;
 use tokio_cron_scheduler::{Job, JobScheduler};
 
+static EXPIRY_QUEUE: LazyLock<ExpiryQueue> = LazyLock::new(ExpiryQueue::default);
+
+/// Start dates of scheduled rows and expiration dates that still have to be
+/// resolved, earliest first. The expiry task sleeps until the head of the
+/// queue is due and is woken whenever the schedule changes.
+#[derive(Default)]
+struct ExpiryQueue {
+    running: AtomicBool,
+    due: Mutex<BinaryHeap<Reverse<i64>>>,
+    notify: Notify,
+}
+
+impl ExpiryQueue {
+    fn push(&self, expiration_date: i64) {
+        self.due
+            .lock()
+            .expect("Error locking expiry queue")
+            .push(Reverse(expiration_date));
+    }
+
+    fn replace(&self, expiration_dates: Vec<i64>) {
+        *self.due.lock().expect("Error locking expiry queue") =
+            expiration_dates.into_iter().map(Reverse).collect();
+    }
+
+    fn next_due(&self) -> Option<i64> {
+        self.due
+            .lock()
+            .expect("Error locking expiry queue")
+            .peek()
+            .map(|Reverse(date)| *date)
+    }
+
+    /// Removes every entry that is due at `now`, returning how many there were.
+    fn pop_due(&self, now: i64) -> usize {
+        let mut due = self.due.lock().expect("Error locking expiry queue");
+        let mut popped = 0;
+        while matches!(due.peek(), Some(Reverse(date)) if *date < now) {
+            due.pop();
+            popped += 1;
+        }
+        popped
+    }
+}
+
 pub async fn start_scheduler(cron_schedule: &str) {
     let sched = JobScheduler::new().await.expect("Error scheduling job");
     let job = Job::new_async(cron_schedule, |_uuid, _l| {
         Box::pin(async move {
+            // Failed starts are in the event log already.
+            let _ = start_scheduled_entries().await;
             resolve_timed_mutes().await;
         })
     })
@@ -16,3 +78,116 @@ pub async fn start_scheduler(cron_schedule: &str) {
     // Start the scheduler
     sched.start().await.expect("Error starting scheduler");
 }
+
+/// Starts the job that compares active timed mutes with Bluesky and marks the
+/// ones removed outside this service.
+pub async fn start_reconcile_scheduler(cron_schedule: &str) {
+    let sched = JobScheduler::new().await.expect("Error scheduling job");
+    let job = Job::new_async(cron_schedule, |_uuid, _l| {
+        Box::pin(async move {
+            reconcile_timed_mutes().await;
+        })
+    })
+    .unwrap();
+
+    sched.add(job).await.expect("Error adding reconcile job");
+    sched.start().await.expect("Error starting scheduler");
+}
+
+/// Starts the job that turns the due occurrences of recurring mutes into
+/// timed mutes and timed mute words.
+pub async fn start_recurring_scheduler(cron_schedule: &str) {
+    let sched = JobScheduler::new().await.expect("Error scheduling job");
+    let job = Job::new_async(cron_schedule, |_uuid, _l| {
+        Box::pin(async move {
+            materialize_recurring_mutes().await;
+        })
+    })
+    .unwrap();
+
+    sched.add(job).await.expect("Error adding recurring job");
+    sched.start().await.expect("Error starting scheduler");
+}
+
+/// Starts the task that starts scheduled timed mutes as soon as they are due
+/// and resolves timed mutes as soon as they expire. The cron job started by
+/// [`start_scheduler`] is kept as a safety sweep.
+pub fn start_expiry_scheduler() {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL");
+    let mut conn = establish_connection(database_url.as_str());
+    EXPIRY_QUEUE.running.store(true, Ordering::SeqCst);
+    reload_expiries(&mut conn);
+    tokio::spawn(run_expiry_scheduler());
+}
+
+/// Adds an expiration date to the queue and wakes the expiry task.
+pub fn schedule_expiry(expiration_date: i64) {
+    if !EXPIRY_QUEUE.running.load(Ordering::SeqCst) {
+        return;
+    }
+    EXPIRY_QUEUE.push(expiration_date);
+    EXPIRY_QUEUE.notify.notify_one();
+}
+
+/// Adds the start date of a scheduled row to the queue and wakes the expiry
+/// task.
+pub fn schedule_start(start_date: i64) {
+    schedule_expiry(start_date);
+}
+
+/// Rebuilds the queue from the database, dropping dates whose rows were
+/// deleted, and wakes the expiry task.
+pub fn reload_expiries(conn: &mut SqliteConnection) {
+    if !EXPIRY_QUEUE.running.load(Ordering::SeqCst) {
+        return;
+    }
+    let mut dates = fetch_active_expiration_dates(conn);
+    dates.extend(fetch_scheduled_start_dates(conn));
+    EXPIRY_QUEUE.replace(dates);
+    EXPIRY_QUEUE.notify.notify_one();
+}
+
+async fn run_expiry_scheduler() {
+    loop {
+        let Some(next_due) = EXPIRY_QUEUE.next_due() else {
+            EXPIRY_QUEUE.notify.notified().await;
+            continue;
+        };
+        // Rows are resolved once the current time is past their expiration.
+        let wait = next_due + 1 - chrono::offset::Utc::now().timestamp();
+        if wait > 0 {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(wait as u64)) => {}
+                _ = EXPIRY_QUEUE.notify.notified() => continue,
+            }
+        }
+        if EXPIRY_QUEUE.pop_due(chrono::offset::Utc::now().timestamp()) > 0 {
+            // Failed starts are in the event log already.
+            let _ = start_scheduled_entries().await;
+            resolve_timed_mutes().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_queue_order() {
+        let queue = ExpiryQueue::default();
+        assert_eq!(queue.next_due(), None);
+
+        queue.push(3000);
+        queue.push(1000);
+        queue.push(2000);
+        assert_eq!(queue.next_due(), Some(1000));
+
+        assert_eq!(queue.pop_due(2001), 2);
+        assert_eq!(queue.next_due(), Some(3000));
+
+        queue.replace(vec![500]);
+        assert_eq!(queue.next_due(), Some(500));
+        assert_eq!(queue.pop_due(500), 0);
+    }
+}
@@ -10,6 +10,40 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    mute_event (rowid) {
+        rowid -> Integer,
+        actor -> Text,
+        source -> Text,
+        action -> Text,
+        kind -> Text,
+        target -> Text,
+        entry_id -> Nullable<Integer>,
+        result -> Text,
+        error -> Nullable<Text>,
+        created_date -> BigInt,
+    }
+}
+
+diesel::table! {
+    mute_group (rowid) {
+        rowid -> Integer,
+        actor -> Text,
+        name -> Text,
+        created_date -> BigInt,
+        expiration_date -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    mute_group_member (rowid) {
+        rowid -> Integer,
+        group_id -> Integer,
+        kind -> Text,
+        target -> Text,
+    }
+}
+
 diesel::table! {
     profile (rowid) {
         rowid -> Integer,
@@ -31,6 +65,55 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    recurring_mute (rowid) {
+        rowid -> Integer,
+        actor -> Text,
+        kind -> Text,
+        target -> Text,
+        cron -> Text,
+        timezone -> Text,
+        duration -> BigInt,
+        next_run_date -> BigInt,
+        last_run_date -> Nullable<BigInt>,
+        last_error -> Nullable<Text>,
+        created_date -> BigInt,
+        attempt_count -> Integer,
+        next_attempt_date -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    timed_block (rowid) {
+        rowid -> Integer,
+        actor -> Text,
+        blocked_actor -> Text,
+        record_uri -> Text,
+        created_date -> BigInt,
+        expiration_date -> BigInt,
+        status -> Integer,
+        attempt_count -> Integer,
+        last_error -> Nullable<Text>,
+        next_attempt_date -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    timed_list_mute (rowid) {
+        rowid -> Integer,
+        actor -> Text,
+        list_uri -> Text,
+        mode -> Text,
+        record_uri -> Nullable<Text>,
+        created_date -> BigInt,
+        expiration_date -> BigInt,
+        status -> Integer,
+        attempt_count -> Integer,
+        last_error -> Nullable<Text>,
+        next_attempt_date -> Nullable<BigInt>,
+    }
+}
+
 diesel::table! {
     timed_mute (rowid) {
         rowid -> Integer,
@@ -39,6 +122,17 @@ diesel::table! {
         created_date -> BigInt,
         expiration_date -> BigInt,
         status -> Integer,
+        original_expiration_date -> Nullable<BigInt>,
+        extended_date -> Nullable<BigInt>,
+        drift_date -> Nullable<BigInt>,
+        expired_date -> Nullable<BigInt>,
+        cancelled_date -> Nullable<BigInt>,
+        attempt_count -> Integer,
+        last_error -> Nullable<Text>,
+        next_attempt_date -> Nullable<BigInt>,
+        was_muted_before -> Bool,
+        group_id -> Nullable<Integer>,
+        start_date -> Nullable<BigInt>,
     }
 }
 
@@ -50,13 +144,46 @@ diesel::table! {
         created_date -> BigInt,
         expiration_date -> BigInt,
         status -> Integer,
+        original_expiration_date -> Nullable<BigInt>,
+        extended_date -> Nullable<BigInt>,
+        targets -> Text,
+        actor_target -> Text,
+        drift_date -> Nullable<BigInt>,
+        expired_date -> Nullable<BigInt>,
+        cancelled_date -> Nullable<BigInt>,
+        attempt_count -> Integer,
+        last_error -> Nullable<Text>,
+        next_attempt_date -> Nullable<BigInt>,
+        group_id -> Nullable<Integer>,
+        start_date -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    timed_thread_mute (rowid) {
+        rowid -> Integer,
+        actor -> Text,
+        thread_uri -> Text,
+        created_date -> BigInt,
+        expiration_date -> BigInt,
+        status -> Integer,
+        attempt_count -> Integer,
+        last_error -> Nullable<Text>,
+        next_attempt_date -> Nullable<BigInt>,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
     cookie,
+    mute_event,
+    mute_group,
+    mute_group_member,
     profile,
     profile_session,
+    recurring_mute,
+    timed_block,
+    timed_list_mute,
     timed_mute,
     timed_mute_word,
+    timed_thread_mute,
 );
@@ -0,0 +1,212 @@
+use std::fmt;
+
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Integer;
+use diesel::sqlite::{Sqlite, SqliteValue};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::AppError;
+
+/// Lifecycle of a timed mute, word, block, thread mute or list mute row.
+/// Stored, and sent over the API, as the integer code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow)]
+#[diesel(sql_type = Integer)]
+pub enum MuteStatus {
+    /// In effect on Bluesky until it expires.
+    Active,
+    /// Undone on Bluesky after it expired.
+    Expired,
+    /// The resolver gave up undoing it.
+    Failed,
+    /// Replaced by a newer row for the same target.
+    Superseded,
+    /// Waiting for its start time; nothing is on Bluesky yet.
+    Scheduled,
+    /// Being undone by the resolver.
+    Expiring,
+    /// Removed on Bluesky outside this service.
+    Drifted,
+    /// Deleted by the user before it expired.
+    Cancelled,
+}
+
+impl MuteStatus {
+    pub const ALL: [MuteStatus; 8] = [
+        MuteStatus::Active,
+        MuteStatus::Expired,
+        MuteStatus::Failed,
+        MuteStatus::Superseded,
+        MuteStatus::Scheduled,
+        MuteStatus::Expiring,
+        MuteStatus::Drifted,
+        MuteStatus::Cancelled,
+    ];
+
+    /// Rows that are, or may still be, in effect on Bluesky and wait for the
+    /// resolver. Expiring rows are only left over from an interrupted run.
+    pub const UNRESOLVED: [MuteStatus; 2] = [MuteStatus::Active, MuteStatus::Expiring];
+
+    pub fn code(self) -> i32 {
+        match self {
+            MuteStatus::Active => 0,
+            MuteStatus::Expired => 1,
+            MuteStatus::Failed => 2,
+            MuteStatus::Superseded => 3,
+            MuteStatus::Scheduled => 4,
+            MuteStatus::Expiring => 5,
+            MuteStatus::Drifted => 8,
+            MuteStatus::Cancelled => 9,
+        }
+    }
+
+    pub fn from_code(code: i32) -> Option<MuteStatus> {
+        MuteStatus::ALL
+            .into_iter()
+            .find(|status| status.code() == code)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MuteStatus::Active => "active",
+            MuteStatus::Expired => "expired",
+            MuteStatus::Failed => "failed",
+            MuteStatus::Superseded => "superseded",
+            MuteStatus::Scheduled => "scheduled",
+            MuteStatus::Expiring => "expiring",
+            MuteStatus::Drifted => "drifted",
+            MuteStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// Expired, cancelled and superseded rows never change again.
+    pub fn can_transition_to(self, next: MuteStatus) -> bool {
+        use MuteStatus::*;
+        matches!(
+            (self, next),
+            (Scheduled, Active | Cancelled | Failed | Superseded)
+                | (
+                    Active,
+                    Expiring | Expired | Cancelled | Failed | Superseded | Drifted
+                )
+                | (Expiring, Active | Expired | Failed)
+                | (Failed, Expired | Cancelled)
+                | (Drifted, Cancelled)
+        )
+    }
+
+    /// Returns `next` if the row may move there from this status.
+    pub fn transition(self, next: MuteStatus) -> Result<MuteStatus, AppError> {
+        if self.can_transition_to(next) {
+            Ok(next)
+        } else {
+            Err(AppError::InvalidTransition(self, next))
+        }
+    }
+
+    /// Like [`MuteStatus::transition`], for a change that only starts from
+    /// `from`, such as the resolver putting back a row it claimed.
+    pub fn transition_from(
+        self,
+        from: MuteStatus,
+        next: MuteStatus,
+    ) -> Result<MuteStatus, AppError> {
+        if self == from {
+            self.transition(next)
+        } else {
+            Err(AppError::InvalidTransition(self, next))
+        }
+    }
+}
+
+impl fmt::Display for MuteStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ToSql<Integer, Sqlite> for MuteStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        out.set_value(self.code());
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Integer, Sqlite> for MuteStatus {
+    fn from_sql(bytes: SqliteValue<'_, '_, '_>) -> deserialize::Result<Self> {
+        let code = <i32 as FromSql<Integer, Sqlite>>::from_sql(bytes)?;
+        MuteStatus::from_code(code).ok_or_else(|| format!("Unknown status {}", code).into())
+    }
+}
+
+impl Serialize for MuteStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i32(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for MuteStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let code = i32::deserialize(deserializer)?;
+        MuteStatus::from_code(code)
+            .ok_or_else(|| serde::de::Error::custom(format!("Unknown status {}", code)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_round_trip() {
+        for status in MuteStatus::ALL {
+            assert_eq!(MuteStatus::from_code(status.code()), Some(status));
+        }
+        assert_eq!(MuteStatus::from_code(7), None);
+        assert_eq!(MuteStatus::Cancelled.code(), 9);
+    }
+
+    #[test]
+    fn test_transitions() {
+        use MuteStatus::*;
+        assert!(Active.can_transition_to(Expiring));
+        assert!(Expiring.can_transition_to(Expired));
+        assert!(Expiring.can_transition_to(Active));
+        assert!(Scheduled.can_transition_to(Active));
+        assert!(!Scheduled.can_transition_to(Expired));
+        assert!(!Active.can_transition_to(Active));
+        assert!(!Expiring.can_transition_to(Cancelled));
+        for terminal in [Expired, Cancelled, Superseded] {
+            assert!(MuteStatus::ALL
+                .iter()
+                .all(|next| !terminal.can_transition_to(*next)));
+        }
+
+        assert_eq!(Active.transition(Cancelled).unwrap(), Cancelled);
+        assert!(matches!(
+            Expired.transition(Cancelled),
+            Err(AppError::InvalidTransition(Expired, Cancelled))
+        ));
+        assert_eq!(Expiring.transition_from(Expiring, Active).unwrap(), Active);
+        // Allowed in general, but not the change the caller is making.
+        assert!(matches!(
+            Failed.transition_from(Expiring, Expired),
+            Err(AppError::InvalidTransition(Failed, Expired))
+        ));
+    }
+
+    #[test]
+    fn test_serialize_as_code() {
+        assert_eq!(serde_json::to_string(&MuteStatus::Drifted).unwrap(), "8");
+        let status: MuteStatus = serde_json::from_str("3").unwrap();
+        assert_eq!(status, MuteStatus::Superseded);
+        assert!(serde_json::from_str::<MuteStatus>("6").is_err());
+    }
+}
@@ -1,32 +1,48 @@
-use std::collections::HashMap;
-use std::env;
-
-use axum::extract::{Json, State};
-use axum::response::{IntoResponse, Response};
+use axum::extract::State;
 use axum::http::header::CONTENT_TYPE;
 use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use bsky_sdk::api::app::bsky::actor::defs::ProfileViewDetailed;
 use bsky_sdk::api::app::bsky::actor::get_profile::{Parameters, ParametersData};
-use bsky_sdk::api::types::string::AtIdentifier;
-use bsky_sdk::api::types::string::AtIdentifier::Handle;
+use diesel::SqliteConnection;
 use ipld_core::ipld::Ipld;
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
 use tower_sessions::Session;
+use utoipa::ToSchema;
 
 use crate::agent::{
-    add_mute_word_to_pref, get_agent, mute_actor, remove_mute_word_from_pref, unmute_actor,
+    add_mute_word_to_pref, block_actor, block_list, get_agent_for_profile, mute_actor,
+    mute_actor_list, mute_thread, remove_mute_word_from_pref, unblock_actor, unblock_list,
+    unmute_actor, unmute_actor_list, unmute_thread, Agent,
 };
 use crate::error::AppError;
+use crate::event::{EntryKind, Event, EventAction, EventSource};
+use crate::expiration::{
+    resolve_expiration, resolve_new_expiration, resolve_schedule, ExpirationLength,
+};
+use crate::extract::Json;
 use crate::helper::{
-    create_timed_mute, create_timed_mute_word, establish_connection, fetch_profile,
-    fetch_profile_v1, fetch_timed_mute_words, fetch_timed_mute_words_v1, fetch_timed_mutes,
-    fetch_timed_mutes_v1, update_timed_mute, update_timed_mute_list_v1, update_timed_mute_word,
-    update_timed_mute_word_list_v1,
+    create_scheduled_timed_mute, create_scheduled_timed_mute_word, create_timed_block,
+    create_timed_list_mute, create_timed_mute, create_timed_mute_word, create_timed_thread_mute,
+    extend_timed_mute, extend_timed_mute_word, fetch_active_timed_mute,
+    fetch_active_timed_mute_word, fetch_failed_timed_blocks, fetch_failed_timed_list_mutes,
+    fetch_failed_timed_mute_words, fetch_failed_timed_mutes, fetch_failed_timed_thread_mutes,
+    fetch_profile, fetch_profile_v1, fetch_scheduled_timed_mute_words, fetch_scheduled_timed_mutes,
+    fetch_timed_block, fetch_timed_blocks, fetch_timed_list_mute, fetch_timed_list_mutes,
+    fetch_timed_mute, fetch_timed_mute_word, fetch_timed_mute_words, fetch_timed_mutes,
+    fetch_timed_thread_mute, fetch_timed_thread_mutes, replace_timed_mute, replace_timed_mute_word,
+    update_timed_block_v1, update_timed_list_mute_v1, update_timed_mute_v1,
+    update_timed_mute_word_v1, update_timed_thread_mute_v1,
 };
-use crate::models::TimedMute;
+use crate::identifier::parse_actor;
+use crate::models::{TimedBlock, TimedListMute, TimedMute, TimedMuteWord, TimedThreadMute};
+use crate::resolver::resolve_timed_mutes;
+use crate::scheduled::start_scheduled_entries;
+use crate::scheduler::{reload_expiries, schedule_expiry, schedule_start};
+use crate::status::MuteStatus;
 use crate::{DBPool, APPLICATION_JSON, USER_ID_KEY};
 
-async fn get_user_id(session: Session) -> Result<String, AppError> {
+pub(crate) async fn get_user_id(session: Session) -> Result<String, AppError> {
     session
         .get(USER_ID_KEY)
         .await
@@ -41,7 +57,7 @@ async fn get_user_id(session: Session) -> Result<String, AppError> {
         ("bskytools" = String, Cookie,)
     ),
     responses(
-        (status=200, description="List of timed mute words", body = Vec<TimedMute>),
+        (status=200, description="Active and scheduled timed mute words", body = Vec<TimedMute>),
         (status=401, description="Unauthorized"),
     ),
 )]
@@ -51,7 +67,8 @@ pub async fn list_word(
 ) -> Result<Response, AppError> {
     let user_id = get_user_id(session).await?;
     let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
-    let mute_list = fetch_timed_mute_words(&mut conn, user_id.as_str());
+    let mut mute_list = fetch_timed_mute_words(&mut conn, user_id.as_str());
+    mute_list.extend(fetch_scheduled_timed_mute_words(&mut conn, user_id.as_str()));
     Ok((
         StatusCode::OK,
         [(CONTENT_TYPE, APPLICATION_JSON)],
@@ -66,7 +83,7 @@ pub async fn list_word(
         ("bskytools" = String, Cookie,)
     ),
     responses(
-        (status=200, description="List of timed mutes", body = Vec<TimedMute>),
+        (status=200, description="Active and scheduled timed mutes", body = Vec<TimedMute>),
         (status=401, description="Unauthorized"),
     ),
 )]
@@ -76,7 +93,8 @@ pub async fn list(
 ) -> Result<Response, AppError> {
     let user_id = get_user_id(session).await?;
     let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
-    let mute_list = fetch_timed_mutes(&mut conn, user_id.as_str());
+    let mut mute_list = fetch_timed_mutes(&mut conn, user_id.as_str());
+    mute_list.extend(fetch_scheduled_timed_mutes(&mut conn, user_id.as_str()));
     Ok((
         StatusCode::OK,
         [(CONTENT_TYPE, APPLICATION_JSON)],
@@ -91,8 +109,10 @@ pub async fn list(
         ("bskytools" = String, Cookie,)
     ),
     responses(
-        (status=200, description="Timed mute successfully created"),
+        (status=200, description="Timed mute created, scheduled, or the existing one extended or replaced", body = CreateTimedMuteResponse),
+        (status=400, description="Invalid handle, start or expiration"),
         (status=401, description="Unauthorized"),
+        (status=409, description="The existing entry is being expired"),
     ),
 )]
 pub async fn create(
@@ -104,30 +124,750 @@ pub async fn create(
     let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
 
     let create_time = chrono::offset::Utc::now().timestamp();
-    let expire_time = create_time + req.expiration_length;
+    let (start_time, expire_time) = resolve_schedule(
+        create_time,
+        req.starts_at.as_deref(),
+        req.expiration_length.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
+
+    let other_actor = parse_actor(req.muted_actor_handle.as_str())?;
 
     let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
     let profile = profile_list.first().ok_or(AppError::NotFound)?;
-    let agent = get_agent(profile.handle.as_str(), profile.password.as_str()).await?;
-
-    let parsed_handle = req
-        .muted_actor_handle
-        .parse::<bsky_sdk::api::types::string::Handle>();
-    if let Err(e) = parsed_handle {
-        let response = BadHandle {
-            error: e.to_string(),
-        };
+    let agent = get_agent_for_profile(profile).await?;
+
+    let profile_data = agent
+        .api
+        .app
+        .bsky
+        .actor
+        .get_profile(Parameters {
+            data: ParametersData {
+                actor: other_actor,
+            },
+            extra_data: Ipld::Null,
+        })
+        .await
+        .map_err(|e| AppError::BskyError(e.to_string()))?;
+
+    let response = if start_time > create_time {
+        // Muted on Bluesky by the scheduler once `starts_at` arrives.
+        let scheduled = create_scheduled_timed_mute(
+            &mut conn,
+            user_id.as_str(),
+            profile_data.did.as_str(),
+            &create_time,
+            &start_time,
+            &expire_time,
+        )?;
+        schedule_start(start_time);
+        Event::new(
+            user_id.as_str(),
+            EventSource::Api,
+            EventAction::Create,
+            EntryKind::Mute,
+            profile_data.did.as_str(),
+        )
+        .entry(scheduled.id)
+        .record(&mut conn);
+        CreateTimedMuteResponse::new(CreateOutcome::Scheduled, expire_time)
+    } else {
+        apply_timed_mute(
+            &mut conn,
+            &agent,
+            user_id.as_str(),
+            EventSource::Api,
+            &profile_data,
+            create_time,
+            expire_time,
+            req.on_conflict.unwrap_or_default(),
+        ).await?
+    };
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(response)
+    ).into_response())
+}
+
+/// Whether the user muted the account themselves. A mute through a mute list
+/// is not the user's own and is left out.
+pub(crate) fn is_muted_by_user(profile: &ProfileViewDetailed) -> bool {
+    profile
+        .viewer
+        .as_ref()
+        .is_some_and(|viewer| viewer.muted == Some(true) && viewer.muted_by_list.is_none())
+}
+
+/// Returned for a cancel whose row left `read` before it could be updated.
+fn cancel_conflict(current: Option<MuteStatus>, read: MuteStatus) -> AppError {
+    AppError::InvalidTransition(current.unwrap_or(read), MuteStatus::Cancelled)
+}
+
+fn is_expiring(status: Option<MuteStatus>) -> bool {
+    status == Some(MuteStatus::Expiring)
+}
+
+/// Returned for a create whose target is being undone by the resolver. The
+/// new entry would be undone with it, so the user has to retry once it ends.
+pub(crate) fn expiring_conflict() -> AppError {
+    AppError::InvalidTransition(MuteStatus::Expiring, MuteStatus::Active)
+}
+
+/// Mutes the account on Bluesky, unless the user already did, and writes its
+/// timed mute. An active timed mute for the account is extended or replaced
+/// according to `on_conflict`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn apply_timed_mute(
+    conn: &mut SqliteConnection,
+    agent: &Agent,
+    user_id: &str,
+    source: EventSource,
+    profile: &ProfileViewDetailed,
+    create_time: i64,
+    expire_time: i64,
+    on_conflict: OnConflict,
+) -> Result<CreateTimedMuteResponse, AppError> {
+    if is_expiring(fetch_active_timed_mute(conn, user_id, profile.did.as_str()).map(|m| m.status)) {
+        return Err(expiring_conflict());
+    }
+    let muted_already = is_muted_by_user(profile);
+    let muted = if muted_already {
+        None
+    } else {
+        Some(mute_actor(agent, profile.did.as_str()).await)
+    };
+    let mut event = Event::new(
+        user_id,
+        source,
+        EventAction::Create,
+        EntryKind::Mute,
+        profile.did.as_str(),
+    )
+    .bsky(muted.as_ref());
+    if let Some(Err(e)) = muted {
+        event.record(conn);
+        return Err(e);
+    }
+
+    let existing = fetch_active_timed_mute(conn, user_id, profile.did.as_str());
+    if is_expiring(existing.as_ref().map(|m| m.status)) {
+        event.record(conn);
+        return Err(expiring_conflict());
+    }
+    // With an active timed mute the account is muted because of it, so the
+    // earlier state is carried over instead.
+    let was_muted_before = existing
+        .as_ref()
+        .map_or(muted_already, |existing| existing.was_muted_before);
+    let response = match existing {
+        None => {
+            create_timed_mute(
+                conn,
+                user_id,
+                profile.did.as_str(),
+                &create_time,
+                &expire_time,
+                &MuteStatus::Active,
+                &was_muted_before,
+            )?;
+            CreateTimedMuteResponse::new(CreateOutcome::Created, expire_time)
+        }
+        Some(existing) => match on_conflict {
+            OnConflict::Extend => {
+                if expire_time > existing.expiration_date
+                    && !extend_timed_mute(conn, user_id, &existing.id, &expire_time, &create_time)?
+                {
+                    event.record(conn);
+                    return Err(expiring_conflict());
+                }
+                CreateTimedMuteResponse::new(
+                    CreateOutcome::Extended,
+                    expire_time.max(existing.expiration_date),
+                )
+            }
+            OnConflict::Replace => {
+                let replaced = replace_timed_mute(
+                    conn,
+                    user_id,
+                    &existing.id,
+                    profile.did.as_str(),
+                    &create_time,
+                    &expire_time,
+                    &was_muted_before,
+                );
+                match replaced {
+                    Ok(0) => {
+                        event.record(conn);
+                        return Err(expiring_conflict());
+                    }
+                    Err(e) => {
+                        event.record(conn);
+                        return Err(e);
+                    }
+                    Ok(_) => {}
+                }
+                CreateTimedMuteResponse::new(CreateOutcome::Replaced, expire_time)
+            }
+        },
+    };
+    schedule_expiry(response.expiration_date);
+    event.action = response.outcome.event_action();
+    event.entry_id = fetch_active_timed_mute(conn, user_id, profile.did.as_str()).map(|m| m.id);
+    event.record(conn);
+    Ok(response)
+}
+
+pub async fn trigger() -> Result<Response, AppError> {
+    let started = start_scheduled_entries().await;
+    resolve_timed_mutes().await;
+    started?;
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)]
+    ).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/deleteTimedMute",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Successfully delete timed mute"),
+        (status=409, description="Already expired, cancelled or being expired")
+    ),
+)]
+pub async fn delete(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<DeleteTimedMuteRequest>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let timed_mute = fetch_timed_mute(&mut conn, user_id.as_str(), req.id)
+        .ok_or(AppError::NotFound)?;
+    timed_mute.status.transition(MuteStatus::Cancelled)?;
+    let profile_list1 = fetch_profile(&mut conn, user_id.as_str());
+    let profile1 = profile_list1.first().ok_or(AppError::NotFound)?;
+
+    // Unmute before cancelling: a failed call leaves the row to the resolver.
+    // A scheduled mute was never muted on Bluesky.
+    let unmuted = if timed_mute.was_muted_before || timed_mute.status == MuteStatus::Scheduled {
+        None
+    } else {
+        Some(async {
+            let agent_res = get_agent_for_profile(profile1).await?;
+            unmute_actor(&agent_res, timed_mute.muted_actor.as_str()).await
+        }.await)
+    };
+    let event = Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Delete,
+        EntryKind::Mute,
+        timed_mute.muted_actor.as_str(),
+    )
+    .entry(req.id)
+    .bsky(unmuted.as_ref());
+    if let Some(Err(e)) = unmuted {
+        event.record(&mut conn);
+        return Err(e);
+    }
+
+    let success =
+        update_timed_mute_v1(&mut conn, user_id.as_str(), &req.id, &MuteStatus::Cancelled)?;
+    if !success {
+        // The resolver changed the row after it was read.
+        let current = fetch_timed_mute(&mut conn, user_id.as_str(), req.id).map(|row| row.status);
+        return Err(cancel_conflict(current, timed_mute.status));
+    }
+    reload_expiries(&mut conn);
+    event.record(&mut conn);
+
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)]
+    ).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/timed-mute-word",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Timed mute word created, scheduled, or the existing one extended or replaced", body = CreateTimedMuteResponse),
+        (status=400, description="Invalid start or expiration"),
+        (status=401, description="Unauthorized"),
+        (status=409, description="The existing entry is being expired"),
+    ),
+)]
+pub async fn create_word(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<CreateTimedMuteWordRequest>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let create_time = chrono::offset::Utc::now().timestamp();
+    let (start_time, expire_time) = resolve_schedule(
+        create_time,
+        req.starts_at.as_deref(),
+        req.expiration_length.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
+    let targets = mute_word_targets(req.targets.as_deref())?;
+    let actor_target = req.actor_target.unwrap_or_default();
+
+    if start_time > create_time {
+        // Added to the preference by the scheduler once `starts_at` arrives.
+        let scheduled = create_scheduled_timed_mute_word(
+            &mut conn,
+            user_id.as_str(),
+            req.muted_word.as_str(),
+            targets.join(",").as_str(),
+            actor_target.as_str(),
+            &create_time,
+            &start_time,
+            &expire_time,
+        )?;
+        schedule_start(start_time);
+        Event::new(
+            user_id.as_str(),
+            EventSource::Api,
+            EventAction::Create,
+            EntryKind::Word,
+            req.muted_word.as_str(),
+        )
+        .entry(scheduled.id)
+        .record(&mut conn);
         return Ok((
-            StatusCode::BAD_REQUEST,
+            StatusCode::OK,
             [(CONTENT_TYPE, APPLICATION_JSON)],
-            axum::Json(response)
+            axum::Json(CreateTimedMuteResponse::new(CreateOutcome::Scheduled, expire_time))
         ).into_response());
     }
-    let other_handle: AtIdentifier = Handle(
-        req.muted_actor_handle
-            .parse()
-            .map_err(|e| AppError::BskyError(format!("{:?}", e)))?,
+
+    let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
+    let profile = profile_list.first().ok_or(AppError::NotFound)?;
+    let agent = get_agent_for_profile(profile).await?;
+
+    let response = apply_timed_mute_word(
+        &mut conn,
+        &agent,
+        user_id.as_str(),
+        EventSource::Api,
+        req.muted_word.as_str(),
+        &targets,
+        actor_target,
+        create_time,
+        expire_time,
+        req.on_conflict.unwrap_or_default(),
+    ).await?;
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(response)
+    ).into_response())
+}
+
+/// Adds the word to the user's muted words, unless it has an active timed
+/// mute word already, and writes its timed mute word. An active one is
+/// extended or replaced according to `on_conflict`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn apply_timed_mute_word(
+    conn: &mut SqliteConnection,
+    agent: &Agent,
+    user_id: &str,
+    source: EventSource,
+    muted_word: &str,
+    targets: &[String],
+    actor_target: MuteWordActorTarget,
+    create_time: i64,
+    expire_time: i64,
+    on_conflict: OnConflict,
+) -> Result<CreateTimedMuteResponse, AppError> {
+    let mut event = Event::new(
+        user_id,
+        source,
+        EventAction::Create,
+        EntryKind::Word,
+        muted_word,
     );
+    let existing = fetch_active_timed_mute_word(conn, user_id, muted_word);
+    if is_expiring(existing.as_ref().map(|w| w.status)) {
+        return Err(expiring_conflict());
+    }
+    let response = match existing {
+        None => {
+            let added = add_mute_word_to_pref(
+                agent,
+                muted_word.to_string(),
+                targets.to_vec(),
+                actor_target.as_str(),
+            ).await;
+            event = event.bsky(Some(&added));
+            if let Err(e) = added {
+                event.record(conn);
+                return Err(e);
+            }
+            create_timed_mute_word(
+                conn,
+                user_id,
+                muted_word,
+                targets.join(",").as_str(),
+                actor_target.as_str(),
+                &create_time,
+                &expire_time,
+                &MuteStatus::Active,
+            )?;
+            CreateTimedMuteResponse::new(CreateOutcome::Created, expire_time)
+        }
+        // The word is already in the preference; only the row changes.
+        Some(existing) => match on_conflict {
+            OnConflict::Extend => {
+                if expire_time > existing.expiration_date
+                    && !extend_timed_mute_word(
+                        conn,
+                        user_id,
+                        &existing.id,
+                        &expire_time,
+                        &create_time,
+                    )?
+                {
+                    return Err(expiring_conflict());
+                }
+                CreateTimedMuteResponse::new(
+                    CreateOutcome::Extended,
+                    expire_time.max(existing.expiration_date),
+                )
+            }
+            OnConflict::Replace => {
+                // Updates the word this service added in place.
+                let added = add_mute_word_to_pref(
+                    agent,
+                    muted_word.to_string(),
+                    targets.to_vec(),
+                    actor_target.as_str(),
+                ).await;
+                event = event.bsky(Some(&added));
+                if let Err(e) = added {
+                    event.record(conn);
+                    return Err(e);
+                }
+                let replaced = replace_timed_mute_word(
+                    conn,
+                    user_id,
+                    &existing.id,
+                    muted_word,
+                    targets.join(",").as_str(),
+                    actor_target.as_str(),
+                    &create_time,
+                    &expire_time,
+                );
+                match replaced {
+                    Ok(0) => {
+                        event.record(conn);
+                        return Err(expiring_conflict());
+                    }
+                    Err(e) => {
+                        event.record(conn);
+                        return Err(e);
+                    }
+                    Ok(_) => {}
+                }
+                CreateTimedMuteResponse::new(CreateOutcome::Replaced, expire_time)
+            }
+        },
+    };
+    schedule_expiry(response.expiration_date);
+    event.action = response.outcome.event_action();
+    event.entry_id = fetch_active_timed_mute_word(conn, user_id, muted_word).map(|w| w.id);
+    event.record(conn);
+    Ok(response)
+}
+
+#[utoipa::path(
+    post,
+    path = "/deleteTimedMuteWord",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Successfully delete timed mute"),
+        (status=409, description="Already expired, cancelled or being expired")
+    ),
+)]
+pub async fn delete_word(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<DeleteTimedMuteWordRequest>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let timed_mute_word = fetch_timed_mute_word(&mut conn, user_id.as_str(), req.id)
+        .ok_or(AppError::NotFound)?;
+    timed_mute_word.status.transition(MuteStatus::Cancelled)?;
+    let profile_list1 = fetch_profile(&mut conn, user_id.as_str());
+    let profile1 = profile_list1.first().ok_or(AppError::NotFound)?;
+
+    // Remove the word before cancelling: a failed call leaves the row to the
+    // resolver. A scheduled word was never added to the preference.
+    let removed = if timed_mute_word.status == MuteStatus::Scheduled {
+        None
+    } else {
+        Some(async {
+            let agent = get_agent_for_profile(profile1).await?;
+            remove_mute_word_from_pref(&agent, timed_mute_word.muted_word.clone()).await
+        }.await)
+    };
+    let event = Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Delete,
+        EntryKind::Word,
+        timed_mute_word.muted_word.as_str(),
+    )
+    .entry(req.id)
+    .bsky(removed.as_ref());
+    if let Some(Err(e)) = removed {
+        event.record(&mut conn);
+        return Err(e);
+    }
+
+    let success =
+        update_timed_mute_word_v1(&mut conn, user_id.as_str(), &req.id, &MuteStatus::Cancelled)?;
+    if !success {
+        // The resolver changed the row after it was read.
+        let current = fetch_timed_mute_word(&mut conn, user_id.as_str(), req.id)
+            .map(|row| row.status);
+        return Err(cancel_conflict(current, timed_mute_word.status));
+    }
+    reload_expiries(&mut conn);
+    event.record(&mut conn);
+
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)]
+    ).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/extendTimedMute",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Timed mute expiration changed", body = ExtendTimedMuteResponse),
+        (status=400, description="Invalid expiration"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn extend(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<ExtendTimedMuteRequest>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let current = fetch_timed_mute(&mut conn, user_id.as_str(), req.id)
+        .filter(|m| m.status == MuteStatus::Active)
+        .ok_or(AppError::NotFound)?;
+
+    let extend_time = chrono::offset::Utc::now().timestamp();
+    let new_expire_time = resolve_new_expiration(
+        extend_time,
+        current.expiration_date,
+        req.delta.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
+
+    let success = extend_timed_mute(
+        &mut conn,
+        user_id.as_str(),
+        &req.id,
+        &new_expire_time,
+        &extend_time,
+    )?;
+    if !success {
+        return Err(AppError::NotFound);
+    }
+    reload_expiries(&mut conn);
+    Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Extend,
+        EntryKind::Mute,
+        current.muted_actor.as_str(),
+    )
+    .entry(req.id)
+    .record(&mut conn);
+
+    let response = ExtendTimedMuteResponse {
+        expiration_date: new_expire_time,
+    };
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(response)
+    ).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/extendTimedMuteWord",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Timed mute word expiration changed", body = ExtendTimedMuteResponse),
+        (status=400, description="Invalid expiration"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn extend_word(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<ExtendTimedMuteWordRequest>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let current = fetch_timed_mute_word(&mut conn, user_id.as_str(), req.id)
+        .filter(|w| w.status == MuteStatus::Active)
+        .ok_or(AppError::NotFound)?;
+
+    let extend_time = chrono::offset::Utc::now().timestamp();
+    let new_expire_time = resolve_new_expiration(
+        extend_time,
+        current.expiration_date,
+        req.delta.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
+
+    let success = extend_timed_mute_word(
+        &mut conn,
+        user_id.as_str(),
+        &req.id,
+        &new_expire_time,
+        &extend_time,
+    )?;
+    if !success {
+        return Err(AppError::NotFound);
+    }
+    reload_expiries(&mut conn);
+    Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Extend,
+        EntryKind::Word,
+        current.muted_word.as_str(),
+    )
+    .entry(req.id)
+    .record(&mut conn);
+
+    let response = ExtendTimedMuteResponse {
+        expiration_date: new_expire_time,
+    };
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(response)
+    ).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/timed-failures",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Timed entries that could not be undone on Bluesky", body = FailedTimedMutes),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_failed(
+    session: Session,
+    State(pool): State<DBPool>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+    let failed = FailedTimedMutes::fetch(&mut conn, Some(user_id.as_str()));
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(failed)
+    ).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/timed-blocks",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="List of timed blocks", body = Vec<TimedBlock>),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_block(
+    session: Session,
+    State(pool): State<DBPool>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+    let block_list = fetch_timed_blocks(&mut conn, user_id.as_str());
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(block_list)
+    ).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/timed-block",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Timed block successfully created"),
+        (status=400, description="Invalid handle or expiration"),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn create_block(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<CreateTimedBlockRequest>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let create_time = chrono::offset::Utc::now().timestamp();
+    let expire_time = resolve_expiration(
+        create_time,
+        req.expiration_length.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
+
+    let other_actor = parse_actor(req.blocked_actor_handle.as_str())?;
+
+    let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
+    let profile = profile_list.first().ok_or(AppError::NotFound)?;
+    let agent = get_agent_for_profile(profile).await?;
+
     let profile_data = agent
         .api
         .app
@@ -135,71 +875,253 @@ pub async fn create(
         .actor
         .get_profile(Parameters {
             data: ParametersData {
-                actor: other_handle,
+                actor: other_actor,
             },
             extra_data: Ipld::Null,
         })
         .await
         .map_err(|e| AppError::BskyError(e.to_string()))?;
 
-    mute_actor(&agent, profile_data.did.as_str()).await?;
+    let blocked = block_actor(&agent, profile_data.did.as_str()).await;
+    let event = Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Create,
+        EntryKind::Block,
+        profile_data.did.as_str(),
+    )
+    .bsky(Some(&blocked));
+    let record_uri = match blocked {
+        Ok(record_uri) => record_uri,
+        Err(e) => {
+            event.record(&mut conn);
+            return Err(e);
+        }
+    };
 
-    create_timed_mute(
+    let block = create_timed_block(
         &mut conn,
         user_id.as_str(),
         profile_data.did.as_str(),
+        record_uri.as_str(),
         &create_time,
         &expire_time,
-        &0,
+        &MuteStatus::Active,
     )?;
+    event.entry(block.id).record(&mut conn);
+    schedule_expiry(expire_time);
     Ok((
         StatusCode::OK,
         [(CONTENT_TYPE, APPLICATION_JSON)]
     ).into_response())
 }
 
-pub async fn trigger() -> Response {
-    resolve_timed_mutes().await;
-    (
+#[utoipa::path(
+    post,
+    path = "/deleteTimedBlock",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Successfully delete timed block"),
+        (status=404, description="No such entry"),
+        (status=409, description="Already expired, cancelled or being expired")
+    ),
+)]
+pub async fn delete_block(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<DeleteTimedBlockRequest>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let block = fetch_timed_block(&mut conn, user_id.as_str(), req.id)
+        .ok_or(AppError::NotFound)?;
+    block.status.transition(MuteStatus::Cancelled)?;
+    let profile_list1 = fetch_profile(&mut conn, user_id.as_str());
+    let profile1 = profile_list1.first().ok_or(AppError::NotFound)?;
+
+    // Undo it on Bluesky before cancelling: a failed call leaves the row to
+    // the resolver.
+    let unblocked = async {
+        let agent_res = get_agent_for_profile(profile1).await?;
+        unblock_actor(&agent_res, block.record_uri.as_str()).await
+    }.await;
+    let event = Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Delete,
+        EntryKind::Block,
+        block.blocked_actor.as_str(),
+    )
+    .entry(block.id)
+    .bsky(Some(&unblocked));
+    if let Err(e) = unblocked {
+        event.record(&mut conn);
+        return Err(e);
+    }
+
+    let success =
+        update_timed_block_v1(&mut conn, user_id.as_str(), &req.id, &MuteStatus::Cancelled)?;
+    if !success {
+        // The resolver changed the row after it was read.
+        let current = fetch_timed_block(&mut conn, user_id.as_str(), req.id).map(|row| row.status);
+        return Err(cancel_conflict(current, block.status));
+    }
+    reload_expiries(&mut conn);
+    event.record(&mut conn);
+
+    Ok((
         StatusCode::OK,
         [(CONTENT_TYPE, APPLICATION_JSON)]
-    ).into_response()
+    ).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/timed-thread-mutes",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="List of timed thread mutes", body = Vec<TimedThreadMute>),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_thread(
+    session: Session,
+    State(pool): State<DBPool>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+    let mute_list = fetch_timed_thread_mutes(&mut conn, user_id.as_str());
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(mute_list)
+    ).into_response())
 }
 
 #[utoipa::path(
     post,
-    path = "/deleteTimedMute",
+    path = "/timed-thread-mute",
     params(
         ("bskytools" = String, Cookie,)
     ),
     responses(
-        (status=200, description="Successfully delete timed mute")
+        (status=200, description="Timed thread mute successfully created"),
+        (status=400, description="Not a post AT-URI or invalid expiration"),
+        (status=401, description="Unauthorized"),
     ),
 )]
-pub async fn delete(
+pub async fn create_thread(
     session: Session,
     State(pool): State<DBPool>,
-    Json(req): Json<DeleteTimedMuteRequest>,
+    Json(req): Json<CreateTimedThreadMuteRequest>,
 ) -> Result<Response, AppError> {
     let user_id = get_user_id(session).await?;
     let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
 
-    let success = update_timed_mute(
+    validate_record_uri(req.thread_uri.as_str(), "app.bsky.feed.post")?;
+
+    let create_time = chrono::offset::Utc::now().timestamp();
+    let expire_time = resolve_expiration(
+        create_time,
+        req.expiration_length.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
+
+    let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
+    let profile = profile_list.first().ok_or(AppError::NotFound)?;
+    let agent = get_agent_for_profile(profile).await?;
+
+    let muted = mute_thread(&agent, req.thread_uri.as_str()).await;
+    let event = Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Create,
+        EntryKind::ThreadMute,
+        req.thread_uri.as_str(),
+    )
+    .bsky(Some(&muted));
+    if let Err(e) = muted {
+        event.record(&mut conn);
+        return Err(e);
+    }
+
+    let thread_mute = create_timed_thread_mute(
         &mut conn,
         user_id.as_str(),
-        req.muted_actor_did.as_str(),
-        &req.expiration_date,
-        &9,
+        req.thread_uri.as_str(),
+        &create_time,
+        &expire_time,
+        &MuteStatus::Active,
     )?;
-    if !success {
-        return Err(AppError::Unauthorized);
-    }
+    event.entry(thread_mute.id).record(&mut conn);
+    schedule_expiry(expire_time);
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)]
+    ).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/deleteTimedThreadMute",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="Successfully delete timed thread mute"),
+        (status=404, description="No such entry"),
+        (status=409, description="Already expired, cancelled or being expired")
+    ),
+)]
+pub async fn delete_thread(
+    session: Session,
+    State(pool): State<DBPool>,
+    Json(req): Json<DeleteTimedThreadMuteRequest>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+
+    let thread_mute = fetch_timed_thread_mute(&mut conn, user_id.as_str(), req.id)
+        .ok_or(AppError::NotFound)?;
+    thread_mute.status.transition(MuteStatus::Cancelled)?;
     let profile_list1 = fetch_profile(&mut conn, user_id.as_str());
     let profile1 = profile_list1.first().ok_or(AppError::NotFound)?;
 
-    let agent_res = get_agent(profile1.handle.as_str(), profile1.password.as_str()).await?;
+    // Undo it on Bluesky before cancelling: a failed call leaves the row to
+    // the resolver.
+    let unmuted = async {
+        let agent_res = get_agent_for_profile(profile1).await?;
+        unmute_thread(&agent_res, thread_mute.thread_uri.as_str()).await
+    }.await;
+    let event = Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Delete,
+        EntryKind::ThreadMute,
+        thread_mute.thread_uri.as_str(),
+    )
+    .entry(req.id)
+    .bsky(Some(&unmuted));
+    if let Err(e) = unmuted {
+        event.record(&mut conn);
+        return Err(e);
+    }
 
-    unmute_actor(&agent_res, req.muted_actor_did.as_str()).await?;
+    let success =
+        update_timed_thread_mute_v1(&mut conn, user_id.as_str(), &req.id, &MuteStatus::Cancelled)?;
+    if !success {
+        // The resolver changed the row after it was read.
+        let current = fetch_timed_thread_mute(&mut conn, user_id.as_str(), req.id)
+            .map(|row| row.status);
+        return Err(cancel_conflict(current, thread_mute.status));
+    }
+    reload_expiries(&mut conn);
+    event.record(&mut conn);
 
     Ok((
         StatusCode::OK,
@@ -207,42 +1129,96 @@ pub async fn delete(
     ).into_response())
 }
 
+#[utoipa::path(
+    get,
+    path = "/timed-list-mutes",
+    params(
+        ("bskytools" = String, Cookie,)
+    ),
+    responses(
+        (status=200, description="List of timed list mutes and list blocks", body = Vec<TimedListMute>),
+        (status=401, description="Unauthorized"),
+    ),
+)]
+pub async fn list_list(
+    session: Session,
+    State(pool): State<DBPool>,
+) -> Result<Response, AppError> {
+    let user_id = get_user_id(session).await?;
+    let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
+    let mute_list = fetch_timed_list_mutes(&mut conn, user_id.as_str());
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, APPLICATION_JSON)],
+        axum::Json(mute_list)
+    ).into_response())
+}
+
 #[utoipa::path(
     post,
-    path = "/timed-mute-word",
+    path = "/timed-list-mute",
     params(
         ("bskytools" = String, Cookie,)
     ),
     responses(
-        (status=200, description="Timed mute word successfully created"),
+        (status=200, description="Timed list mute or list block successfully created"),
+        (status=400, description="Not a list AT-URI or invalid expiration"),
         (status=401, description="Unauthorized"),
     ),
 )]
-pub async fn create_word(
+pub async fn create_list(
     session: Session,
     State(pool): State<DBPool>,
-    Json(req): Json<CreateTimedMuteWordRequest>,
+    Json(req): Json<CreateTimedListMuteRequest>,
 ) -> Result<Response, AppError> {
     let user_id = get_user_id(session).await?;
     let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
 
+    validate_record_uri(req.list_uri.as_str(), "app.bsky.graph.list")?;
+
     let create_time = chrono::offset::Utc::now().timestamp();
-    let expire_time = create_time + req.expiration_length;
+    let expire_time = resolve_expiration(
+        create_time,
+        req.expiration_length.as_ref(),
+        req.expires_at.as_deref(),
+    )?;
 
     let profile_list = fetch_profile_v1(&mut conn, user_id.as_str());
     let profile = profile_list.first().ok_or(AppError::NotFound)?;
-    let agent = get_agent(profile.handle.as_str(), profile.password.as_str()).await?;
+    let agent = get_agent_for_profile(profile).await?;
 
-    add_mute_word_to_pref(&agent, req.muted_word.clone()).await?;
+    let applied = match req.mode {
+        ListMuteMode::Mute => mute_actor_list(&agent, req.list_uri.as_str()).await.map(|_| None),
+        ListMuteMode::Block => block_list(&agent, req.list_uri.as_str()).await.map(Some),
+    };
+    let event = Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Create,
+        EntryKind::ListMute,
+        req.list_uri.as_str(),
+    )
+    .bsky(Some(&applied));
+    let record_uri = match applied {
+        Ok(record_uri) => record_uri,
+        Err(e) => {
+            event.record(&mut conn);
+            return Err(e);
+        }
+    };
 
-    create_timed_mute_word(
+    let list_mute = create_timed_list_mute(
         &mut conn,
         user_id.as_str(),
-        req.muted_word.as_str(),
+        req.list_uri.as_str(),
+        req.mode.as_str(),
+        record_uri.as_deref(),
         &create_time,
         &expire_time,
-        &0,
+        &MuteStatus::Active,
     )?;
+    event.entry(list_mute.id).record(&mut conn);
+    schedule_expiry(expire_time);
     Ok((
         StatusCode::OK,
         [(CONTENT_TYPE, APPLICATION_JSON)]
@@ -251,32 +1227,60 @@ pub async fn create_word(
 
 #[utoipa::path(
     post,
-    path = "/deleteTimedMuteWord",
+    path = "/deleteTimedListMute",
     params(
         ("bskytools" = String, Cookie,)
     ),
     responses(
-        (status=200, description="Successfully delete timed mute")
+        (status=200, description="Successfully delete timed list mute or list block"),
+        (status=404, description="No such entry"),
+        (status=409, description="Already expired, cancelled or being expired")
     ),
 )]
-pub async fn delete_word(
+pub async fn delete_list(
     session: Session,
     State(pool): State<DBPool>,
-    Json(req): Json<DeleteTimedMuteWordRequest>,
+    Json(req): Json<DeleteTimedListMuteRequest>,
 ) -> Result<Response, AppError> {
     let user_id = get_user_id(session).await?;
     let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
 
-    let success = update_timed_mute_word(&mut conn, user_id.as_str(), req.muted_word.as_str(), &9)?;
-    if !success {
-        return Err(AppError::Unauthorized);
-    }
+    let list_mute = fetch_timed_list_mute(&mut conn, user_id.as_str(), req.id)
+        .ok_or(AppError::NotFound)?;
+    list_mute.status.transition(MuteStatus::Cancelled)?;
     let profile_list1 = fetch_profile(&mut conn, user_id.as_str());
     let profile1 = profile_list1.first().ok_or(AppError::NotFound)?;
 
-    let agent = get_agent(profile1.handle.as_str(), profile1.password.as_str()).await?;
+    // Undo it on Bluesky before cancelling: a failed call leaves the row to
+    // the resolver.
+    let undone = async {
+        let agent_res = get_agent_for_profile(profile1).await?;
+        undo_list_mute(&agent_res, &list_mute).await
+    }.await;
+    let event = Event::new(
+        user_id.as_str(),
+        EventSource::Api,
+        EventAction::Delete,
+        EntryKind::ListMute,
+        list_mute.list_uri.as_str(),
+    )
+    .entry(list_mute.id)
+    .bsky(Some(&undone));
+    if let Err(e) = undone {
+        event.record(&mut conn);
+        return Err(e);
+    }
 
-    remove_mute_word_from_pref(&agent, req.muted_word.clone()).await?;
+    let success =
+        update_timed_list_mute_v1(&mut conn, user_id.as_str(), &req.id, &MuteStatus::Cancelled)?;
+    if !success {
+        // The resolver changed the row after it was read.
+        let current = fetch_timed_list_mute(&mut conn, user_id.as_str(), req.id)
+            .map(|row| row.status);
+        return Err(cancel_conflict(current, list_mute.status));
+    }
+    reload_expiries(&mut conn);
+    event.record(&mut conn);
 
     Ok((
         StatusCode::OK,
@@ -284,108 +1288,297 @@ pub async fn delete_word(
     ).into_response())
 }
 
-pub async fn resolve_timed_mutes() {
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL");
-    let mut conn = establish_connection(database_url.as_str());
-    let timed_mutes_res = fetch_timed_mutes_v1(&mut conn);
-    let current_timestamp = chrono::offset::Utc::now().timestamp();
-    let mut resolved_timed_mutes: HashMap<String, Vec<String>> = HashMap::new();
-
-    for timed_mute in timed_mutes_res {
-        if current_timestamp > timed_mute.expiration_date {
-            if resolved_timed_mutes.contains_key(&timed_mute.actor) {
-                let x = resolved_timed_mutes
-                    .get_mut(&timed_mute.actor)
-                    .expect("Error getting vector");
-                x.push(timed_mute.muted_actor);
-            } else {
-                let actor = timed_mute.actor.clone();
-                let x: Vec<String> = vec![timed_mute.muted_actor];
-                resolved_timed_mutes.insert(actor, x);
-            }
+pub(crate) async fn undo_list_mute(
+    agent: &Agent,
+    list_mute: &TimedListMute,
+) -> Result<(), AppError> {
+    match list_mute.record_uri.as_deref() {
+        Some(record_uri) if list_mute.mode == ListMuteMode::Block.as_str() => {
+            unblock_list(agent, record_uri).await
         }
+        _ => unmute_actor_list(agent, list_mute.list_uri.as_str()).await,
     }
+}
 
-    for (key, value) in resolved_timed_mutes {
-        let profile_list = fetch_profile_v1(&mut conn, key.as_str());
-        let profile = match profile_list.first() {
-            Some(p) => p,
-            None => continue,
-        };
-
-        let agent_res = match get_agent(profile.handle.as_str(), profile.password.as_str()).await {
-            Ok(a) => a,
-            Err(_) => continue,
-        };
-
-        for actor_val in &value {
-            let _ = unmute_actor(&agent_res, actor_val).await;
+/// Returns the Bluesky targets for a new mute word, defaulting to both tags
+/// and content.
+pub(crate) fn mute_word_targets(
+    targets: Option<&[MuteWordTarget]>,
+) -> Result<Vec<String>, AppError> {
+    let Some(targets) = targets else {
+        return Ok(vec!["tag".to_string(), "content".to_string()]);
+    };
+    if targets.is_empty() {
+        return Err(AppError::BadRequest("targets must not be empty".to_string()));
+    }
+    let mut values: Vec<String> = Vec::new();
+    for target in targets {
+        let value = target.as_str().to_string();
+        if !values.contains(&value) {
+            values.push(value);
         }
+    }
+    Ok(values)
+}
 
-        let _ = update_timed_mute_list_v1(&mut conn, key.as_str(), value, &1);
+/// Checks that `uri` looks like `at://<repo>/<collection>/<rkey>`.
+fn validate_record_uri(uri: &str, collection: &str) -> Result<(), AppError> {
+    let parts: Vec<&str> = uri
+        .strip_prefix("at://")
+        .map(|path| path.split('/').collect())
+        .unwrap_or_default();
+    match parts.as_slice() {
+        [repo, c, rkey] if *c == collection && !repo.is_empty() && !rkey.is_empty() => Ok(()),
+        _ => Err(AppError::BadRequest(format!(
+            "Expected an AT-URI for {}, got {}",
+            collection, uri
+        ))),
     }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateTimedMuteRequest {
+    /// Handle, DID or `bsky.app` profile link of the account.
+    pub muted_actor_handle: String,
+    /// What to do when the account already has an active timed mute; defaults to `extend`.
+    #[serde(default)]
+    pub on_conflict: Option<OnConflict>,
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// RFC3339 timestamp to start the mute at instead of right away;
+    /// `expiration_length` then counts from it.
+    #[serde(default)]
+    pub starts_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateTimedMuteWordRequest {
+    pub muted_word: String,
+    /// Where the word is matched; defaults to both `tag` and `content`.
+    #[serde(default)]
+    pub targets: Option<Vec<MuteWordTarget>>,
+    /// Defaults to `all`.
+    #[serde(default)]
+    pub actor_target: Option<MuteWordActorTarget>,
+    /// What to do when the word already has an active timed mute word; defaults to `extend`.
+    #[serde(default)]
+    pub on_conflict: Option<OnConflict>,
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// RFC3339 timestamp to start muting the word at instead of right away;
+    /// `expiration_length` then counts from it.
+    #[serde(default)]
+    pub starts_at: Option<String>,
+}
+
+/// How a create request treats an active row for the same target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+    /// Keep the existing row, moving its expiration if the new one is later.
+    #[default]
+    Extend,
+    /// Mark the existing row superseded and start a new one.
+    Replace,
+}
 
-    let timed_mute_words_res = fetch_timed_mute_words_v1(&mut conn);
-    let mut resolved_timed_mute_words: HashMap<String, Vec<String>> = HashMap::new();
-    for timed_mute_word in timed_mute_words_res {
-        if current_timestamp > timed_mute_word.expiration_date {
-            if resolved_timed_mute_words.contains_key(&timed_mute_word.actor) {
-                let x = resolved_timed_mute_words
-                    .get_mut(&timed_mute_word.actor)
-                    .expect("Error getting vector");
-                x.push(timed_mute_word.muted_word);
-            } else {
-                let actor = timed_mute_word.actor.clone();
-                let x: Vec<String> = vec![timed_mute_word.muted_word];
-                resolved_timed_mute_words.insert(actor, x);
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CreateOutcome {
+    Created,
+    Extended,
+    Replaced,
+    /// Saved with status scheduled until `starts_at`.
+    Scheduled,
+}
+
+impl CreateOutcome {
+    fn event_action(self) -> EventAction {
+        match self {
+            CreateOutcome::Extended => EventAction::Extend,
+            CreateOutcome::Created | CreateOutcome::Replaced | CreateOutcome::Scheduled => {
+                EventAction::Create
             }
         }
     }
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateTimedMuteResponse {
+    pub outcome: CreateOutcome,
+    /// Expiration of the active row after the request.
+    pub expiration_date: i64,
+}
 
-    for (key, value) in resolved_timed_mute_words {
-        let profile_list = fetch_profile_v1(&mut conn, key.as_str());
-        let profile = match profile_list.first() {
-            Some(p) => p,
-            None => continue,
-        };
+impl CreateTimedMuteResponse {
+    fn new(outcome: CreateOutcome, expiration_date: i64) -> Self {
+        CreateTimedMuteResponse {
+            outcome,
+            expiration_date,
+        }
+    }
+}
 
-        let agent_res = match get_agent(profile.handle.as_str(), profile.password.as_str()).await {
-            Ok(a) => a,
-            Err(_) => continue,
-        };
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MuteWordTarget {
+    Tag,
+    Content,
+}
 
-        for muted_word in &value {
-            let _ = remove_mute_word_from_pref(&agent_res, muted_word.to_string()).await;
+impl MuteWordTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MuteWordTarget::Tag => "tag",
+            MuteWordTarget::Content => "content",
         }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum MuteWordActorTarget {
+    #[default]
+    All,
+    /// Don't hide posts from accounts the user follows.
+    ExcludeFollowing,
+}
 
-        let _ = update_timed_mute_word_list_v1(&mut conn, key.as_str(), value, &1);
+impl MuteWordActorTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MuteWordActorTarget::All => "all",
+            MuteWordActorTarget::ExcludeFollowing => "exclude-following",
+        }
     }
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
-pub struct CreateTimedMuteRequest {
-    pub muted_actor_handle: String,
-    pub expiration_length: i64,
+pub struct DeleteTimedMuteRequest {
+    pub id: i32,
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
-pub struct CreateTimedMuteWordRequest {
-    pub muted_word: String,
-    pub expiration_length: i64,
+pub struct DeleteTimedMuteWordRequest {
+    pub id: i32,
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
-pub struct DeleteTimedMuteRequest {
-    pub muted_actor_did: String,
+pub struct ExtendTimedMuteRequest {
+    pub id: i32,
+    /// Signed change to the current expiration, e.g. `2h` or `-30m`.
+    #[serde(default)]
+    pub delta: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `delta`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ExtendTimedMuteWordRequest {
+    pub id: i32,
+    /// Signed change to the current expiration, e.g. `2h` or `-30m`.
+    #[serde(default)]
+    pub delta: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `delta`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ExtendTimedMuteResponse {
     pub expiration_date: i64,
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
-pub struct DeleteTimedMuteWordRequest {
-    pub muted_word: String,
+pub struct CreateTimedBlockRequest {
+    /// Handle, DID or `bsky.app` profile link of the account.
+    pub blocked_actor_handle: String,
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeleteTimedBlockRequest {
+    pub id: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateTimedThreadMuteRequest {
+    pub thread_uri: String,
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeleteTimedThreadMuteRequest {
+    pub id: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ListMuteMode {
+    Mute,
+    Block,
+}
+
+impl ListMuteMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListMuteMode::Mute => "mute",
+            ListMuteMode::Block => "block",
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
-pub struct BadHandle {
-    pub error: String,
+pub struct CreateTimedListMuteRequest {
+    pub list_uri: String,
+    pub mode: ListMuteMode,
+    #[serde(default)]
+    pub expiration_length: Option<ExpirationLength>,
+    /// RFC3339 timestamp, as an alternative to `expiration_length`.
+    #[serde(default)]
+    pub expires_at: Option<String>,
 }
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeleteTimedListMuteRequest {
+    pub id: i32,
+}
+
+/// Rows the resolver gave up on (status 2), with their attempt count and last error.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct FailedTimedMutes {
+    pub mutes: Vec<TimedMute>,
+    pub words: Vec<TimedMuteWord>,
+    pub blocks: Vec<TimedBlock>,
+    pub thread_mutes: Vec<TimedThreadMute>,
+    pub list_mutes: Vec<TimedListMute>,
+}
+
+impl FailedTimedMutes {
+    /// Loads the failed rows for `user_id`, or for every user when it is `None`.
+    pub fn fetch(conn: &mut SqliteConnection, user_id: Option<&str>) -> Self {
+        Self {
+            mutes: fetch_failed_timed_mutes(conn, user_id),
+            words: fetch_failed_timed_mute_words(conn, user_id),
+            blocks: fetch_failed_timed_blocks(conn, user_id),
+            thread_mutes: fetch_failed_timed_thread_mutes(conn, user_id),
+            list_mutes: fetch_failed_timed_list_mutes(conn, user_id),
+        }
+    }
+}
+
@@ -1,11 +1,15 @@
 use crate::agent::get_agent;
+use crate::crypto;
 use crate::error::AppError;
-use crate::helper::{create_profile, deactivate_profile, fetch_profile, update_profile};
+use crate::extract::Json;
+use crate::helper::{
+    create_profile, deactivate_profile, delete_profile_session, fetch_profile, update_profile,
+};
 use crate::{
     DBPool, ACCESS_JWT_KEY, ACTIVE_KEY, APPLICATION_JSON, DID_KEY, REFRESH_JWT_KEY,
     USER_HANDLE_KEY, USER_ID_KEY,
 };
-use axum::extract::{Json, State};
+use axum::extract::State;
 use axum::response::{IntoResponse, Response};
 use axum::http::header::CONTENT_TYPE;
 use axum::http::StatusCode;
@@ -42,7 +46,7 @@ pub async fn login(
             bsky_session.handle.as_str(),
             req.password.as_str(),
         )?;
-    } else if profiles.first().unwrap().password.eq(&req.password) {
+    } else if crypto::decrypt(profiles.first().unwrap().password.as_str())?.eq(&req.password) {
         update_profile(
             &mut conn,
             bsky_session.did.as_str(),
@@ -110,6 +114,7 @@ pub async fn deactivate(
 
     let mut conn = pool.get().map_err(|e| AppError::PoolError(e.to_string()))?;
     deactivate_profile(&mut conn, user_id.as_str())?;
+    delete_profile_session(&mut conn, user_id.as_str())?;
     session.delete().await.ok();
     Ok(StatusCode::OK.into_response())
 }